@@ -1,9 +1,11 @@
 // Standard library
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs::{create_dir_all, File};
 use std::io::{stdin, BufReader, BufWriter, Cursor};
 use std::path::Path;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 // Other crates
 use anyhow::anyhow;
@@ -36,6 +38,7 @@ const CONN_CONF_FILE: MultipathRef = MultipathRef::new(&[
 const SNOWBOY_DATA_PATH: PathRef = PathRef::own("hotword");
 pub const HOTWORD_CHECK_INTERVAL_MS: u16 = 20; // Larger = less CPU, more wait time
 pub const ACTIVE_LISTENING_INTERVAL_MS: u16 = 200;
+pub const FOLLOW_UP_WAIT_MS: u64 = 8000; // How much to wait for the user to start a follow-up
 enum ProgState<'a> {
     PasiveListening,
     ActiveListening(AsyncMGuard<'a, RecDevice>),
@@ -75,6 +78,7 @@ async fn receive(
     my_name: &str,
     _rec_dev: Rc<AsyncMutex<RecDevice>>,
     conf_change: watch::Sender<ClientConf>,
+    keep_listening: mpsc::Sender<()>,
     client: Rc<RefCell<AsyncClient>>,
     eloop: &mut EventLoop,
 ) -> anyhow::Result<()> {
@@ -120,11 +124,13 @@ async fn receive(
                             })
                             .unwrap_or_else(|| Err(anyhow!("Expected audio for this client")))?;
                     }
-                    /*_ if topic.ends_with("/session_end") => {
-                        debug!("Received msg from server");
-                        let msg: MsgAnswer = decode::from_read(std::io::Cursor::new(pub_msg.payload))?;
-
-                    }*/
+                    _ if topic.ends_with("/session_end") => {
+                        debug!("Session ended");
+                    }
+                    _ if topic.ends_with("/keep_listening") => {
+                        debug!("Server expects a follow-up");
+                        keep_listening.send(()).await?;
+                    }
                     _ => {}
                 }
             }
@@ -138,6 +144,7 @@ async fn user_listen(
     mqtt_name: &str,
     rec_dev: Rc<AsyncMutex<RecDevice>>,
     mut config: watch::Receiver<ClientConf>,
+    mut keep_listening: mpsc::Receiver<()>,
     client: Rc<RefCell<AsyncClient>>,
 ) -> anyhow::Result<()> {
     let snowboy_path = SNOWBOY_DATA_PATH.resolve();
@@ -183,6 +190,9 @@ async fn user_listen(
     let (key_channel_in, mut key_channel_out) = mpsc::channel::<()>(1);
     let _waiter_thread = std::thread::spawn(move || wait_key(key_channel_in, Key::Char('l')));
 
+    // When listening for a follow-up, the moment we started doing so
+    let mut follow_up_start: Option<Instant> = None;
+
     loop {
         let interval = if current_state == ProgState::PasiveListening {
             HOTWORD_CHECK_INTERVAL_MS
@@ -211,7 +221,7 @@ async fn user_listen(
 
                                 debug!("I'm listening for your command");
 
-                                let msg_pack = encode::to_vec(&MsgEvent{satellite: mqtt_name.to_string(), event: "init_reco".into()})?;
+                                let msg_pack = encode::to_vec(&MsgEvent{satellite: mqtt_name.to_string(), event: "init_reco".into(), data: HashMap::new()})?;
                                 client.borrow_mut().publish("lily/event", QoS::AtMostOnce, false, msg_pack).await?;
                             }
                         }
@@ -223,9 +233,17 @@ async fn user_listen(
 
                         debug!("I'm listening for your command");
 
-                        let msg_pack = encode::to_vec(&MsgEvent{satellite: mqtt_name.to_string(), event: "init_reco".into()})?;
+                        let msg_pack = encode::to_vec(&MsgEvent{satellite: mqtt_name.to_string(), event: "init_reco".into(), data: HashMap::new()})?;
                         client.borrow_mut().publish("lily/event", QoS::AtMostOnce, false, msg_pack).await?;
                     }
+
+                    _ = keep_listening.recv() => {
+                        // No hotword needed, the user is already talking with us
+                        current_state = ProgState::ActiveListening(rec_guard);
+                        follow_up_start = Some(Instant::now());
+
+                        debug!("I'm listening for your follow-up");
+                    }
                 }
             }
             ProgState::ActiveListening(ref mut rec_guard) => {
@@ -245,8 +263,18 @@ async fn user_listen(
                         .unwrap();
                 }
                 match act_listener.process(mic_data)? {
-                    ActiveState::NoOneTalking => {}
+                    ActiveState::NoOneTalking => {
+                        let waited_too_much = follow_up_start.map_or(false, |start| {
+                            start.elapsed() > Duration::from_millis(FOLLOW_UP_WAIT_MS)
+                        });
+                        if waited_too_much {
+                            debug!("No follow-up, back to waiting for the hotword");
+                            follow_up_start = None;
+                            current_state = ProgState::PasiveListening;
+                        }
+                    }
                     ActiveState::Hearing(data) => {
+                        follow_up_start = None;
                         send_audio(mqtt_name, client.clone(), data, false).await?
                     }
                     ActiveState::Done(data) => {
@@ -344,6 +372,12 @@ pub async fn main() -> anyhow::Result<()> {
             QoS::AtMostOnce,
         )
         .await?;
+    client
+        .subscribe(
+            &format!("lily/{}/keep_listening", mqtt_conn.name),
+            QoS::AtMostOnce,
+        )
+        .await?;
     client
         .subscribe("lily/satellite_welcome", QoS::AtMostOnce)
         .await?;
@@ -352,16 +386,24 @@ pub async fn main() -> anyhow::Result<()> {
     info!("Mqtt connection made");
 
     let (conf_change_tx, conf_change_rx) = watch::channel(ClientConf::default());
+    let (keep_listening_tx, keep_listening_rx) = mpsc::channel(1);
     let rec_dev = Rc::new(AsyncMutex::new(RecDevice::new()));
     try_join!(
         receive(
             &mqtt_conn.name,
             rec_dev.clone(),
             conf_change_tx,
+            keep_listening_tx,
             client_share.clone(),
             &mut eloop
         ),
-        user_listen(&mqtt_conn.name, rec_dev, conf_change_rx, client_share,)
+        user_listen(
+            &mqtt_conn.name,
+            rec_dev,
+            conf_change_rx,
+            keep_listening_rx,
+            client_share,
+        )
     )?;
 
     Ok(())
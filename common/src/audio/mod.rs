@@ -148,6 +148,23 @@ impl Audio {
         }
     }
 
+    /// Transform into a WAV file, only raw and Ogg Opus audios can be
+    pub fn into_wav(self) -> Result<Vec<u8>, AudioError> {
+        match self.buffer {
+            Data::Raw(audio_raw) => Ok(audio_raw.to_wav()),
+            Data::Encoded(vec_data) => {
+                if ogg_opus::is_ogg_opus(std::io::Cursor::new(&vec_data.data)) {
+                    let (buffer, _) = ogg_opus::decode::<_, DEFAULT_SAMPLES_PER_SECOND>(
+                        std::io::Cursor::new(vec_data.data),
+                    )?;
+                    Ok(AudioRaw::new_raw(buffer, DEFAULT_SAMPLES_PER_SECOND).to_wav())
+                } else {
+                    Err(AudioError::UnsupportedFormat)
+                }
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
         self.buffer.clear();
     }
@@ -227,6 +244,84 @@ impl AudioRaw {
     pub fn to_ogg_opus(&self) -> Result<Vec<u8>, AudioError> {
         Ok(encode::<16000, 1>(&self.buffer)?)
     }
+
+    /// Read a 16 bits PCM WAV file, multiple channels are mixed into one
+    pub fn from_wav(wav: &[u8]) -> Result<Self, AudioError> {
+        if wav.len() < 12 || &wav[0..4] != b"RIFF" || &wav[8..12] != b"WAVE" {
+            return Err(AudioError::InvalidWav);
+        }
+
+        let mut format = None;
+        let mut pos = 12;
+        while pos + 8 <= wav.len() {
+            let id = &wav[pos..pos + 4];
+            let size = u32::from_le_bytes([wav[pos + 4], wav[pos + 5], wav[pos + 6], wav[pos + 7]])
+                as usize;
+            let body = &wav[pos + 8..wav.len().min(pos + 8 + size)];
+
+            match id {
+                b"fmt " if body.len() >= 16 => {
+                    let channels = u16::from_le_bytes([body[2], body[3]]);
+                    let sps = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                    let bits = u16::from_le_bytes([body[14], body[15]]);
+                    format = Some((channels, sps, bits));
+                }
+                b"data" => {
+                    let (channels, sps, bits) = format.ok_or(AudioError::InvalidWav)?;
+                    if bits != 16 || channels == 0 {
+                        return Err(AudioError::UnsupportedFormat);
+                    }
+                    if sps != Self::get_samples_per_second() {
+                        return Err(AudioError::IncompatibleSps);
+                    }
+
+                    let buffer = body
+                        .chunks_exact(2 * channels as usize)
+                        .map(|frame| {
+                            let sum: i32 = frame
+                                .chunks_exact(2)
+                                .map(|s| i16::from_le_bytes([s[0], s[1]]) as i32)
+                                .sum();
+                            (sum / channels as i32) as i16
+                        })
+                        .collect();
+                    return Ok(Self { buffer });
+                }
+                _ => {}
+            }
+
+            // Chunks are padded to an even size
+            pos += 8 + size + (size % 2);
+        }
+
+        Err(AudioError::InvalidWav)
+    }
+
+    /// Write as a 16 bits mono PCM WAV file
+    pub fn to_wav(&self) -> Vec<u8> {
+        let sps = Self::get_samples_per_second();
+        let data_len = (self.buffer.len() * 2) as u32;
+
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1u16.to_le_bytes()); // Mono
+        wav.extend_from_slice(&sps.to_le_bytes());
+        wav.extend_from_slice(&(sps * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in &self.buffer {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        wav
+    }
 }
 
 #[derive(Error, Debug)]
@@ -239,4 +334,10 @@ pub enum AudioError {
 
     #[error("")]
     OggOpusError(#[from] ogg_opus::Error),
+
+    #[error("Not a valid WAV file")]
+    InvalidWav,
+
+    #[error("Unsupported audio format")]
+    UnsupportedFormat,
 }
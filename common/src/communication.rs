@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
 use crate::other::ConnectionConf;
@@ -23,6 +24,8 @@ fn no_vec() -> Option<Vec<u8>> {
 #[derive(Debug, Deserialize, Serialize)]
 pub enum RequestData {
     Audio { data: Vec<u8>, is_final: bool },
+    // Already decoded audio, at the default samples per second
+    RawAudio { data: Vec<i16>, is_final: bool },
     Text(String),
 }
 #[derive(Debug, Deserialize, Serialize)]
@@ -43,6 +46,24 @@ pub struct MsgWelcome {
 pub struct MsgEvent {
     pub satellite: String,
     pub event: String,
+    // Anything that comes with the event, missing in older satellites
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+}
+
+/// Call an intent right away, without going through STT nor NLU
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MsgTriggerIntent {
+    // Either "skill/intent" or just the intent if no other skill has one
+    // with that name
+    pub intent: String,
+    #[serde(default)]
+    pub slots: HashMap<String, String>,
+    // Where to answer, all satellites if there's none
+    #[serde(default)]
+    pub satellite: Option<String>,
+    #[serde(default)]
+    pub lang: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
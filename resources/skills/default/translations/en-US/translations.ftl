@@ -22,7 +22,7 @@ order_repeat = Repeat ({$what_to_repeat})
 data1 = garage
 data2 = room
 
-say_repeat = I Repeat {$what_to_say}
+say_repeat = I Repeat {$what_to_repeat}
 
 lily_unknown = Ok, I didn't get that
     .a_1 = Are you sure I can do that?
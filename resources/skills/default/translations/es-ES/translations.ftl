@@ -1,28 +1,28 @@
 order_say_hello = Hola
-.a_1 = Di hola
+    .a_1 = Di hola
 
 say_hello_i18n = Hola, querido
-.a_1 = Como estás?
+    .a_1 = Como estás?
 
 lily_start = Lily lista para la acción
     .a_1 = Una vez más, estoy lista
 
 order_say_name = Dime tu nombre
-.a_1 = Di tu nombre
-.a_2 = Como te llamas
-.a_3 = Quien eres
+    .a_1 = Di tu nombre
+    .a_2 = Como te llamas
+    .a_3 = Quien eres
 
 say_name = Mi nombre es lily
 
 order_say_time = Que hora es
-.a_1 = Dime la hora
+    .a_1 = Dime la hora
 
 time_format = Son las %H y %M
 
 order_say_date = Que dia és
     .a_1 = A qué fecha estamos
     .a_2 = Qué día es hoy
-.a_3 = A qué día estamos
+    .a_3 = A qué día estamos
 
 date_format = Hoy es %A %-d de %B
 
@@ -0,0 +1,65 @@
+query_failed = I couldn't get that information
+    .a_1 = Sorry, something went wrong while looking that up
+query_empty = There's nothing to tell
+
+slot_prompt = I need a bit more information, could you tell me?
+    .a_1 = Something is missing, could you be more specific?
+
+slot_filling_failed = Sorry, I didn't get it, let's leave it for now
+    .a_1 = I still don't get it, we can try again later
+
+signal_set = Done, I will let you know
+    .a_1 = Okay, it is set
+
+timer_ring = Time is up!
+    .a_1 = Your timer is done
+
+timer_missed = A timer went off while I was turned off
+    .a_1 = I missed a timer while I was off, sorry
+
+timers_none = There are no timers set by voice
+    .a_1 = You don't have any timer set by voice
+
+timers_list = You have {$count} timers set by voice: {$timers}
+
+timer_unnamed = a timer
+
+timer_entry_hours = {$label}, {$amount} hours left
+timer_entry_minutes = {$label}, {$amount} minutes left
+timer_entry_seconds = {$label}, {$amount} seconds left
+timer_entry_paused = {$label}, paused
+
+timer_which = Which timer?
+    .a_1 = There are several timers, which one?
+
+timer_not_found = I couldn't find that timer, only those set by voice can be changed
+
+timer_cancelled = Timer cancelled
+    .a_1 = Done, it's cancelled
+
+timer_paused = Timer paused
+
+timer_resumed = Timer resumed
+
+timer_snoozed = Ok, I'll remind you again in {$minutes} minutes
+
+timer_nothing_to_snooze = There's nothing to snooze
+
+## Words to understand timers, each one is a comma separated list
+time_unit_seconds = s, sec, secs, second, seconds
+time_unit_minutes = m, min, mins, minute, minutes
+time_unit_hours = h, hr, hrs, hour, hours
+time_half = half
+time_numbers = a=1, an=1, one=1, two=2, three=3, four=4, five=5, six=6, seven=7, eight=8, nine=9, ten=10, eleven=11, twelve=12, fifteen=15, twenty=20, thirty=30, forty=40, fifty=50, sixty=60, ninety=90
+time_every_day = every day, everyday, daily
+time_weekdays = weekday
+time_weekend = weekend
+time_monday = mon, monday, mondays
+time_tuesday = tue, tues, tuesday, tuesdays
+time_wednesday = wed, wednesday, wednesdays
+time_thursday = thu, thurs, thursday, thursdays
+time_friday = fri, friday, fridays
+time_saturday = sat, saturday, saturdays
+time_sunday = sun, sunday, sundays
+time_am = am
+time_pm = pm
@@ -0,0 +1,65 @@
+query_failed = No he podido obtener esa información
+    .a_1 = Lo siento, algo ha fallado mientras lo buscaba
+query_empty = No hay nada que contar
+
+slot_prompt = Necesito algo más de información, ¿me la dices?
+    .a_1 = Falta algo, ¿puedes ser más concreto?
+
+slot_filling_failed = Lo siento, no lo he entendido, lo dejamos para otro momento
+    .a_1 = Sigo sin entenderlo, podemos intentarlo más tarde
+
+signal_set = Hecho, ya te avisaré
+    .a_1 = Vale, queda programado
+
+timer_ring = ¡Se acabó el tiempo!
+    .a_1 = Tu temporizador ha terminado
+
+timer_missed = Un temporizador sonó mientras el sistema estaba apagado
+    .a_1 = Se pasó un temporizador mientras el sistema estaba apagado, lo siento
+
+timers_none = No hay temporizadores puestos por voz
+    .a_1 = No tienes ningún temporizador puesto por voz
+
+timers_list = Tienes {$count} temporizadores puestos por voz: {$timers}
+
+timer_unnamed = un temporizador
+
+timer_entry_hours = {$label}, quedan {$amount} horas
+timer_entry_minutes = {$label}, quedan {$amount} minutos
+timer_entry_seconds = {$label}, quedan {$amount} segundos
+timer_entry_paused = {$label}, en pausa
+
+timer_which = ¿Qué temporizador?
+    .a_1 = Hay varios temporizadores, ¿cuál?
+
+timer_not_found = No he encontrado ese temporizador, solo se pueden cambiar los puestos por voz
+
+timer_cancelled = Temporizador cancelado
+    .a_1 = Hecho, está cancelado
+
+timer_paused = Temporizador en pausa
+
+timer_resumed = Temporizador reanudado
+
+timer_snoozed = Vale, te lo recuerdo otra vez en {$minutes} minutos
+
+timer_nothing_to_snooze = No hay nada que posponer
+
+## Palabras para entender temporizadores, cada una es una lista separada por comas
+time_unit_seconds = s, seg, segundo, segundos
+time_unit_minutes = m, min, minuto, minutos
+time_unit_hours = h, hora, horas
+time_half = media, medio
+time_numbers = un=1, una=1, uno=1, dos=2, tres=3, cuatro=4, cinco=5, seis=6, siete=7, ocho=8, nueve=9, diez=10, once=11, doce=12, quince=15, veinte=20, treinta=30, cuarenta=40, cincuenta=50, sesenta=60, noventa=90
+time_every_day = todos los días, a diario
+time_weekdays = entre semana, laborable
+time_weekend = fin de semana, fines de semana
+time_monday = lunes
+time_tuesday = martes
+time_wednesday = miércoles, miercoles
+time_thursday = jueves
+time_friday = viernes
+time_saturday = sábado, sábados, sabado, sabados
+time_sunday = domingo, domingos
+time_am = am
+time_pm = pm, de la tarde
@@ -0,0 +1,36 @@
+order_set_timer = Set a timer for {$duration}
+    .a_1 = Set a {$label} timer for {$duration}
+    .a_2 = Start a timer of {$duration}
+    .a_3 = Set a timer for the {$label} for {$duration}
+    .a_4 = Let me know in {$duration}
+
+order_list_timers = What timers do I have
+    .a_1 = How much time is left
+    .a_2 = List my timers
+
+order_cancel_timer = Cancel the timer
+    .a_1 = Cancel the {$label} timer
+    .a_2 = Stop the {$label} timer
+    .a_3 = Delete the timer for the {$label}
+
+order_pause_timer = Pause the timer
+    .a_1 = Pause the {$label} timer
+
+order_resume_timer = Resume the timer
+    .a_1 = Resume the {$label} timer
+    .a_2 = Continue the {$label} timer
+
+order_snooze_timer = Snooze
+    .a_1 = Snooze for {$duration}
+    .a_2 = Remind me again in {$duration}
+
+set_timer_prompt = For how long?
+
+duration_1 = 5 minutes
+duration_2 = an hour
+duration_3 = 30 seconds
+duration_4 = an hour and a half
+
+label_1 = pasta
+label_2 = laundry
+label_3 = eggs
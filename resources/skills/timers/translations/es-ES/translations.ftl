@@ -0,0 +1,34 @@
+order_set_timer = Pon un temporizador de {$duration}
+    .a_1 = Pon un temporizador para {$label} de {$duration}
+    .a_2 = Avísame en {$duration}
+    .a_3 = Pon un temporizador de {$duration} para {$label}
+
+order_list_timers = Qué temporizadores tengo
+    .a_1 = Cuánto tiempo queda
+    .a_2 = Dime mis temporizadores
+
+order_cancel_timer = Cancela el temporizador
+    .a_1 = Cancela el temporizador de {$label}
+    .a_2 = Quita el temporizador de {$label}
+
+order_pause_timer = Pausa el temporizador
+    .a_1 = Pausa el temporizador de {$label}
+
+order_resume_timer = Reanuda el temporizador
+    .a_1 = Reanuda el temporizador de {$label}
+    .a_2 = Continúa el temporizador de {$label}
+
+order_snooze_timer = Pospón la alarma
+    .a_1 = Pospón la alarma {$duration}
+    .a_2 = Recuérdamelo otra vez en {$duration}
+
+set_timer_prompt = ¿Por cuánto tiempo?
+
+duration_1 = 5 minutos
+duration_2 = una hora
+duration_3 = 30 segundos
+duration_4 = una hora y media
+
+label_1 = la pasta
+label_2 = la colada
+label_3 = los huevos
@@ -1,6 +1,9 @@
 // Standard library
 use std::collections::HashMap;
 
+// This crate
+use crate::nlu::SlotValue;
+
 /** A struct holding all the data for a skill to answer a user request. Note that
  * is it made in a way that would resemble an output JSON-like message */
 pub struct ActionContext {
@@ -11,11 +14,25 @@ pub struct ActionContext {
 
 pub struct SatelliteData {
     pub uuid: String,
+    pub session: Option<SessionData>,
+}
+
+/// The dialogue session the satellite is in, if any
+#[derive(Clone, Debug)]
+pub struct SessionData {
+    pub id: String,
+    pub custom_data: Option<String>,
 }
 
 pub enum ContextData {
-    Event { event: String },
-    Intent { intent: IntentData },
+    // Data is whatever came with the event (e.g: an MQTT topic and payload)
+    Event {
+        event: String,
+        data: HashMap<String, String>,
+    },
+    Intent {
+        intent: IntentData,
+    },
 }
 
 impl ContextData {
@@ -25,11 +42,30 @@ impl ContextData {
             _ => None,
         }
     }
+
+    /// The slots of an intent (as text) or the data of an event
+    pub fn values(&self) -> HashMap<String, String> {
+        match self {
+            ContextData::Intent { intent } => intent.slot_texts(),
+            ContextData::Event { data, .. } => data.clone(),
+        }
+    }
 }
 
 pub struct IntentData {
     pub input: String,
     pub name: String,
     pub confidence: f32,
-    pub slots: HashMap<String, String>,
+    pub slots: HashMap<String, SlotValue>,
+}
+
+impl IntentData {
+    /// For those which only take text, see `SlotValue`'s `Display` for how
+    /// each kind of value is written
+    pub fn slot_texts(&self) -> HashMap<String, String> {
+        self.slots
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_string()))
+            .collect()
+    }
 }
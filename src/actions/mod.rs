@@ -3,6 +3,7 @@ mod action_context;
 pub use self::action_context::*;
 
 // Standard library
+use std::collections::HashMap;
 use std::fmt; // For Debug in LocalActionRegistry
 use std::fs::{self, File};
 use std::io::Read;
@@ -12,13 +13,16 @@ use std::sync::{Arc, Mutex, Weak};
 // This crate
 use crate::collections::BaseRegistry;
 use crate::exts::LockIt;
+use crate::skills::translations::get_translations;
 
 // Other crates
 use anyhow::Result;
 use async_trait::async_trait;
+use fluent_bundle::FluentArgs;
 use lazy_static::lazy_static;
 use lily_common::audio::Audio;
 use log::error;
+use unic_langid::LanguageIdentifier;
 
 pub type ActionRegistry = BaseRegistry<dyn Action + Send>;
 pub type ActionItem = Arc<Mutex<dyn Action + Send>>;
@@ -31,6 +35,8 @@ lazy_static! {
 pub enum MainAnswer {
     Sound(Audio),
     Text(String),
+    // Nothing to say, the answer just ends (or keeps) the session
+    Nothing,
 }
 
 #[derive(Clone)]
@@ -57,6 +63,57 @@ impl ActionAnswer {
             should_end_session: end_session,
         })
     }
+
+    pub fn nothing(end_session: bool) -> Result<Self> {
+        Ok(Self {
+            answer: MainAnswer::Nothing,
+            should_end_session: end_session,
+        })
+    }
+}
+
+/// Builds a text answer from a skill's translations. One of the variants of
+/// the message is picked at random, and the slots of the intent (or the data
+/// of the event) can be used as arguments inside of it.
+pub struct TranslatedAnswer<'a> {
+    skill_name: &'a str,
+    msg_id: &'a str,
+    args: HashMap<String, String>,
+}
+
+impl<'a> TranslatedAnswer<'a> {
+    pub fn new(skill_name: &'a str, msg_id: &'a str) -> Self {
+        Self {
+            skill_name,
+            msg_id,
+            args: HashMap::new(),
+        }
+    }
+
+    /// Add an argument, it will take precedence over a slot with the same name
+    pub fn arg(mut self, name: &str, value: String) -> Self {
+        self.args.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn build(self, context: &ActionContext, end_session: bool) -> Result<ActionAnswer> {
+        let lang: LanguageIdentifier = context.locale.parse()?;
+
+        let mut all_args = context.data.values();
+        all_args.extend(self.args);
+
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in all_args {
+            fluent_args.set(name, value);
+        }
+
+        let text = get_translations(self.skill_name)?.get_random(
+            &lang,
+            self.msg_id,
+            Some(&fluent_args),
+        )?;
+        ActionAnswer::send_text(text, end_session)
+    }
 }
 
 #[async_trait(?Send)]
@@ -106,15 +163,21 @@ impl ActionSet {
         self.acts.push(action);
     }
 
+    pub fn extend(&mut self, other: ActionSet) {
+        self.acts.extend(other.acts);
+    }
+
     pub async fn call_all(&self, context: &ActionContext) -> Vec<ActionAnswer> {
         let mut res = Vec::new();
-        for action in &self.acts {
-            match action.upgrade().unwrap().lock_it().call(context).await {
+        // Actions of unregistered skills are gone, just skip them
+        for action in self.acts.iter().filter_map(Weak::upgrade) {
+            let mut action = action.lock_it();
+            match action.call(context).await {
                 Ok(a) => res.push(a),
                 Err(e) => {
                     error!(
                         "Action {} failed while being triggered: {}",
-                        &action.upgrade().unwrap().lock_it().get_name(),
+                        &action.get_name(),
                         e
                     );
                 }
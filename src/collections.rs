@@ -9,7 +9,7 @@ use std::iter::IntoIterator;
 use std::sync::{Arc, Mutex};
 
 // This crate
-use crate::vars::mangle;
+use crate::vars::{is_mangled_from, mangle};
 
 // Other crates
 use anyhow::{anyhow, Result};
@@ -78,6 +78,14 @@ impl<A: ?std::marker::Sized> BaseRegistry<A> {
         }
     }
 
+    /// Remove every item of a skill, returns how many were removed
+    pub fn remove_skill(&mut self, skill_name: &str) -> usize {
+        let prev_len = self.map.len();
+        self.map
+            .retain(|mangled, _| !is_mangled_from(skill_name, mangled));
+        prev_len - self.map.len()
+    }
+
     delegate! {to self.map {
         pub fn iter_mut(&mut self) -> IterMut<String,Arc<Mutex<A>>>;
         #[call(remove)]
@@ -4,6 +4,9 @@ use std::collections::HashMap;
 use std::rc::Rc;
 
 // This crate
+use crate::nlu::NluConf;
+use crate::signals::{TimersConf, WebhookConf};
+use crate::skills::hermes::HermesConf;
 use crate::stt::SttData;
 use crate::tts::TtsData;
 use crate::vars::MAIN_CONF_PATH;
@@ -37,6 +40,18 @@ pub struct Config {
     #[serde(default)]
     pub mqtt: ConnectionConf,
 
+    #[serde(default)]
+    pub hermes: HermesConf,
+
+    #[serde(default)]
+    pub nlu: NluConf,
+
+    #[serde(default)]
+    pub timers: TimersConf,
+
+    #[serde(default)]
+    pub webhook: WebhookConf,
+
     #[serde(flatten)]
     pub skills_conf: HashMap<String, Value>,
 }
@@ -54,6 +69,10 @@ impl Default for Config {
             debug_record_active_speech: false,
             skills_conf: HashMap::new(),
             mqtt: ConnectionConf::default(),
+            hermes: HermesConf::default(),
+            nlu: NluConf::default(),
+            timers: TimersConf::default(),
+            webhook: WebhookConf::default(),
             tts: TtsData::default(),
         }
     }
@@ -41,12 +41,6 @@ fn get_locale_default() -> String {
 
 #[tokio::main(flavor = "current_thread")]
 pub async fn main() -> Result<()> {
-    // Set explicit handle for Ctrl-C signal
-    ctrlc::set_handler(move || {
-        std::process::exit(0);
-    })
-    .expect("Error setting Ctrl-C handler");
-
     set_app_name("lily");
     init_log();
 
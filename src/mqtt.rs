@@ -2,12 +2,15 @@ use std::sync::{Arc, Mutex};
 
 use crate::config::Config;
 use crate::signals::mqtt::{MqttInterfaceIn, MqttInterfaceOut};
+use crate::exts::LockIt;
 use crate::signals::order::dev_mgmt::SessionManager;
-use crate::skills::hermes::{HermesApiIn, HermesApiOut};
+use crate::signals::TOPIC_SIGNAL;
+use crate::skills::hermes::{HermesApiIn, HermesApiOut, NluQueryMessage};
 use crate::tts::TtsData;
 
 use anyhow::Result;
 use lily_common::communication::*;
+use log::error;
 use rumqttc::{AsyncClient, Event, EventLoop, Packet};
 use tokio::{sync::mpsc, try_join};
 use unic_langid::LanguageIdentifier;
@@ -16,9 +19,13 @@ pub struct MqttApi {
     api_out: MqttApiOut,
 }
 impl MqttApi {
-    pub fn new(def_lang: LanguageIdentifier) -> Result<Self> {
+    pub fn new(
+        def_lang: LanguageIdentifier,
+        config: &Config,
+        nlu_queries: mpsc::Sender<NluQueryMessage>,
+    ) -> Result<Self> {
         Ok(Self {
-            api_in: MqttApiIn::new(def_lang),
+            api_in: MqttApiIn::new(def_lang, config, nlu_queries),
             api_out: MqttApiOut::new()?,
         })
     }
@@ -30,14 +37,21 @@ impl MqttApi {
         sessions: Arc<Mutex<SessionManager>>,
         channel_nlu: mpsc::Sender<MsgRequest>,
         channel_event: mpsc::Sender<MsgEvent>,
+        channel_trigger: mpsc::Sender<MsgTriggerIntent>,
     ) -> Result<()> {
         let mqtt_conf = ConnectionConfResolved::from(config.mqtt.clone(), || "lily-server".into());
         let (client_raw, eloop) = make_mqtt_conn(&mqtt_conf, None)?;
         let client = Arc::new(Mutex::new(client_raw));
 
-        let i = self
-            .api_in
-            .handle(eloop, client.clone(), config, channel_nlu, channel_event);
+        let i = self.api_in.handle(
+            eloop,
+            client.clone(),
+            config,
+            sessions.clone(),
+            channel_nlu,
+            channel_event,
+            channel_trigger,
+        );
         let o = self
             .api_out
             .handle(curr_langs, &config.tts, def_lang, sessions, client);
@@ -53,9 +67,13 @@ pub struct MqttApiIn {
 }
 
 impl MqttApiIn {
-    fn new(def_lang: LanguageIdentifier) -> Self {
+    fn new(
+        def_lang: LanguageIdentifier,
+        config: &Config,
+        nlu_queries: mpsc::Sender<NluQueryMessage>,
+    ) -> Self {
         Self {
-            hermes_in: HermesApiIn::new(def_lang),
+            hermes_in: HermesApiIn::new(def_lang, &config.hermes, nlu_queries),
             satellite_server_in: MqttInterfaceIn::new(),
         }
     }
@@ -64,11 +82,16 @@ impl MqttApiIn {
         mut eloop: EventLoop,
         client: Arc<Mutex<AsyncClient>>,
         config: &Config,
+        sessions: Arc<Mutex<SessionManager>>,
         channel_nlu: mpsc::Sender<MsgRequest>,
         channel_event: mpsc::Sender<MsgEvent>,
+        channel_trigger: mpsc::Sender<MsgTriggerIntent>,
     ) -> Result<()> {
         MqttInterfaceIn::subscribe(&client).await?;
         HermesApiIn::subscribe(&client).await?;
+        if let Some(topics) = TOPIC_SIGNAL.lock_it().clone() {
+            topics.lock_it().connected(client.clone())?;
+        }
 
         loop {
             match eloop.poll().await? {
@@ -86,9 +109,22 @@ impl MqttApiIn {
                                 .await?;
                         }
                         "lily/event" => {
-                            self.satellite_server_in
+                            if let Err(e) = self
+                                .satellite_server_in
                                 .handle_event(&pub_msg.payload, &channel_event)
-                                .await?;
+                                .await
+                            {
+                                error!("Failed to handle event: {}", e);
+                            }
+                        }
+                        "lily/intent/trigger" => {
+                            if let Err(e) = self
+                                .satellite_server_in
+                                .handle_intent_trigger(&pub_msg.payload, &channel_trigger)
+                                .await
+                            {
+                                error!("Failed to handle intent trigger: {}", e);
+                            }
                         }
                         "lily/disconnected" => {
                             self.satellite_server_in
@@ -100,7 +136,72 @@ impl MqttApiIn {
                         "hermes/tts/say" => {
                             self.hermes_in.handle_tts_say(&pub_msg.payload).await?;
                         }
-                        _ => {}
+                        "hermes/nlu/query" => {
+                            if let Err(e) = self.hermes_in.handle_nlu_query(&pub_msg.payload).await
+                            {
+                                error!("Failed to handle Hermes NLU query: {}", e);
+                            }
+                        }
+                        "hermes/dialogueManager/startSession" => {
+                            if let Err(e) = self
+                                .hermes_in
+                                .handle_start_session(&pub_msg.payload, &sessions)
+                                .await
+                            {
+                                error!("Failed to handle Hermes startSession: {}", e);
+                            }
+                        }
+                        "hermes/dialogueManager/endSession" => {
+                            if let Err(e) = self
+                                .hermes_in
+                                .handle_end_session(&pub_msg.payload, &sessions)
+                                .await
+                            {
+                                error!("Failed to handle Hermes endSession: {}", e);
+                            }
+                        }
+                        "hermes/dialogueManager/continueSession" => {
+                            if let Err(e) = self
+                                .hermes_in
+                                .handle_continue_session(&pub_msg.payload, &sessions)
+                                .await
+                            {
+                                error!("Failed to handle Hermes continueSession: {}", e);
+                            }
+                        }
+                        topic if topic.starts_with("hermes/hotword/") => {
+                            if let Err(e) = self
+                                .hermes_in
+                                .handle_hotword_detected(&pub_msg.payload)
+                                .await
+                            {
+                                error!("Failed to handle Hermes hotword detection: {}", e);
+                            }
+                        }
+                        topic if topic.starts_with("hermes/audioServer/") => {
+                            if let Err(e) = self
+                                .hermes_in
+                                .handle_audio_server(topic, &pub_msg.payload, &channel_nlu)
+                                .await
+                            {
+                                error!("Failed to handle Hermes audio server message: {}", e);
+                            }
+                        }
+                        "lily/hermes/register_intents" => {
+                            if let Err(e) = self
+                                .hermes_in
+                                .handle_register_intents(&pub_msg.payload)
+                                .await
+                            {
+                                error!("Failed to register Hermes intents: {}", e);
+                            }
+                        }
+                        // Anything else might be for a skill
+                        topic => {
+                            if let Some(topics) = TOPIC_SIGNAL.lock_it().clone() {
+                                topics.lock_it().on_message(topic, &pub_msg.payload);
+                            }
+                        }
                     }
                 }
                 _ => {}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::nlu::{
+    BuiltinNlu, BuiltinNluManager, EntityDef, FallbackNlu, FallbackNluManager, Nlu, NluContext,
+    NluManager, NluManagerStatic, NluResponse, NluUtterance, RasaNlu, RasaNluManager,
+    RasaServerConf, RasaServerNlu, RasaServerNluManager, SnipsNlu, SnipsNluManager,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use log::warn;
+use serde::Deserialize;
+use unic_langid::LanguageIdentifier;
+
+/// Config ////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NluBackend {
+    Snips,
+    Rasa,
+    // An already running Rasa, talked to through HTTP
+    RasaServer,
+    Builtin,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NluConf {
+    #[serde(default = "def_backend")]
+    pub backend: NluBackend,
+
+    // Languages which use another backend (e.g: "ca: builtin")
+    #[serde(default)]
+    pub languages: HashMap<String, NluBackend>,
+
+    #[serde(default)]
+    pub rasa_server: RasaServerConf,
+}
+
+fn def_backend() -> NluBackend {
+    NluBackend::Snips
+}
+
+impl Default for NluConf {
+    fn default() -> Self {
+        Self {
+            backend: def_backend(),
+            languages: HashMap::new(),
+            rasa_server: RasaServerConf::default(),
+        }
+    }
+}
+
+impl NluConf {
+    /// The backend for a language, an override for "es" is also used for
+    /// "es-ES" unless there's one just for "es-ES"
+    pub fn backend_for(&self, lang: &LanguageIdentifier) -> NluBackend {
+        let mut found = None;
+        for (name, backend) in &self.languages {
+            match name.parse::<LanguageIdentifier>() {
+                Ok(ref l) if l == lang => return *backend,
+                Ok(ref l) if l.language == lang.language && l.region.is_none() => {
+                    found = Some(*backend)
+                }
+                Ok(_) => {}
+                Err(_) => warn!("\"{}\" in the NLU's languages is not a language", name),
+            }
+        }
+
+        found.unwrap_or(self.backend)
+    }
+}
+
+//// NluManager ///////////////////////////////////////////////////////////////////////////////////
+
+type SnipsOrBuiltin = FallbackNluManager<SnipsNluManager, BuiltinNluManager>;
+type RasaOrBuiltin = FallbackNluManager<RasaNluManager, BuiltinNluManager>;
+type RasaServerOrBuiltin = FallbackNluManager<RasaServerNluManager, BuiltinNluManager>;
+
+/// Whichever NLU the config asked for, one is made for each language. All
+/// but the builtin one fall back to it when they can't be used.
+#[derive(Clone, Debug)]
+pub enum AnyNluManager {
+    Snips(SnipsOrBuiltin),
+    Rasa(RasaOrBuiltin),
+    RasaServer(RasaServerOrBuiltin),
+    Builtin(BuiltinNluManager),
+}
+
+impl AnyNluManager {
+    pub fn new(backend: NluBackend) -> Self {
+        match backend {
+            NluBackend::Snips => AnyNluManager::Snips(SnipsOrBuiltin::new()),
+            NluBackend::Rasa => AnyNluManager::Rasa(RasaOrBuiltin::new()),
+            NluBackend::RasaServer => AnyNluManager::RasaServer(RasaServerOrBuiltin::new()),
+            NluBackend::Builtin => AnyNluManager::Builtin(BuiltinNluManager::new()),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnyNluManager::Snips(_) => SnipsOrBuiltin::name(),
+            AnyNluManager::Rasa(_) => RasaOrBuiltin::name(),
+            AnyNluManager::RasaServer(_) => RasaServerOrBuiltin::name(),
+            AnyNluManager::Builtin(_) => BuiltinNluManager::name(),
+        }
+    }
+
+    pub fn is_lang_compatible(&self, lang: &LanguageIdentifier) -> bool {
+        match self {
+            AnyNluManager::Snips(_) => SnipsOrBuiltin::is_lang_compatible(lang),
+            AnyNluManager::Rasa(_) => RasaOrBuiltin::is_lang_compatible(lang),
+            AnyNluManager::RasaServer(_) => RasaServerOrBuiltin::is_lang_compatible(lang),
+            AnyNluManager::Builtin(_) => BuiltinNluManager::is_lang_compatible(lang),
+        }
+    }
+
+    pub fn get_paths(&self) -> (PathBuf, PathBuf) {
+        match self {
+            AnyNluManager::Snips(_) => SnipsNluManager::get_paths(),
+            AnyNluManager::Rasa(_) => RasaNluManager::get_paths(),
+            AnyNluManager::RasaServer(_) => RasaServerNluManager::get_paths(),
+            AnyNluManager::Builtin(_) => BuiltinNluManager::get_paths(),
+        }
+    }
+
+    pub fn entity(&self, name: &str) -> Option<&EntityDef> {
+        match self {
+            AnyNluManager::Snips(man) => man.entity(name),
+            AnyNluManager::Rasa(man) => man.entity(name),
+            AnyNluManager::RasaServer(man) => man.entity(name),
+            AnyNluManager::Builtin(man) => man.entity(name),
+        }
+    }
+}
+
+impl NluManager for AnyNluManager {
+    type NluType = AnyNlu;
+
+    fn ready_lang(&mut self, lang: &LanguageIdentifier) -> Result<()> {
+        match self {
+            AnyNluManager::Snips(man) => man.ready_lang(lang),
+            AnyNluManager::Rasa(man) => man.ready_lang(lang),
+            AnyNluManager::RasaServer(man) => man.ready_lang(lang),
+            AnyNluManager::Builtin(man) => man.ready_lang(lang),
+        }
+    }
+
+    fn add_intent(&mut self, order_name: &str, phrases: Vec<NluUtterance>) {
+        match self {
+            AnyNluManager::Snips(man) => man.add_intent(order_name, phrases),
+            AnyNluManager::Rasa(man) => man.add_intent(order_name, phrases),
+            AnyNluManager::RasaServer(man) => man.add_intent(order_name, phrases),
+            AnyNluManager::Builtin(man) => man.add_intent(order_name, phrases),
+        }
+    }
+
+    fn add_entity(&mut self, name: String, def: EntityDef) {
+        match self {
+            AnyNluManager::Snips(man) => man.add_entity(name, def),
+            AnyNluManager::Rasa(man) => man.add_entity(name, def),
+            AnyNluManager::RasaServer(man) => man.add_entity(name, def),
+            AnyNluManager::Builtin(man) => man.add_entity(name, def),
+        }
+    }
+
+    fn add_entity_value(&mut self, name: &str, value: String) -> Result<()> {
+        match self {
+            AnyNluManager::Snips(man) => man.add_entity_value(name, value),
+            AnyNluManager::Rasa(man) => man.add_entity_value(name, value),
+            AnyNluManager::RasaServer(man) => man.add_entity_value(name, value),
+            AnyNluManager::Builtin(man) => man.add_entity_value(name, value),
+        }
+    }
+
+    fn remove_skill(&mut self, skill_name: &str) {
+        match self {
+            AnyNluManager::Snips(man) => man.remove_skill(skill_name),
+            AnyNluManager::Rasa(man) => man.remove_skill(skill_name),
+            AnyNluManager::RasaServer(man) => man.remove_skill(skill_name),
+            AnyNluManager::Builtin(man) => man.remove_skill(skill_name),
+        }
+    }
+
+    fn train(
+        &self,
+        train_set_path: &Path,
+        engine_path: &Path,
+        lang: &LanguageIdentifier,
+    ) -> Result<AnyNlu> {
+        Ok(match self {
+            AnyNluManager::Snips(man) => {
+                AnyNlu::Snips(man.train(train_set_path, engine_path, lang)?)
+            }
+            AnyNluManager::Rasa(man) => {
+                AnyNlu::Rasa(man.train(train_set_path, engine_path, lang)?)
+            }
+            AnyNluManager::RasaServer(man) => {
+                AnyNlu::RasaServer(man.train(train_set_path, engine_path, lang)?)
+            }
+            AnyNluManager::Builtin(man) => {
+                AnyNlu::Builtin(man.train(train_set_path, engine_path, lang)?)
+            }
+        })
+    }
+}
+
+/// Nlu ////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum AnyNlu {
+    Snips(FallbackNlu<SnipsNlu, BuiltinNlu>),
+    Rasa(FallbackNlu<RasaNlu, BuiltinNlu>),
+    RasaServer(FallbackNlu<RasaServerNlu, BuiltinNlu>),
+    Builtin(BuiltinNlu),
+}
+
+#[async_trait(?Send)]
+impl Nlu for AnyNlu {
+    async fn parse(&self, input: &str) -> Result<NluResponse> {
+        match self {
+            AnyNlu::Snips(nlu) => nlu.parse(input).await,
+            AnyNlu::Rasa(nlu) => nlu.parse(input).await,
+            AnyNlu::RasaServer(nlu) => nlu.parse(input).await,
+            AnyNlu::Builtin(nlu) => nlu.parse(input).await,
+        }
+    }
+
+    async fn parse_with_context(&self, input: &str, context: &NluContext) -> Result<NluResponse> {
+        match self {
+            AnyNlu::Snips(nlu) => nlu.parse_with_context(input, context).await,
+            AnyNlu::Rasa(nlu) => nlu.parse_with_context(input, context).await,
+            AnyNlu::RasaServer(nlu) => nlu.parse_with_context(input, context).await,
+            AnyNlu::Builtin(nlu) => nlu.parse_with_context(input, context).await,
+        }
+    }
+}
@@ -0,0 +1,621 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::nlu::{
+    EntityData, EntityDef, Nlu, NluManager, NluManagerStatic, NluResponse, NluResponseSlot,
+    NluUtterance, SlotValue, SLOT_RE,
+};
+use crate::vars::is_mangled_from;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use unic_langid::{langid, LanguageIdentifier};
+
+// How alike two words must be to be taken as the same (0 to 1)
+const FUZZY_THRESHOLD: f32 = 0.75;
+// Words this short must be exactly the same, a typo is a whole other word
+const FUZZY_MIN_LEN: usize = 4;
+// What a word in the input that isn't in the sample costs
+const EXTRA_TOKEN_PENALTY: f32 = 0.5;
+// What a slot filled with anything (not a known value) is worth
+const WILDCARD_SCORE: f32 = 0.7;
+const MAX_WILDCARD_TOKENS: usize = 8;
+
+//// NluManager ///////////////////////////////////////////////////////////////////////////////////
+
+/// An NLU made in Rust, it matches the input against the samples themselves
+/// so it needs no training nor anything installed, though it is not as smart
+/// as the others.
+#[derive(Clone, Debug)]
+pub struct BuiltinNluManager {
+    intents: Vec<(String, Vec<NluUtterance>)>,
+    entities: HashMap<String, EntityDef>,
+}
+
+impl BuiltinNluManager {
+    /// An entity as it is now, with any value added to it after registering
+    pub fn entity(&self, name: &str) -> Option<&EntityDef> {
+        self.entities.get(name)
+    }
+}
+
+impl NluManager for BuiltinNluManager {
+    type NluType = BuiltinNlu;
+
+    fn ready_lang(&mut self, _lang: &LanguageIdentifier) -> Result<()> {
+        Ok(())
+    }
+
+    fn add_intent(&mut self, order_name: &str, phrases: Vec<NluUtterance>) {
+        // A skill registering again replaces what it had
+        self.intents.retain(|(name, _)| name != order_name);
+        self.intents.push((order_name.to_string(), phrases));
+    }
+
+    fn add_entity(&mut self, name: String, def: EntityDef) {
+        self.entities.insert(name, def);
+    }
+
+    fn add_entity_value(&mut self, name: &str, value: String) -> Result<()> {
+        let def = self
+            .entities
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Entity {} does not exist", name))?;
+        def.data.push(EntityData {
+            value,
+            synonyms: vec![],
+        });
+        Ok(())
+    }
+
+    fn remove_skill(&mut self, skill_name: &str) {
+        self.intents
+            .retain(|(name, _)| !is_mangled_from(skill_name, name));
+        self.entities
+            .retain(|name, _| !is_mangled_from(skill_name, name));
+    }
+
+    fn train(
+        &self,
+        _train_set_path: &Path,
+        _engine_path: &Path,
+        _lang: &LanguageIdentifier,
+    ) -> Result<BuiltinNlu> {
+        Ok(BuiltinNlu::new(&self.intents, &self.entities))
+    }
+}
+
+impl NluManagerStatic for BuiltinNluManager {
+    fn new() -> Self {
+        BuiltinNluManager {
+            intents: vec![],
+            entities: HashMap::new(),
+        }
+    }
+
+    // Only a hint, it doesn't know about languages at all
+    fn list_compatible_langs() -> Vec<LanguageIdentifier> {
+        vec![
+            langid!("de"),
+            langid!("en"),
+            langid!("es"),
+            langid!("fr"),
+            langid!("it"),
+            langid!("pt"),
+        ]
+    }
+
+    fn is_lang_compatible(_lang: &LanguageIdentifier) -> bool {
+        true
+    }
+
+    fn name() -> &'static str {
+        "Builtin"
+    }
+
+    // Nothing is written to disk
+    fn get_paths() -> (PathBuf, PathBuf) {
+        (PathBuf::new(), PathBuf::new())
+    }
+}
+
+/// Nlu ////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+enum Part {
+    Word(String),
+    Slot { name: String, entity: String },
+}
+
+#[derive(Debug)]
+struct EntityMatcher {
+    // Canonical value and every way of saying it, already tokenized
+    values: Vec<(String, Vec<Vec<String>>)>,
+    // Whether anything can fill it, not only the values known
+    extensible: bool,
+}
+
+impl EntityMatcher {
+    fn new(def: &EntityDef) -> Self {
+        let values = def
+            .data
+            .iter()
+            .map(|d| {
+                let variants = std::iter::once(&d.value)
+                    .chain(d.synonyms.iter())
+                    .map(|v| words(v))
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                (d.value.clone(), variants)
+            })
+            .collect();
+
+        Self {
+            values,
+            extensible: def.automatically_extensible,
+        }
+    }
+
+    // Entities we know nothing about (e.g: builtin ones) take anything
+    fn unknown() -> Self {
+        Self {
+            values: Vec::new(),
+            extensible: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BuiltinNlu {
+    intents: Vec<(String, Vec<Vec<Part>>)>,
+    entities: HashMap<String, EntityMatcher>,
+}
+
+impl BuiltinNlu {
+    fn new(intents: &[(String, Vec<NluUtterance>)], entities: &HashMap<String, EntityDef>) -> Self {
+        let mut matchers: HashMap<String, EntityMatcher> = entities
+            .iter()
+            .map(|(name, def)| (name.clone(), EntityMatcher::new(def)))
+            .collect();
+
+        let intents = intents
+            .iter()
+            .map(|(name, utts)| {
+                let templates = utts
+                    .iter()
+                    .map(|utt| {
+                        let parts = compile(utt);
+                        for part in &parts {
+                            if let Part::Slot { entity, .. } = part {
+                                matchers
+                                    .entry(entity.clone())
+                                    .or_insert_with(EntityMatcher::unknown);
+                            }
+                        }
+                        parts
+                    })
+                    .filter(|parts| !parts.is_empty())
+                    .collect();
+                (name.clone(), templates)
+            })
+            .collect();
+
+        Self {
+            intents,
+            entities: matchers,
+        }
+    }
+
+    fn match_template(&self, parts: &[Part], input: &str, tokens: &[Token]) -> Match {
+        let table = self.align(parts, tokens);
+
+        // Follow the best path to get the slots
+        let mut slots = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < parts.len() || j < tokens.len() {
+            match table[i][j].step {
+                Step::SkipInput => j += 1,
+                Step::SkipPart => i += 1,
+                Step::Word => {
+                    i += 1;
+                    j += 1;
+                }
+                Step::Slot { end, value } => {
+                    if let Part::Slot { name, entity } = &parts[i] {
+                        let range = tokens[j].range.start..tokens[end - 1].range.end;
+                        let raw = input[range.clone()].to_string();
+                        let value = match value {
+                            Some(index) => SlotValue::Entity {
+                                raw: raw.clone(),
+                                value: self.entities[entity].values[index].0.clone(),
+                            },
+                            None => SlotValue::Text(raw.clone()),
+                        };
+                        slots.push(NluResponseSlot {
+                            raw_value: raw,
+                            value,
+                            name: name.clone(),
+                            entity: Some(entity.clone()),
+                            range: Some(range),
+                        });
+                    }
+                    i += 1;
+                    j = end;
+                }
+            }
+        }
+
+        let best = &table[0][0];
+        let total = parts.len() as f32 + EXTRA_TOKEN_PENALTY * best.extra as f32;
+        Match {
+            confidence: if total > 0.0 {
+                best.gained / total
+            } else {
+                0.0
+            },
+            slots,
+        }
+    }
+
+    // Best alignment of the input with the sample, cell [i][j] is the best
+    // way of matching parts from i and tokens from j onwards
+    fn align(&self, parts: &[Part], tokens: &[Token]) -> Vec<Vec<Cell>> {
+        let mut table = vec![vec![Cell::default(); tokens.len() + 1]; parts.len() + 1];
+        for j in (0..tokens.len()).rev() {
+            table[parts.len()][j] = table[parts.len()][j + 1].then(Step::SkipInput, 0.0, 1);
+        }
+
+        for i in (0..parts.len()).rev() {
+            for j in (0..=tokens.len()).rev() {
+                let mut best = table[i + 1][j].then(Step::SkipPart, 0.0, 0);
+                if j < tokens.len() {
+                    best = best.max(table[i][j + 1].then(Step::SkipInput, 0.0, 1));
+                }
+
+                match &parts[i] {
+                    Part::Word(word) => {
+                        if j < tokens.len() {
+                            let sim = similarity(word, &tokens[j].text);
+                            if sim >= FUZZY_THRESHOLD {
+                                best = best.max(table[i + 1][j + 1].then(Step::Word, sim, 0));
+                            }
+                        }
+                    }
+                    Part::Slot { entity, .. } => {
+                        let matcher = &self.entities[entity];
+                        for (index, (_, variants)) in matcher.values.iter().enumerate() {
+                            for variant in variants {
+                                if let Some(sim) = match_words(variant, &tokens[j..]) {
+                                    let step = Step::Slot {
+                                        end: j + variant.len(),
+                                        value: Some(index),
+                                    };
+                                    best = best
+                                        .max(table[i + 1][j + variant.len()].then(step, sim, 0));
+                                }
+                            }
+                        }
+
+                        if matcher.extensible {
+                            let max_end = tokens.len().min(j + MAX_WILDCARD_TOKENS);
+                            for end in (j + 1)..=max_end {
+                                let step = Step::Slot { end, value: None };
+                                best = best.max(table[i + 1][end].then(step, WILDCARD_SCORE, 0));
+                            }
+                        }
+                    }
+                }
+
+                table[i][j] = best;
+            }
+        }
+
+        table
+    }
+}
+
+#[async_trait(?Send)]
+impl Nlu for BuiltinNlu {
+    async fn parse(&self, input: &str) -> Result<NluResponse> {
+        let tokens = tokenize(input);
+        let mut best: Option<(&str, Match)> = None;
+
+        for (name, templates) in &self.intents {
+            for parts in templates {
+                let res = self.match_template(parts, input, &tokens);
+                if best
+                    .as_ref()
+                    .map_or(true, |(_, b)| res.confidence > b.confidence)
+                {
+                    best = Some((name, res));
+                }
+            }
+        }
+
+        Ok(match best {
+            Some((name, res)) if res.confidence > 0.0 => NluResponse {
+                name: Some(name.to_string()),
+                confidence: res.confidence,
+                slots: res.slots,
+            },
+            _ => NluResponse {
+                name: None,
+                confidence: 0.0,
+                slots: Vec::new(),
+            },
+        })
+    }
+}
+
+struct Match {
+    confidence: f32,
+    slots: Vec<NluResponseSlot>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    SkipInput,
+    SkipPart,
+    Word,
+    // Tokens up to `end` are the slot, `value` is which of the entity's
+    Slot { end: usize, value: Option<usize> },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Cell {
+    gained: f32,
+    // Input tokens left unexplained
+    extra: usize,
+    step: Step,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            gained: 0.0,
+            extra: 0,
+            step: Step::SkipInput,
+        }
+    }
+}
+
+impl Cell {
+    fn then(&self, step: Step, gained: f32, extra: usize) -> Self {
+        Self {
+            gained: self.gained + gained,
+            extra: self.extra + extra,
+            step,
+        }
+    }
+
+    fn score(&self) -> f32 {
+        self.gained - EXTRA_TOKEN_PENALTY * self.extra as f32
+    }
+
+    fn max(self, other: Self) -> Self {
+        if other.score() > self.score() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+struct Token {
+    text: String,
+    range: Range<usize>,
+}
+
+// Lowercased words and where they are in the input
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (pos, c) in input
+        .char_indices()
+        .chain(std::iter::once((input.len(), ' ')))
+    {
+        let is_word = c.is_alphanumeric() || c == '\'';
+        match (start, is_word) {
+            (None, true) => start = Some(pos),
+            (Some(s), false) => {
+                tokens.push(Token {
+                    text: input[s..pos].to_lowercase(),
+                    range: s..pos,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+fn words(text: &str) -> Vec<String> {
+    tokenize(text).into_iter().map(|t| t.text).collect()
+}
+
+fn compile(utt: &NluUtterance) -> Vec<Part> {
+    let (text, entities) = match utt {
+        NluUtterance::Direct(text) => return words(text).into_iter().map(Part::Word).collect(),
+        NluUtterance::WithEntities { text, entities } => (text, entities),
+    };
+
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for caps in SLOT_RE.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        let name = caps.get(1).or_else(|| caps.get(2)).unwrap().as_str();
+        parts.extend(
+            words(&text[last..whole.start()])
+                .into_iter()
+                .map(Part::Word),
+        );
+        match entities.get(name) {
+            Some(instance) => parts.push(Part::Slot {
+                name: name.to_string(),
+                entity: instance.kind.clone(),
+            }),
+            // Not a slot after all, just text
+            None => parts.extend(words(whole.as_str()).into_iter().map(Part::Word)),
+        }
+        last = whole.end();
+    }
+    parts.extend(words(&text[last..]).into_iter().map(Part::Word));
+    parts
+}
+
+// How alike all the words of a value are to the start of the input
+fn match_words(value: &[String], tokens: &[Token]) -> Option<f32> {
+    if value.len() > tokens.len() {
+        return None;
+    }
+
+    let mut total = 0.0;
+    for (word, token) in value.iter().zip(tokens) {
+        let sim = similarity(word, &token.text);
+        if sim < FUZZY_THRESHOLD {
+            return None;
+        }
+        total += sim;
+    }
+    Some(total / value.len() as f32)
+}
+
+fn similarity(a: &str, b: &str) -> f32 {
+    if a == b {
+        return 1.0;
+    }
+
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len() < FUZZY_MIN_LEN || b.len() < FUZZY_MIN_LEN {
+        return 0.0;
+    }
+    1.0 - levenshtein(&a, &b) as f32 / a.len().max(b.len()) as f32
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nlu::EntityInstance;
+
+    fn utterance(text: &str, slots: &[(&str, &str)]) -> NluUtterance {
+        NluUtterance::WithEntities {
+            text: text.to_string(),
+            entities: slots
+                .iter()
+                .map(|(name, kind)| {
+                    let instance = EntityInstance {
+                        kind: kind.to_string(),
+                        example: String::new(),
+                    };
+                    (name.to_string(), instance)
+                })
+                .collect(),
+        }
+    }
+
+    fn nlu() -> BuiltinNlu {
+        let mut manager = BuiltinNluManager::new();
+        manager.add_entity(
+            "color".to_string(),
+            EntityDef::new(
+                vec![EntityData {
+                    value: "red".to_string(),
+                    synonyms: vec!["crimson".to_string()],
+                }],
+                false,
+            ),
+        );
+        manager.add_entity("song".to_string(), EntityDef::new(vec![], true));
+        manager.add_intent(
+            "lights",
+            vec![NluUtterance::Direct("turn on the lights".to_string())],
+        );
+        manager.add_intent(
+            "paint",
+            vec![utterance("paint it $color", &[("color", "color")])],
+        );
+        manager.add_intent("play", vec![utterance("play $song", &[("song", "song")])]);
+        manager
+            .train(Path::new(""), Path::new(""), &langid!("en"))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn exact_match() {
+        let res = nlu().parse("Turn on the lights").await.unwrap();
+        assert_eq!(res.name.as_deref(), Some("lights"));
+        assert_eq!(res.confidence, 1.0);
+    }
+
+    #[tokio::test]
+    async fn typos() {
+        // A typo in a word just long enough is still that word
+        assert_eq!(similarity("door", "doer"), FUZZY_THRESHOLD);
+        assert_eq!(similarity("the", "thy"), 0.0);
+
+        // "tirn" counts as "turn" instead of being skipped
+        let res = nlu().parse("tirn on the lights").await.unwrap();
+        assert_eq!(res.name.as_deref(), Some("lights"));
+        assert_eq!(res.confidence, (3.0 + FUZZY_THRESHOLD) / 4.0);
+    }
+
+    #[tokio::test]
+    async fn synonym_is_canonical_value() {
+        let res = nlu().parse("paint it crimson").await.unwrap();
+        assert_eq!(res.name.as_deref(), Some("paint"));
+        assert_eq!(res.slots.len(), 1);
+        assert_eq!(res.slots[0].name, "color");
+        assert_eq!(
+            res.slots[0].value,
+            SlotValue::Entity {
+                raw: "crimson".to_string(),
+                value: "red".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn wildcard_slot() {
+        let res = nlu().parse("play bohemian rhapsody").await.unwrap();
+        assert_eq!(res.name.as_deref(), Some("play"));
+        assert_eq!(res.slots.len(), 1);
+        assert_eq!(
+            res.slots[0].value,
+            SlotValue::Text("bohemian rhapsody".to_string())
+        );
+        assert_eq!(res.slots[0].range, Some(5..22));
+    }
+
+    #[tokio::test]
+    async fn no_match() {
+        let mut manager = BuiltinNluManager::new();
+        manager.add_intent(
+            "lights",
+            vec![NluUtterance::Direct("turn on the lights".to_string())],
+        );
+        let nlu = manager
+            .train(Path::new(""), Path::new(""), &langid!("en"))
+            .unwrap();
+
+        let res = nlu.parse("what time is it").await.unwrap();
+        assert_eq!(res.name, None);
+        assert!(res.slots.is_empty());
+    }
+}
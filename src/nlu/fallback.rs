@@ -0,0 +1,187 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use crate::nlu::{
+    BuiltinNluManager, EntityDef, Nlu, NluContext, NluManager, NluManagerStatic, NluResponse,
+    NluUtterance,
+};
+use crate::signals::nlu_failed;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use log::{info, warn};
+use unic_langid::LanguageIdentifier;
+
+//// NluManager ///////////////////////////////////////////////////////////////////////////////////
+
+/// Uses `P` whenever it can, if it can't handle the language or it's tooling
+/// is not there (e.g: Snips' Python CLI is not installed) `F` is used instead.
+#[derive(Clone, Debug)]
+pub struct FallbackNluManager<P, F> {
+    primary: P,
+    fallback: F,
+    use_fallback: bool,
+}
+
+impl<P> FallbackNluManager<P, BuiltinNluManager> {
+    /// The builtin NLU knows every entity whichever ends up being used
+    pub fn entity(&self, name: &str) -> Option<&EntityDef> {
+        self.fallback.entity(name)
+    }
+}
+
+impl<P, F> NluManager for FallbackNluManager<P, F>
+where
+    P: NluManager + NluManagerStatic,
+    F: NluManager + NluManagerStatic,
+{
+    type NluType = FallbackNlu<P::NluType, F::NluType>;
+
+    fn ready_lang(&mut self, lang: &LanguageIdentifier) -> Result<()> {
+        if !P::is_lang_compatible(lang) {
+            info!(
+                "{} NLU can't handle \"{}\", using {} NLU",
+                P::name(),
+                lang,
+                F::name()
+            );
+            self.use_fallback = true;
+        } else if let Err(e) = self.primary.ready_lang(lang) {
+            warn!(
+                "{} NLU can't be used ({}), using {} NLU",
+                P::name(),
+                e,
+                F::name()
+            );
+            self.use_fallback = true;
+        }
+
+        if self.use_fallback {
+            self.fallback.ready_lang(lang)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Both are kept up to date, we don't know yet which one will be used
+
+    fn add_intent(&mut self, order_name: &str, phrases: Vec<NluUtterance>) {
+        self.primary.add_intent(order_name, phrases.clone());
+        self.fallback.add_intent(order_name, phrases);
+    }
+
+    fn add_entity(&mut self, name: String, def: EntityDef) {
+        self.primary.add_entity(name.clone(), def.clone());
+        self.fallback.add_entity(name, def);
+    }
+
+    // Whatever happens to the primary the fallback must get the value, it
+    // might be the one used
+    fn add_entity_value(&mut self, name: &str, value: String) -> Result<()> {
+        let res = self.fallback.add_entity_value(name, value.clone());
+
+        let primary = &mut self.primary;
+        match panic::catch_unwind(AssertUnwindSafe(|| primary.add_entity_value(name, value))) {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("{} NLU failed to add a value to {}: {}", P::name(), name, e),
+            Err(_) => warn!("{} NLU panicked adding a value to {}", P::name(), name),
+        }
+
+        res
+    }
+
+    fn remove_skill(&mut self, skill_name: &str) {
+        self.primary.remove_skill(skill_name);
+        self.fallback.remove_skill(skill_name);
+    }
+
+    fn train(
+        &self,
+        train_set_path: &Path,
+        engine_path: &Path,
+        lang: &LanguageIdentifier,
+    ) -> Result<Self::NluType> {
+        if !self.use_fallback {
+            match self.primary.train(train_set_path, engine_path, lang) {
+                Ok(nlu) => return Ok(FallbackNlu::Primary(nlu)),
+                Err(e) => {
+                    warn!(
+                        "{} NLU failed to train ({}), using {} NLU",
+                        P::name(),
+                        e,
+                        F::name()
+                    );
+                    nlu_failed(&lang.to_string(), None, &e);
+                }
+            }
+        }
+
+        let (train_set_path, engine_path) = F::get_paths();
+        Ok(FallbackNlu::Fallback(self.fallback.train(
+            &train_set_path,
+            &engine_path,
+            lang,
+        )?))
+    }
+}
+
+impl<P, F> NluManagerStatic for FallbackNluManager<P, F>
+where
+    P: NluManager + NluManagerStatic,
+    F: NluManager + NluManagerStatic,
+{
+    fn new() -> Self {
+        Self {
+            primary: P::new(),
+            fallback: F::new(),
+            use_fallback: false,
+        }
+    }
+
+    fn list_compatible_langs() -> Vec<LanguageIdentifier> {
+        let mut langs = P::list_compatible_langs();
+        for lang in F::list_compatible_langs() {
+            if !langs.contains(&lang) {
+                langs.push(lang);
+            }
+        }
+        langs
+    }
+
+    fn is_lang_compatible(lang: &LanguageIdentifier) -> bool {
+        P::is_lang_compatible(lang) || F::is_lang_compatible(lang)
+    }
+
+    fn name() -> &'static str {
+        P::name()
+    }
+
+    fn get_paths() -> (PathBuf, PathBuf) {
+        P::get_paths()
+    }
+}
+
+/// Nlu ////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum FallbackNlu<P, F> {
+    Primary(P),
+    Fallback(F),
+}
+
+#[async_trait(?Send)]
+impl<P: Nlu, F: Nlu> Nlu for FallbackNlu<P, F> {
+    async fn parse(&self, input: &str) -> Result<NluResponse> {
+        match self {
+            FallbackNlu::Primary(nlu) => nlu.parse(input).await,
+            FallbackNlu::Fallback(nlu) => nlu.parse(input).await,
+        }
+    }
+
+    async fn parse_with_context(&self, input: &str, context: &NluContext) -> Result<NluResponse> {
+        match self {
+            FallbackNlu::Primary(nlu) => nlu.parse_with_context(input, context).await,
+            FallbackNlu::Fallback(nlu) => nlu.parse_with_context(input, context).await,
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 use crate::signals::collections::Hook;
@@ -10,19 +11,37 @@ use crate::vars::mangle;
 use anyhow::Result;
 use async_trait::async_trait;
 use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::Serialize;
 use unic_langid::LanguageIdentifier;
 
-#[cfg(not(feature = "devel_rasa_nlu"))]
+lazy_static! {
+    // Slots in samples are written either as "($slot)" or just "$slot"
+    pub static ref SLOT_RE: Regex = Regex::new(r"\(\s*\$(\w+)\s*\)|\$(\w+)").unwrap();
+}
+
+mod backend;
+pub use self::backend::*;
+
+mod builtin;
+pub use self::builtin::*;
+
+mod fallback;
+pub use self::fallback::*;
+
 mod snips;
-#[cfg(not(feature = "devel_rasa_nlu"))]
 pub use self::snips::*;
 
-#[cfg(feature = "devel_rasa_nlu")]
 mod rasa;
-#[cfg(feature = "devel_rasa_nlu")]
 pub use self::rasa::*;
 
+mod rasa_server;
+pub use self::rasa_server::*;
+
+mod slot_value;
+pub use self::slot_value::*;
+
 pub trait NluManager {
     type NluType: Nlu + Debug + Send;
     fn ready_lang(&mut self, lang: &LanguageIdentifier) -> Result<()>;
@@ -31,6 +50,9 @@ pub trait NluManager {
     fn add_entity(&mut self, name: String, def: EntityDef);
     fn add_entity_value(&mut self, name: &str, value: String) -> Result<()>;
 
+    /// Drop every intent and entity (by their mangled names) of a skill
+    fn remove_skill(&mut self, skill_name: &str);
+
     // Consume the struct so that we can reuse memory
     fn train(
         &self,
@@ -68,6 +90,12 @@ pub enum NluUtterance {
 #[async_trait(?Send)]
 pub trait Nlu {
     async fn parse(&self, input: &str) -> Result<NluResponse>;
+
+    /// Parse an utterance which is a follow-up of a previous one in the same
+    /// session. By default, it is just completed with the previous slots.
+    async fn parse_with_context(&self, input: &str, context: &NluContext) -> Result<NluResponse> {
+        Ok(context.complete(self.parse(input).await?))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -173,10 +201,41 @@ pub struct NluResponse {
     pub slots: Vec<NluResponseSlot>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct NluResponseSlot {
-    pub value: String,
+    // What was said, and what it was understood as
+    pub raw_value: String,
+    pub value: SlotValue,
     pub name: String,
+
+    // Which entity it is and where it is in the input, if the NLU tells us
+    pub entity: Option<String>,
+    pub range: Option<Range<usize>>,
+}
+
+/// What was understood in the previous turn of a session
+#[derive(Clone, Debug)]
+pub struct NluContext {
+    pub intent: String,
+    pub slots: Vec<NluResponseSlot>,
+}
+
+impl NluContext {
+    /// If the response is about the same intent, any slot not said this time
+    /// is taken from the previous turn (e.g: "and tomorrow?").
+    pub fn complete(&self, mut resp: NluResponse) -> NluResponse {
+        if resp.name.as_deref() == Some(&self.intent) {
+            let missing: Vec<NluResponseSlot> = self
+                .slots
+                .iter()
+                .filter(|prev| !resp.slots.iter().any(|s| s.name == prev.name))
+                .cloned()
+                .collect();
+            resp.slots.extend(missing);
+        }
+
+        resp
+    }
 }
 
 pub fn try_open_file_and_check(
@@ -220,7 +279,7 @@ pub fn write_contents(file: &mut File, contents: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn compare_sets_and_train<F: FnOnce()>(
+pub fn compare_sets_and_train<F: FnOnce() -> Result<()>>(
     train_set_path: &Path,
     train_set: &str,
     engine_path: &Path,
@@ -240,8 +299,11 @@ pub fn compare_sets_and_train<F: FnOnce()>(
         // Write train file
         write_contents(&mut train_file, train_set)?;
 
-        // Train engine
-        callback();
+        // Train engine, if it fails forget the set so that it is tried again
+        if let Err(e) = callback() {
+            std::fs::remove_file(train_set_path)?;
+            return Err(e);
+        }
     }
 
     Ok(())
@@ -7,9 +7,9 @@ use std::process::{Child, Command};
 use crate::nlu::{compare_sets_and_train, try_open_file_and_check, write_contents};
 use crate::nlu::{
     EntityData, EntityDef, Nlu, NluManager, NluManagerStatic, NluResponse, NluResponseSlot,
-    NluUtterance,
+    NluUtterance, SlotValue,
 };
-use crate::vars::NLU_RASA_PATH;
+use crate::vars::{is_mangled_from, NLU_RASA_PATH};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -130,7 +130,7 @@ struct RasaNluPipelineElement {
     name: String,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RasaNluManager {
     intents: Vec<(String, Vec<NluUtterance>)>,
     synonyms: Vec<EntityData>,
@@ -192,6 +192,8 @@ impl NluManager for RasaNluManager {
     }
 
     fn add_intent(&mut self, order_name: &str, phrases: Vec<NluUtterance>) {
+        // A skill registering again replaces what it had
+        self.intents.retain(|(name, _)| name != order_name);
         self.intents.push((order_name.to_string(), phrases));
     }
 
@@ -202,8 +204,32 @@ impl NluManager for RasaNluManager {
     }
 
     fn add_entity_value(&mut self, name: &str, value: String) -> Result<()> {
-        // TODO: Finish this!
-        std::unimplemented!();
+        let values = self
+            .equivalences
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Entity {} does not exist", name))?;
+        values.push(value.clone());
+        self.synonyms.push(EntityData {
+            value,
+            synonyms: vec![],
+        });
+        Ok(())
+    }
+
+    fn remove_skill(&mut self, skill_name: &str) {
+        self.intents
+            .retain(|(name, _)| !is_mangled_from(skill_name, name));
+
+        // Synonyms are not tied to their entity, find them by value
+        let removed: Vec<String> = self
+            .equivalences
+            .iter()
+            .filter(|(name, _)| is_mangled_from(skill_name, name))
+            .flat_map(|(_, values)| values.iter().cloned())
+            .collect();
+        self.equivalences
+            .retain(|name, _| !is_mangled_from(skill_name, name));
+        self.synonyms.retain(|s| !removed.contains(&s.value));
     }
 
     fn train(
@@ -225,12 +251,17 @@ impl NluManager for RasaNluManager {
 
         // Make sure it's different, otherwise no need to train it
         compare_sets_and_train(train_set_path, &train_set, engine_path, || {
-            std::process::Command::new("rasa")
+            let status = std::process::Command::new("rasa")
                 .args(&["train", "nlu"])
-                .spawn()
-                .expect("Failed to execute rasa")
-                .wait()
-                .expect("rasa failed it's training, maybe some argument it's wrong?");
+                .status()
+                .map_err(|e| anyhow!("Failed to execute rasa: {}", e))?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "rasa failed it's training, maybe some argument it's wrong?"
+                ))
+            }
         })?;
 
         RasaNlu::new(engine_path)
@@ -346,8 +377,11 @@ impl Into<NluResponse> for RasaResponse {
                 .entities
                 .into_iter()
                 .map(|e| NluResponseSlot {
-                    value: e.value,
+                    value: SlotValue::Text(e.value.clone()),
+                    raw_value: e.value,
                     name: e.entity,
+                    entity: None,
+                    range: Some(e.start as usize..e.end as usize),
                 })
                 .collect(),
         }
@@ -0,0 +1,728 @@
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::GLOBAL_CONF;
+use crate::exts::LockIt;
+use crate::nlu::{try_open_file_and_check, write_contents, SLOT_RE};
+use crate::nlu::{
+    EntityData, EntityDef, Nlu, NluManager, NluManagerStatic, NluResponse, NluResponseSlot,
+    NluUtterance, SlotValue,
+};
+use crate::vars::{is_mangled_from, DEFAULT_RASA_SERVER_URL, NLU_RASA_SERVER_PATH};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use unic_langid::{langid, LanguageIdentifier};
+use url::form_urlencoded;
+
+lazy_static! {
+    // Which language each server is used for
+    static ref SERVER_LANGS: Mutex<HashMap<String, LanguageIdentifier>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RasaServerConf {
+    #[serde(default = "def_url")]
+    pub url: String,
+
+    // Only needed if the server was started with "--auth-token"
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+fn def_url() -> String {
+    DEFAULT_RASA_SERVER_URL.into()
+}
+
+impl Default for RasaServerConf {
+    fn default() -> Self {
+        Self {
+            url: def_url(),
+            token: None,
+        }
+    }
+}
+
+impl RasaServerConf {
+    fn endpoint(&self, path: &str) -> String {
+        let url = format!("{}{}", self.url.trim_end_matches('/'), path);
+        match self.token {
+            Some(ref token) => {
+                let token: String = form_urlencoded::byte_serialize(token.as_bytes()).collect();
+                format!("{}?token={}", url, token)
+            }
+            None => url,
+        }
+    }
+}
+
+//// NluManager ///////////////////////////////////////////////////////////////////////////////////
+
+/// Rasa running on it's own (e.g: as a service), we only send it the training
+/// data and ask it. Note that a server has only one model loaded at a time, so
+/// it can only be used for one language.
+#[derive(Clone, Debug)]
+pub struct RasaServerNluManager {
+    intents: Vec<(String, Vec<NluUtterance>)>,
+    entities: HashMap<String, EntityDef>,
+    conf: RasaServerConf,
+}
+
+#[derive(Serialize)]
+struct RasaTrainData {
+    version: &'static str,
+    recipe: &'static str,
+    language: String,
+    intents: Vec<String>,
+    entities: Vec<String>,
+    nlu: Vec<RasaNluItem>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum RasaNluItem {
+    Intent { intent: String, examples: String },
+    Synonym { synonym: String, examples: String },
+}
+
+impl RasaServerNluManager {
+    fn make_train_set_yaml(&self, lang: &LanguageIdentifier) -> Result<String> {
+        let mut entities = BTreeSet::new();
+        let mut intents = Vec::new();
+        let mut nlu = Vec::new();
+
+        for (name, utts) in &self.intents {
+            let examples: Vec<String> = utts
+                .iter()
+                .enumerate()
+                .filter_map(|(i, utt)| self.make_example(utt, i, &mut entities))
+                .collect();
+            // Rasa refuses intents without examples
+            if examples.is_empty() {
+                continue;
+            }
+            intents.push(name.clone());
+            nlu.push(RasaNluItem::Intent {
+                intent: name.clone(),
+                examples: as_examples(examples.iter()),
+            });
+        }
+
+        // Rasa gives back the value when it finds a synonym
+        for def in self.entities.values() {
+            for data in def.data.iter().filter(|d| !d.synonyms.is_empty()) {
+                nlu.push(RasaNluItem::Synonym {
+                    synonym: data.value.clone(),
+                    examples: as_examples(data.synonyms.iter()),
+                });
+            }
+        }
+
+        let train_set = RasaTrainData {
+            version: "3.1",
+            recipe: "default.v1",
+            language: lang.language.to_string(),
+            intents,
+            entities: entities.into_iter().collect(),
+            nlu,
+        };
+        Ok(serde_yaml::to_string(&train_set)?)
+    }
+
+    // Slots are annotated as "[value](slot)", each sample takes a different
+    // value so that Rasa sees more than one
+    fn make_example(
+        &self,
+        utt: &NluUtterance,
+        index: usize,
+        used_entities: &mut BTreeSet<String>,
+    ) -> Option<String> {
+        let (text, entities) = match utt {
+            NluUtterance::Direct(text) => return Some(text.clone()),
+            NluUtterance::WithEntities { text, entities } => (text, entities),
+        };
+
+        let mut example = String::new();
+        let mut last = 0;
+        for caps in SLOT_RE.captures_iter(text) {
+            let whole = caps.get(0).unwrap();
+            let name = caps.get(1).or_else(|| caps.get(2)).unwrap().as_str();
+            example.push_str(&text[last..whole.start()]);
+            last = whole.end();
+
+            let instance = match entities.get(name) {
+                Some(instance) => instance,
+                None => {
+                    example.push_str(whole.as_str());
+                    continue;
+                }
+            };
+            let value = self
+                .entities
+                .get(&instance.kind)
+                .filter(|def| !def.data.is_empty())
+                .map(|def| def.data[index % def.data.len()].value.clone())
+                .or_else(|| Some(instance.example.clone()).filter(|e| !e.is_empty()));
+            match value {
+                Some(value) => {
+                    example.push_str(&format!("[{}]({})", value, name));
+                    used_entities.insert(name.to_string());
+                }
+                None => {
+                    debug!(
+                        "No example value for slot \"{}\", sample \"{}\" not used",
+                        name, text
+                    );
+                    return None;
+                }
+            }
+        }
+        example.push_str(&text[last..]);
+        Some(example)
+    }
+
+    // The server keeps the model, we just return it's file name
+    async fn train_model(&self, train_set: String) -> Result<String> {
+        let res = reqwest::Client::new()
+            .post(self.conf.endpoint("/model/train"))
+            .header(reqwest::header::CONTENT_TYPE, "application/x-yaml")
+            .body(train_set)
+            .send()
+            .await?
+            .error_for_status()?;
+        res.headers()
+            .get("filename")
+            .and_then(|f| f.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Rasa server didn't say the name of the model"))
+    }
+
+    async fn load_model(&self, model: &str) -> Result<()> {
+        reqwest::Client::new()
+            .put(self.conf.endpoint("/model"))
+            .json(&json!({ "model_file": format!("models/{}", model) }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+impl NluManager for RasaServerNluManager {
+    type NluType = RasaServerNlu;
+
+    fn ready_lang(&mut self, lang: &LanguageIdentifier) -> Result<()> {
+        // A server has just one model, each language would replace the other's
+        {
+            let mut langs = SERVER_LANGS.lock_it();
+            match langs.get(&self.conf.url) {
+                Some(other) if other != lang => {
+                    return Err(anyhow!(
+                    "Rasa server at {} is already used for \"{}\", it can only have one language",
+                    self.conf.url,
+                    other
+                ))
+                }
+                _ => {
+                    langs.insert(self.conf.url.clone(), lang.clone());
+                }
+            }
+        }
+
+        // Just make sure it's there
+        block_on(async {
+            reqwest::get(self.conf.endpoint("/version"))
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+        .map_err(|e| anyhow!("Rasa server at {} can't be reached: {}", self.conf.url, e))
+    }
+
+    fn add_intent(&mut self, order_name: &str, phrases: Vec<NluUtterance>) {
+        // A skill registering again replaces what it had
+        self.intents.retain(|(name, _)| name != order_name);
+        self.intents.push((order_name.to_string(), phrases));
+    }
+
+    fn add_entity(&mut self, name: String, def: EntityDef) {
+        self.entities.insert(name, def);
+    }
+
+    fn add_entity_value(&mut self, name: &str, value: String) -> Result<()> {
+        let def = self
+            .entities
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Entity {} does not exist", name))?;
+        def.data.push(EntityData {
+            value,
+            synonyms: vec![],
+        });
+        Ok(())
+    }
+
+    fn remove_skill(&mut self, skill_name: &str) {
+        self.intents
+            .retain(|(name, _)| !is_mangled_from(skill_name, name));
+        self.entities
+            .retain(|name, _| !is_mangled_from(skill_name, name));
+    }
+
+    fn train(
+        &self,
+        train_set_path: &Path,
+        _engine_path: &Path,
+        lang: &LanguageIdentifier,
+    ) -> Result<RasaServerNlu> {
+        let train_set = self.make_train_set_yaml(lang)?;
+
+        // Both are kept next to the train set, one of each per language
+        let path = train_set_path
+            .parent()
+            .expect("Failed to get Rasa server's path from data's path");
+        let train_set_path = path.join(format!("train-set.{}.yml", lang));
+        let engine_path = path.join(format!("model_name.{}", lang));
+
+        // The engine file just has the name of the last model trained, if the
+        // data is the same that one is used again
+        let changed = try_open_file_and_check(&train_set_path, &train_set)?;
+        block_on(async {
+            let model = match (changed, std::fs::read_to_string(&engine_path)) {
+                (None, Ok(model)) => model,
+                (changed, _) => {
+                    info!("Training Rasa server's model");
+                    let model = self.train_model(train_set.clone()).await?;
+                    std::fs::write(&engine_path, &model)?;
+                    if let Some(mut file) = changed {
+                        write_contents(&mut file, &train_set)?;
+                    }
+                    model
+                }
+            };
+
+            self.load_model(&model).await
+        })?;
+        Ok(RasaServerNlu::new(self.conf.clone()))
+    }
+}
+
+impl NluManagerStatic for RasaServerNluManager {
+    fn new() -> Self {
+        Self {
+            intents: vec![],
+            entities: HashMap::new(),
+            conf: GLOBAL_CONF.with(|c| c.borrow().nlu.rasa_server.clone()),
+        }
+    }
+
+    fn list_compatible_langs() -> Vec<LanguageIdentifier> {
+        vec![
+            langid!("de"),
+            langid!("en"),
+            langid!("es"),
+            langid!("fr"),
+            langid!("it"),
+            langid!("nl"),
+            langid!("pt"),
+            langid!("zh"),
+        ]
+    }
+
+    // It's up to the server's pipeline
+    fn is_lang_compatible(_lang: &LanguageIdentifier) -> bool {
+        true
+    }
+
+    fn name() -> &'static str {
+        "Rasa server"
+    }
+
+    // Each language has it's own files next to these
+    fn get_paths() -> (PathBuf, PathBuf) {
+        let path = NLU_RASA_SERVER_PATH.resolve();
+        (path.join("train-set.yml"), path.join("model_name"))
+    }
+}
+
+/// Nlu ////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub struct RasaServerNlu {
+    client: reqwest::Client,
+    conf: RasaServerConf,
+}
+
+#[derive(Deserialize, Debug)]
+struct RasaParseResponse {
+    #[serde(default)]
+    text: String,
+    intent: RasaParseIntent,
+    #[serde(default)]
+    entities: Vec<RasaParseEntity>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RasaParseIntent {
+    name: Option<String>,
+    confidence: f32,
+}
+
+#[derive(Deserialize, Debug)]
+struct RasaParseEntity {
+    entity: String,
+    start: usize,
+    end: usize,
+    value: serde_json::Value,
+}
+
+impl RasaServerNlu {
+    fn new(conf: RasaServerConf) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            conf,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Nlu for RasaServerNlu {
+    async fn parse(&self, input: &str) -> Result<NluResponse> {
+        let resp: RasaParseResponse = self
+            .client
+            .post(self.conf.endpoint("/model/parse"))
+            .json(&json!({ "text": input }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(resp.into())
+    }
+}
+
+impl From<RasaParseResponse> for NluResponse {
+    fn from(resp: RasaParseResponse) -> Self {
+        let text = resp.text;
+        NluResponse {
+            name: resp.intent.name.filter(|n| !n.is_empty()),
+            confidence: resp.intent.confidence,
+            slots: resp
+                .entities
+                .into_iter()
+                .map(|e| {
+                    let raw = text.get(e.start..e.end).unwrap_or_default().to_string();
+                    (e, raw)
+                })
+                .map(|(e, raw)| NluResponseSlot {
+                    value: match e.value {
+                        // Synonyms are given back as their value
+                        serde_json::Value::String(value) => SlotValue::Entity {
+                            raw: raw.clone(),
+                            value,
+                        },
+                        serde_json::Value::Number(n) => n
+                            .as_f64()
+                            .map(SlotValue::Number)
+                            .unwrap_or_else(|| SlotValue::Text(raw.clone())),
+                        other => SlotValue::Text(other.to_string()),
+                    },
+                    raw_value: raw,
+                    // Entities are named after the slot they fill
+                    name: e.entity,
+                    entity: None,
+                    range: Some(e.start..e.end),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Rasa's examples are a YAML list inside of a text
+fn as_examples<'a, I: Iterator<Item = &'a String>>(examples: I) -> String {
+    examples.map(|e| format!("- {}\n", e)).collect()
+}
+
+// Training is done out of the main runtime (see `dynamic_nlu`), so requests
+// made while at it get a runtime of their own
+fn block_on<T, F: Future<Output = Result<T>>>(f: F) -> Result<T> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nlu::EntityInstance;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct Request {
+        method: String,
+        path: String,
+        headers: HashMap<String, String>,
+        body: String,
+    }
+
+    // Answers like a Rasa server would and keeps what it was asked
+    fn stub_server() -> (RasaServerConf, Arc<Mutex<Vec<Request>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let reqs = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut line = String::new();
+                stream.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap().to_string();
+                let path = parts.next().unwrap().to_string();
+
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).unwrap();
+                    match line.trim_end().split_once(": ") {
+                        Some((name, value)) => {
+                            headers.insert(name.to_lowercase(), value.to_string())
+                        }
+                        None => break,
+                    };
+                }
+                let len = headers
+                    .get("content-length")
+                    .map_or(0, |l| l.parse().unwrap());
+                let mut body = vec![0; len];
+                stream.read_exact(&mut body).unwrap();
+
+                let (status, extra, body_out) = match path.split('?').next().unwrap() {
+                    "/version" => ("200 OK", "", r#"{"version":"3.6.0"}"#),
+                    "/model/train" => ("200 OK", "filename: 20240101-model.tar.gz\r\n", ""),
+                    "/model" => ("204 No Content", "", ""),
+                    "/model/parse" => (
+                        "200 OK",
+                        "",
+                        r#"{"text":"paint it crimson","intent":{"name":"paint","confidence":0.9},
+                        "entities":[{"entity":"color","start":9,"end":16,"value":"red"}]}"#,
+                    ),
+                    _ => ("404 Not Found", "", ""),
+                };
+                reqs.lock_it().push(Request {
+                    method,
+                    path,
+                    headers,
+                    body: String::from_utf8(body).unwrap(),
+                });
+
+                let answer = format!(
+                    "HTTP/1.1 {}\r\n{}content-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    extra,
+                    body_out.len(),
+                    body_out
+                );
+                stream.get_mut().write_all(answer.as_bytes()).unwrap();
+            }
+        });
+
+        let conf = RasaServerConf { url, token: None };
+        (conf, requests)
+    }
+
+    fn manager(conf: RasaServerConf) -> RasaServerNluManager {
+        let mut manager = RasaServerNluManager {
+            intents: vec![],
+            entities: HashMap::new(),
+            conf,
+        };
+        manager.add_entity(
+            "color".to_string(),
+            EntityDef::new(
+                vec![
+                    EntityData {
+                        value: "red".to_string(),
+                        synonyms: vec!["crimson".to_string()],
+                    },
+                    EntityData {
+                        value: "blue".to_string(),
+                        synonyms: vec![],
+                    },
+                ],
+                false,
+            ),
+        );
+        let mut entities = HashMap::new();
+        entities.insert(
+            "color".to_string(),
+            EntityInstance {
+                kind: "color".to_string(),
+                example: "red".to_string(),
+            },
+        );
+        manager.add_intent(
+            "paint",
+            vec![
+                NluUtterance::WithEntities {
+                    text: "paint it $color".to_string(),
+                    entities: entities.clone(),
+                },
+                NluUtterance::WithEntities {
+                    text: "make it ($color)".to_string(),
+                    entities,
+                },
+            ],
+        );
+        manager.add_intent(
+            "lights",
+            vec![NluUtterance::Direct("turn on the lights".to_string())],
+        );
+        manager
+    }
+
+    #[test]
+    fn train_set_yaml() {
+        let yaml = manager(RasaServerConf::default())
+            .make_train_set_yaml(&langid!("en-US"))
+            .unwrap();
+        assert_eq!(
+            yaml,
+            r#"version: '3.1'
+recipe: default.v1
+language: en
+intents:
+- paint
+- lights
+entities:
+- color
+nlu:
+- intent: paint
+  examples: |
+    - paint it [red](color)
+    - make it [blue](color)
+- intent: lights
+  examples: |
+    - turn on the lights
+- synonym: red
+  examples: |
+    - crimson
+"#
+        );
+    }
+
+    #[test]
+    fn train_and_load() {
+        let (conf, requests) = stub_server();
+        let path = std::env::temp_dir().join(format!("lily-rasa-server-{}", std::process::id()));
+        let train_set_path = path.join("train-set.yml");
+        let lang = langid!("en-US");
+
+        let mut manager = manager(conf);
+        manager.ready_lang(&lang).unwrap();
+        manager
+            .train(&train_set_path, &path.join("model_name"), &lang)
+            .unwrap();
+        {
+            let requests = requests.lock_it();
+            let methods: Vec<_> = requests
+                .iter()
+                .map(|r| (r.method.as_str(), r.path.as_str()))
+                .collect();
+            assert_eq!(
+                methods,
+                [
+                    ("GET", "/version"),
+                    ("POST", "/model/train"),
+                    ("PUT", "/model")
+                ]
+            );
+            assert_eq!(requests[1].headers["content-type"], "application/x-yaml");
+            assert_eq!(
+                requests[1].body,
+                manager.make_train_set_yaml(&lang).unwrap()
+            );
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&requests[2].body).unwrap(),
+                json!({"model_file": "models/20240101-model.tar.gz"})
+            );
+        }
+        assert_eq!(
+            std::fs::read_to_string(path.join("model_name.en-US")).unwrap(),
+            "20240101-model.tar.gz"
+        );
+
+        // Same data, the model is just loaded again
+        manager
+            .train(&train_set_path, &path.join("model_name"), &lang)
+            .unwrap();
+        let paths: Vec<_> = requests.lock_it()[3..]
+            .iter()
+            .map(|r| r.path.clone())
+            .collect();
+        assert_eq!(paths, ["/model"]);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn one_lang_per_server() {
+        let (conf, _) = stub_server();
+        let mut manager = manager(conf);
+        manager.ready_lang(&langid!("en-US")).unwrap();
+        manager.ready_lang(&langid!("en-US")).unwrap();
+        assert!(manager.ready_lang(&langid!("es-ES")).is_err());
+    }
+
+    #[tokio::test]
+    async fn parse() {
+        let (conf, requests) = stub_server();
+        let res = RasaServerNlu::new(conf)
+            .parse("paint it crimson")
+            .await
+            .unwrap();
+
+        assert_eq!(res.name.as_deref(), Some("paint"));
+        assert_eq!(res.confidence, 0.9);
+        assert_eq!(res.slots.len(), 1);
+        assert_eq!(res.slots[0].name, "color");
+        assert_eq!(res.slots[0].range, Some(9..16));
+        assert_eq!(
+            res.slots[0].value,
+            SlotValue::Entity {
+                raw: "crimson".to_string(),
+                value: "red".to_string(),
+            }
+        );
+
+        let requests = requests.lock_it();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&requests[0].body).unwrap(),
+            json!({"text": "paint it crimson"})
+        );
+    }
+
+    #[test]
+    fn token_is_encoded() {
+        let conf = RasaServerConf {
+            url: "http://localhost:5005/".to_string(),
+            token: Some("a b&c=d".to_string()),
+        };
+        assert_eq!(
+            conf.endpoint("/model/parse"),
+            "http://localhost:5005/model/parse?token=a+b%26c%3Dd"
+        );
+    }
+}
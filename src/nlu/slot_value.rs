@@ -0,0 +1,224 @@
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
+// How Snips (and its Hermes messages) writes dates
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %:z";
+
+/// What was understood for a slot. NLUs which resolve builtin entities (e.g:
+/// Snips) give the number, date or duration itself, so that skills don't
+/// need to make sense out of "in twenty minutes" again.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SlotValue {
+    /// Just what was said, nothing more is known about it
+    Text(String),
+    /// One of the values of an entity, `value` is the canonical one (the
+    /// one synonyms stand for) and `raw` what was said
+    Entity {
+        raw: String,
+        value: String,
+    },
+    Number(f64),
+    Percentage(f64),
+    DateTime {
+        value: DateTime<FixedOffset>,
+        grain: TimeGrain,
+        precision: TimePrecision,
+    },
+    /// Either side might be open (e.g: "until tomorrow")
+    Interval {
+        from: Option<DateTime<FixedOffset>>,
+        to: Option<DateTime<FixedOffset>>,
+    },
+    Duration(SlotDuration),
+}
+
+/// How precise a date is, "tomorrow" is a day while "at 5" is an hour
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum TimeGrain {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+/// Whether a date or duration is what was said or just near it (e.g: "in
+/// about an hour")
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum TimePrecision {
+    Approximate,
+    Exact,
+}
+
+impl Default for TimePrecision {
+    fn default() -> Self {
+        TimePrecision::Exact
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SlotDuration {
+    pub years: i64,
+    pub quarters: i64,
+    pub months: i64,
+    pub weeks: i64,
+    pub days: i64,
+    pub hours: i64,
+    pub minutes: i64,
+    pub seconds: i64,
+    pub precision: TimePrecision,
+}
+
+impl SlotDuration {
+    /// Months and years don't have a fixed length, they are taken as 30 and
+    /// 365 days
+    pub fn approx(&self) -> Duration {
+        let days = self.years * 365 + self.quarters * 91 + self.months * 30 + self.weeks * 7;
+        let secs =
+            (days + self.days) * 86400 + self.hours * 3600 + self.minutes * 60 + self.seconds;
+        Duration::from_secs(secs.max(0) as u64)
+    }
+}
+
+impl SlotValue {
+    /// Parses a date as written by Snips (e.g: "2021-03-01 18:00:00 +01:00")
+    pub fn parse_date(text: &str) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_str(text, DATE_FORMAT)
+            .or_else(|_| DateTime::parse_from_rfc3339(text))
+            .ok()
+    }
+
+    pub fn format_date(date: &DateTime<FixedOffset>) -> String {
+        date.format(DATE_FORMAT).to_string()
+    }
+
+    /// What was said, for values which were resolved this is the same as
+    /// the value as text
+    pub fn raw(&self) -> String {
+        match self {
+            SlotValue::Text(text) => text.clone(),
+            SlotValue::Entity { raw, .. } => raw.clone(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// The value as text, dates are written as "2021-03-01 18:00:00 +01:00" and
+/// durations as "1h 30m", which is what timers understand
+impl fmt::Display for SlotValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlotValue::Text(text) => write!(f, "{}", text),
+            SlotValue::Entity { value, .. } => write!(f, "{}", value),
+            SlotValue::Number(n) => write!(f, "{}", n),
+            SlotValue::Percentage(n) => write!(f, "{}%", n),
+            SlotValue::DateTime { value, .. } => write!(f, "{}", Self::format_date(value)),
+            SlotValue::Interval { from, to } => {
+                let side = |d: &Option<DateTime<FixedOffset>>| {
+                    d.as_ref().map(Self::format_date).unwrap_or_default()
+                };
+                write!(f, "{} - {}", side(from), side(to))
+            }
+            SlotValue::Duration(dur) => {
+                let secs = dur.approx().as_secs();
+                let parts: Vec<String> =
+                    [(secs / 3600, "h"), (secs / 60 % 60, "m"), (secs % 60, "s")]
+                        .iter()
+                        .filter(|(n, _)| *n > 0)
+                        .map(|(n, unit)| format!("{}{}", n, unit))
+                        .collect();
+                if parts.is_empty() {
+                    write!(f, "0s")
+                } else {
+                    write!(f, "{}", parts.join(" "))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> DateTime<FixedOffset> {
+        SlotValue::parse_date(text).unwrap()
+    }
+
+    #[test]
+    fn dates() {
+        let snips = date("2021-03-01 18:00:00 +01:00");
+        assert_eq!(snips, date("2021-03-01T18:00:00+01:00"));
+        assert_eq!(SlotValue::format_date(&snips), "2021-03-01 18:00:00 +01:00");
+        assert!(SlotValue::parse_date("tomorrow").is_none());
+    }
+
+    #[test]
+    fn as_text() {
+        let entity = SlotValue::Entity {
+            raw: "lounge".into(),
+            value: "living room".into(),
+        };
+        assert_eq!(entity.to_string(), "living room");
+        assert_eq!(entity.raw(), "lounge");
+        assert_eq!(SlotValue::Text("hi".into()).to_string(), "hi");
+        assert_eq!(SlotValue::Number(2.5).to_string(), "2.5");
+        assert_eq!(SlotValue::Number(3.0).to_string(), "3");
+        assert_eq!(SlotValue::Percentage(25.0).to_string(), "25%");
+
+        let value = SlotValue::DateTime {
+            value: date("2021-03-01 18:00:00 +01:00"),
+            grain: TimeGrain::Hour,
+            precision: TimePrecision::Exact,
+        };
+        assert_eq!(value.to_string(), "2021-03-01 18:00:00 +01:00");
+        assert_eq!(value.raw(), value.to_string());
+
+        let open = SlotValue::Interval {
+            from: None,
+            to: Some(date("2021-03-02 00:00:00 +01:00")),
+        };
+        assert_eq!(open.to_string(), " - 2021-03-02 00:00:00 +01:00");
+    }
+
+    #[test]
+    fn durations() {
+        let dur = |d: SlotDuration| SlotValue::Duration(d).to_string();
+        assert_eq!(
+            dur(SlotDuration {
+                hours: 1,
+                minutes: 30,
+                ..Default::default()
+            }),
+            "1h 30m"
+        );
+        assert_eq!(
+            dur(SlotDuration {
+                days: 1,
+                seconds: 5,
+                ..Default::default()
+            }),
+            "24h 5s"
+        );
+        assert_eq!(dur(SlotDuration::default()), "0s");
+
+        let month = SlotDuration {
+            months: 1,
+            weeks: 1,
+            precision: TimePrecision::Approximate,
+            ..Default::default()
+        };
+        assert_eq!(month.approx(), Duration::from_secs(37 * 86400));
+        let negative = SlotDuration {
+            minutes: -5,
+            ..Default::default()
+        };
+        assert_eq!(negative.approx(), Duration::from_secs(0));
+    }
+}
@@ -6,15 +6,17 @@ use std::process::Command;
 
 use crate::nlu::compare_sets_and_train;
 use crate::nlu::{
-    EntityDef, Nlu, NluManager, NluManagerStatic, NluResponse, NluResponseSlot, NluUtterance,
+    EntityDef, Nlu, NluContext, NluManager, NluManagerStatic, NluResponse, NluResponseSlot,
+    NluUtterance, SlotDuration, SlotValue, TimeGrain, TimePrecision,
 };
-use crate::vars::{NLU_ENGINE_PATH, NLU_TRAIN_SET_PATH};
+use crate::vars::{is_mangled_from, MIN_SCORE_FOR_ACTION, NLU_ENGINE_PATH, NLU_TRAIN_SET_PATH};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use regex::Regex;
 use serde::Serialize;
 use snips_nlu_lib::SnipsNluEngine;
+use snips_nlu_ontology::{Grain, Precision, Slot, SlotValue as SnipsValue};
 use unic_langid::{langid, LanguageIdentifier};
 
 use super::EntityData;
@@ -71,7 +73,7 @@ impl From<EntityDef> for SnipsEntityDef {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SnipsNluManager {
     intents: Vec<(String, Vec<NluUtterance>)>,
     entities: HashMap<String, SnipsEntityDef>,
@@ -226,7 +228,7 @@ impl NluManager for SnipsNluManager {
             let success = std::process::Command::new("snips-nlu")
                 .args(&["download", lang_str])
                 .status()
-                .expect("Failed to open snips-nlu binary")
+                .map_err(|e| anyhow!("Failed to open snips-nlu binary: {}", e))?
                 .success();
 
             if success {
@@ -243,6 +245,8 @@ impl NluManager for SnipsNluManager {
     }
 
     fn add_intent(&mut self, order_name: &str, phrases: Vec<NluUtterance>) {
+        // A skill registering again replaces what it had
+        self.intents.retain(|(name, _)| name != order_name);
         self.intents.push((order_name.to_string(), phrases));
     }
 
@@ -262,6 +266,13 @@ impl NluManager for SnipsNluManager {
         Ok(())
     }
 
+    fn remove_skill(&mut self, skill_name: &str) {
+        self.intents
+            .retain(|(name, _)| !is_mangled_from(skill_name, name));
+        self.entities
+            .retain(|name, _| !is_mangled_from(skill_name, name));
+    }
+
     fn train(
         &self,
         train_set_path: &Path,
@@ -274,13 +285,18 @@ impl NluManager for SnipsNluManager {
         let engine_path = Path::new(engine_path);
 
         compare_sets_and_train(train_set_path, &train_set, engine_path, || {
-            std::process::Command::new("snips-nlu")
+            let status = std::process::Command::new("snips-nlu")
                 .arg("train")
                 .args(&[train_set_path, engine_path])
-                .spawn()
-                .expect("Failed to open snips-nlu binary")
-                .wait()
-                .expect("snips-nlu failed it's execution, maybe some argument it's wrong?");
+                .status()
+                .map_err(|e| anyhow!("Failed to open snips-nlu binary: {}", e))?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "snips-nlu failed it's execution, maybe some argument it's wrong?"
+                ))
+            }
         })?;
 
         SnipsNlu::new(engine_path)
@@ -353,6 +369,34 @@ impl Nlu for SnipsNlu {
             })
             .map_err(|_| anyhow!("Failed snips NLU"))
     }
+
+    async fn parse_with_context(&self, input: &str, context: &NluContext) -> Result<NluResponse> {
+        let resp = self.parse(input).await?;
+        if resp.name.is_some() && resp.confidence >= MIN_SCORE_FOR_ACTION {
+            return Ok(context.complete(resp));
+        }
+
+        // Not a whole order by itself, maybe it's just some slots for the
+        // previous intent
+        let slots: Vec<NluResponseSlot> = self
+            .engine
+            .get_slots(input, &context.intent)
+            .map_err(|_| anyhow!("Failed snips NLU"))?
+            .into_iter()
+            .map(Slot::into)
+            .collect();
+
+        if slots.is_empty() {
+            Ok(resp)
+        } else {
+            Ok(context.complete(NluResponse {
+                name: Some(context.intent.clone()),
+                // Just enough to be acted upon
+                confidence: MIN_SCORE_FOR_ACTION,
+                slots,
+            }))
+        }
+    }
 }
 
 impl From<snips_nlu_ontology::IntentParserResult> for NluResponse {
@@ -360,14 +404,85 @@ impl From<snips_nlu_ontology::IntentParserResult> for NluResponse {
         NluResponse {
             name: res.intent.intent_name,
             confidence: res.intent.confidence_score,
-            slots: res
-                .slots
-                .into_iter()
-                .map(|slt| NluResponseSlot {
-                    value: slt.raw_value,
-                    name: slt.slot_name,
-                })
-                .collect(),
+            slots: res.slots.into_iter().map(Slot::into).collect(),
+        }
+    }
+}
+
+impl From<Slot> for NluResponseSlot {
+    fn from(slt: Slot) -> NluResponseSlot {
+        let value = match slt.value {
+            SnipsValue::Custom(v)
+            | SnipsValue::MusicAlbum(v)
+            | SnipsValue::MusicArtist(v)
+            | SnipsValue::MusicTrack(v)
+            | SnipsValue::City(v)
+            | SnipsValue::Country(v)
+            | SnipsValue::Region(v) => SlotValue::Entity {
+                raw: slt.raw_value.clone(),
+                value: v.value,
+            },
+            SnipsValue::Number(n) => SlotValue::Number(n.value),
+            SnipsValue::Ordinal(n) => SlotValue::Number(n.value as f64),
+            SnipsValue::Percentage(n) => SlotValue::Percentage(n.value),
+            // Units are lost, but the raw value still has them
+            SnipsValue::AmountOfMoney(n) => SlotValue::Number(n.value as f64),
+            SnipsValue::Temperature(n) => SlotValue::Number(n.value as f64),
+            SnipsValue::InstantTime(t) => match SlotValue::parse_date(&t.value) {
+                Some(value) => SlotValue::DateTime {
+                    value,
+                    grain: t.grain.into(),
+                    precision: t.precision.into(),
+                },
+                None => SlotValue::Text(slt.raw_value.clone()),
+            },
+            SnipsValue::TimeInterval(t) => SlotValue::Interval {
+                from: t.from.as_deref().and_then(SlotValue::parse_date),
+                to: t.to.as_deref().and_then(SlotValue::parse_date),
+            },
+            SnipsValue::Duration(d) => SlotValue::Duration(SlotDuration {
+                years: d.years,
+                quarters: d.quarters,
+                months: d.months,
+                weeks: d.weeks,
+                days: d.days,
+                hours: d.hours,
+                minutes: d.minutes,
+                seconds: d.seconds,
+                precision: d.precision.into(),
+            }),
+        };
+
+        NluResponseSlot {
+            raw_value: slt.raw_value,
+            value,
+            name: slt.slot_name,
+            entity: Some(slt.entity),
+            range: Some(slt.range),
+        }
+    }
+}
+
+impl From<Grain> for TimeGrain {
+    fn from(grain: Grain) -> TimeGrain {
+        match grain {
+            Grain::Year => TimeGrain::Year,
+            Grain::Quarter => TimeGrain::Quarter,
+            Grain::Month => TimeGrain::Month,
+            Grain::Week => TimeGrain::Week,
+            Grain::Day => TimeGrain::Day,
+            Grain::Hour => TimeGrain::Hour,
+            Grain::Minute => TimeGrain::Minute,
+            Grain::Second => TimeGrain::Second,
+        }
+    }
+}
+
+impl From<Precision> for TimePrecision {
+    fn from(precision: Precision) -> TimePrecision {
+        match precision {
+            Precision::Approximate => TimePrecision::Approximate,
+            Precision::Exact => TimePrecision::Exact,
         }
     }
 }
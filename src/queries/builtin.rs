@@ -0,0 +1,288 @@
+// Standard library
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// This crate
+use super::{Query, QueryData, QueryError, QueryResult};
+use crate::exts::LockIt;
+use crate::vars::QUERY_TIMEOUT;
+
+// Other crates
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json_path::JsonPath;
+use url::form_urlencoded;
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{(\w+)\}").unwrap();
+}
+
+/// Where a query declared by a skill gets it's values from
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QuerySource {
+    /// GET an url expecting JSON, "path" is a JSONPath of the values wanted,
+    /// without it the whole document is the value
+    Http {
+        url: String,
+
+        #[serde(default)]
+        path: Option<String>,
+
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+
+    /// Each line of the command's output is a value
+    Command {
+        command: String,
+
+        #[serde(default)]
+        args: Vec<String>,
+    },
+
+    /// Each line of the file is a value
+    File { path: String },
+}
+
+/// A query as written in a skill's model, "{name}" in urls, arguments and
+/// paths is replaced by the query data with that name (e.g: an intent slot).
+/// With "ttl" (in seconds) results are reused while fresh.
+#[derive(Clone, Debug, Deserialize)]
+pub struct QueryDef {
+    #[serde(flatten)]
+    pub source: QuerySource,
+
+    #[serde(default)]
+    pub ttl: Option<u64>,
+}
+
+#[derive(Clone)]
+pub struct BuiltinQuery {
+    name: String,
+    def: QueryDef,
+    // Keyed by the data used, as it changes the result. Shared by the
+    // copies made to execute the query.
+    cache: Arc<Mutex<HashMap<String, (Instant, QueryResult)>>>,
+}
+
+impl BuiltinQuery {
+    pub fn new(name: String, def: QueryDef) -> Result<Self, QueryError> {
+        // Find bad paths now rather than on each execution
+        if let QuerySource::Http {
+            path: Some(ref path),
+            ..
+        } = def.source
+        {
+            JsonPath::parse(path)?;
+        }
+
+        Ok(Self {
+            name,
+            def,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    async fn fetch(&self, data: &QueryData) -> Result<QueryResult, QueryError> {
+        let timeout = Duration::from_secs(QUERY_TIMEOUT);
+        match &self.def.source {
+            QuerySource::Http { url, path, headers } => {
+                let url = fill(url, data, |v| {
+                    form_urlencoded::byte_serialize(v.as_bytes()).collect()
+                })?;
+                let mut req = reqwest::Client::new().get(&url).timeout(timeout);
+                for (name, value) in headers {
+                    req = req.header(name, value);
+                }
+                let text = req.send().await?.error_for_status()?.text().await?;
+                let json: serde_json::Value = serde_json::from_str(&text)?;
+
+                let values = match path {
+                    Some(path) => JsonPath::parse(path)?
+                        .query(&json)
+                        .all()
+                        .into_iter()
+                        .map(json_to_string)
+                        .collect(),
+                    None => vec![json_to_string(&json)],
+                };
+                Ok(values)
+            }
+            QuerySource::Command { command, args } => {
+                let args = args
+                    .iter()
+                    .map(|a| fill(a, data, str::to_string))
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Killed if it takes too long, as the output is dropped
+                let output = tokio::process::Command::new(command)
+                    .args(args)
+                    .kill_on_drop(true)
+                    .output();
+                let output = tokio::time::timeout(timeout, output)
+                    .await
+                    .map_err(|_| QueryError::Timeout(QUERY_TIMEOUT))??;
+                if !output.status.success() {
+                    return Err(QueryError::CommandFailed(output.status));
+                }
+                Ok(lines(&String::from_utf8(output.stdout)?))
+            }
+            QuerySource::File { path } => {
+                let path = fill(path, data, str::to_string)?;
+                Ok(lines(&tokio::fs::read_to_string(path).await?))
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Query for BuiltinQuery {
+    fn is_monitorable(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, data: QueryData) -> Result<QueryResult, QueryError> {
+        let ttl = match self.def.ttl {
+            Some(ttl) => Duration::from_secs(ttl),
+            None => return self.fetch(&data).await,
+        };
+
+        let key = format!("{:?}", data.iter().collect::<BTreeMap<_, _>>());
+        if let Some((when, res)) = self.cache.lock_it().get(&key) {
+            if when.elapsed() < ttl {
+                return Ok(res.clone());
+            }
+        }
+
+        let res = self.fetch(&data).await?;
+        let mut cache = self.cache.lock_it();
+        cache.retain(|_, (when, _)| when.elapsed() < ttl);
+        cache.insert(key, (Instant::now(), res.clone()));
+        Ok(res)
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn clone_query(&self) -> Box<dyn Query + Send> {
+        Box::new(self.clone())
+    }
+}
+
+// Replace each "{name}" with the data of that name, passed through `escape`
+fn fill<F: Fn(&str) -> String>(
+    template: &str,
+    data: &QueryData,
+    escape: F,
+) -> Result<String, QueryError> {
+    let mut missing = None;
+    let res = PLACEHOLDER.replace_all(template, |caps: &Captures| match data.get(&caps[1]) {
+        Some(value) => escape(value),
+        None => {
+            missing.get_or_insert_with(|| caps[1].to_string());
+            String::new()
+        }
+    });
+
+    match missing {
+        Some(name) => Err(QueryError::MissingData(name)),
+        None => Ok(res.into_owned()),
+    }
+}
+
+// Texts are used as they are, anything else as JSON
+fn json_to_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn lines(text: &str) -> QueryResult {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(def: serde_json::Value) -> Result<BuiltinQuery, QueryError> {
+        BuiltinQuery::new("test".to_string(), serde_json::from_value(def).unwrap())
+    }
+
+    fn data(name: &str, value: &str) -> QueryData {
+        let mut data = QueryData::new();
+        data.insert(name.to_string(), value.to_string());
+        data
+    }
+
+    #[test]
+    fn fill_templates() {
+        let escape = |v: &str| form_urlencoded::byte_serialize(v.as_bytes()).collect();
+        assert_eq!(
+            fill("http://x/?q={city}", &data("city", "New York"), escape).unwrap(),
+            "http://x/?q=New+York"
+        );
+        assert!(matches!(
+            fill("{city}/{country}", &data("city", "Paris"), str::to_string),
+            Err(QueryError::MissingData(name)) if name == "country"
+        ));
+    }
+
+    #[test]
+    fn bad_json_path() {
+        let def = serde_json::json!({"kind": "http", "url": "http://x", "path": "$[["});
+        assert!(matches!(query(def), Err(QueryError::JsonPath(_))));
+    }
+
+    #[tokio::test]
+    async fn file_values_are_cached() {
+        let dir = std::env::temp_dir().join("lily_query_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("values.txt");
+        std::fs::write(&path, "one\n\n  two \n").unwrap();
+
+        let def = serde_json::json!({
+            "kind": "file",
+            "path": dir.join("{file}").to_str().unwrap(),
+            "ttl": 60
+        });
+        let q = query(def).unwrap();
+        let file = data("file", "values.txt");
+        assert_eq!(q.execute(file.clone()).await.unwrap(), vec!["one", "two"]);
+
+        std::fs::write(&path, "three").unwrap();
+        assert_eq!(
+            q.clone_query().execute(file).await.unwrap(),
+            vec!["one", "two"]
+        );
+        assert!(matches!(
+            q.execute(data("file", "missing.txt")).await,
+            Err(QueryError::Io(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command_output() {
+        let def = serde_json::json!({"kind": "command", "command": "echo", "args": ["{word}"]});
+        let q = query(def).unwrap();
+        assert_eq!(q.execute(data("word", "hi")).await.unwrap(), vec!["hi"]);
+
+        let def = serde_json::json!({"kind": "command", "command": "false"});
+        assert!(matches!(
+            query(def).unwrap().execute(QueryData::new()).await,
+            Err(QueryError::CommandFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,28 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum QueryError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Response is not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid JSONPath: {0}")]
+    JsonPath(#[from] serde_json_path::ParseError),
+
+    #[error("Input/output error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Output is not a valid utf-8")]
+    FromUtf8(#[from] std::string::FromUtf8Error),
+
+    #[error("Command exited with {0}")]
+    CommandFailed(std::process::ExitStatus),
+
+    #[error("No answer after {0} seconds")]
+    Timeout(u64),
+
+    #[error("Data \"{0}\" is needed but wasn't provided")]
+    MissingData(String),
+}
@@ -0,0 +1,205 @@
+mod builtin;
+mod error;
+pub use self::builtin::*;
+pub use self::error::*;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::actions::{Action, ActionAnswer, ActionContext, TranslatedAnswer};
+use crate::collections::BaseRegistry;
+use crate::exts::LockIt;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use log::error;
+use regex::Regex;
+
+pub type QueryData = HashMap<String, String>;
+pub type QueryResult = Vec<String>;
+pub type QueryRegistry = BaseRegistry<dyn Query + Send>;
+
+lazy_static! {
+    pub static ref QUERY_REG: Mutex<QueryRegistry> = Mutex::new(QueryRegistry::new());
+}
+
+#[async_trait(?Send)]
+pub trait Query {
+    fn is_monitorable(&self) -> bool;
+    async fn execute(&self, data: QueryData) -> Result<QueryResult, QueryError>;
+    fn get_name(&self) -> &str;
+
+    /// A copy of the query to execute it without keeping it locked, copies
+    /// share any state (e.g: caches) with the original
+    fn clone_query(&self) -> Box<dyn Query + Send>;
+}
+
+/// When a polled query should trigger its actions. The first poll only sets
+/// the baseline, after that conditions fire once each time they become true.
+/// Numbers and texts are taken from the first value of the result.
+#[derive(Clone, Debug)]
+pub enum Condition {
+    Changed,
+    Equals(String),
+    // Crossed the threshold upwards
+    Above(f64),
+    // Crossed the threshold downwards
+    Below(f64),
+    // Any of the values matches
+    Matches(Regex),
+    BecameEmpty,
+    BecameNonEmpty,
+}
+
+impl Condition {
+    /// Understand a condition from a data map, with "condition" being one of
+    /// "changed" (default), "equals", "above", "below", "matches",
+    /// "became_empty" or "became_non_empty"; and "value" what the first four
+    /// compare to
+    pub fn from_data(data: &QueryData) -> Result<Self> {
+        let value = || {
+            data.get("value")
+                .ok_or_else(|| anyhow!("Condition needs a \"value\""))
+        };
+        let number = || -> Result<f64> {
+            let value = value()?;
+            value
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("\"{}\" is not a number", value))
+        };
+
+        match data.get("condition").map(String::as_str) {
+            None | Some("changed") => Ok(Condition::Changed),
+            Some("equals") => Ok(Condition::Equals(value()?.clone())),
+            Some("above") => Ok(Condition::Above(number()?)),
+            Some("below") => Ok(Condition::Below(number()?)),
+            Some("matches") => Ok(Condition::Matches(Regex::new(value()?)?)),
+            Some("became_empty") => Ok(Condition::BecameEmpty),
+            Some("became_non_empty") => Ok(Condition::BecameNonEmpty),
+            Some(other) => Err(anyhow!("\"{}\" is not a known condition", other)),
+        }
+    }
+
+    pub fn check(&self, prev: &QueryResult, curr: &QueryResult) -> bool {
+        match self {
+            Condition::Changed => prev != curr,
+            _ => !self.holds(prev) && self.holds(curr),
+        }
+    }
+
+    fn holds(&self, res: &QueryResult) -> bool {
+        let number = || res.first().and_then(|v| v.trim().parse::<f64>().ok());
+        match self {
+            Condition::Changed => false,
+            Condition::Equals(value) => res.first() == Some(value),
+            Condition::Above(threshold) => number().map(|n| n > *threshold).unwrap_or(false),
+            Condition::Below(threshold) => number().map(|n| n < *threshold).unwrap_or(false),
+            Condition::Matches(regex) => res.iter().any(|v| regex.is_match(v)),
+            Condition::BecameEmpty => res.is_empty(),
+            Condition::BecameNonEmpty => !res.is_empty(),
+        }
+    }
+}
+
+pub struct ActQuery {
+    q: Arc<Mutex<dyn Query + Send>>,
+    name: String,
+}
+
+impl ActQuery {
+    pub fn new(q: Arc<Mutex<dyn Query + Send>>, name: String) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self { q, name }))
+    }
+}
+
+#[async_trait(?Send)]
+impl Action for ActQuery {
+    async fn call(&mut self, context: &ActionContext) -> Result<ActionAnswer> {
+        // Slots are the data of the query
+        let data = context
+            .data
+            .as_intent()
+            .map(|i| i.slot_texts())
+            .unwrap_or_default();
+
+        let q = self.q.lock_it().clone_query();
+        match q.execute(data).await {
+            Ok(v) if v.is_empty() => {
+                TranslatedAnswer::new("embedded", "query_empty").build(context, true)
+            }
+            Ok(v) => ActionAnswer::send_text(v.join(", "), true),
+            Err(e) => {
+                error!("Query '{}' had an error: {}", q.get_name(), e);
+                TranslatedAnswer::new("embedded", "query_failed").build(context, true)
+            }
+        }
+    }
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(condition: &str, value: Option<&str>) -> Result<Condition> {
+        let mut data = QueryData::new();
+        data.insert("condition".to_string(), condition.to_string());
+        if let Some(value) = value {
+            data.insert("value".to_string(), value.to_string());
+        }
+        Condition::from_data(&data)
+    }
+
+    fn res(values: &[&str]) -> QueryResult {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn conditions_from_data() {
+        assert!(matches!(
+            Condition::from_data(&QueryData::new()).unwrap(),
+            Condition::Changed
+        ));
+        assert!(matches!(
+            condition("above", Some(" 20.5 ")).unwrap(),
+            Condition::Above(t) if t == 20.5
+        ));
+        assert!(condition("above", Some("warm")).is_err());
+        assert!(condition("equals", None).is_err());
+        assert!(condition("matches", Some("(")).is_err());
+        assert!(condition("sometimes", None).is_err());
+    }
+
+    #[test]
+    fn conditions_fire_once() {
+        let changed = Condition::Changed;
+        assert!(changed.check(&res(&["a"]), &res(&["b"])));
+        assert!(!changed.check(&res(&["a"]), &res(&["a"])));
+
+        let above = condition("above", Some("20")).unwrap();
+        assert!(above.check(&res(&["19"]), &res(&["21"])));
+        assert!(!above.check(&res(&["21"]), &res(&["22"])));
+        assert!(!above.check(&res(&["19"]), &res(&["hot"])));
+
+        let below = condition("below", Some("5")).unwrap();
+        assert!(below.check(&res(&["6"]), &res(&["4.5"])));
+        assert!(!below.check(&res(&["4"]), &res(&["3"])));
+
+        let equals = condition("equals", Some("on")).unwrap();
+        assert!(equals.check(&res(&["off"]), &res(&["on"])));
+        assert!(!equals.check(&res(&["on"]), &res(&["on"])));
+
+        let matches = condition("matches", Some("^err")).unwrap();
+        assert!(matches.check(&res(&["ok"]), &res(&["ok", "error"])));
+
+        let empty = condition("became_empty", None).unwrap();
+        assert!(empty.check(&res(&["a"]), &res(&[])));
+        let non_empty = condition("became_non_empty", None).unwrap();
+        assert!(non_empty.check(&res(&[]), &res(&["a"])));
+        assert!(!non_empty.check(&res(&["a"]), &res(&["b"])));
+    }
+}
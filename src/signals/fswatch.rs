@@ -0,0 +1,514 @@
+// Standard library
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// This crate
+use crate::actions::{ActionContext, ActionSet, ContextData};
+use crate::config::Config;
+use crate::exts::LockIt;
+use crate::signals::{announce_answers, Signal, SignalEventShared, UserSignal};
+use crate::vars::DEFAULT_FS_DEBOUNCE;
+
+// Other crates
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{error, warn};
+use regex::Regex;
+use tokio::io::unix::AsyncFd;
+use tokio::task::spawn_local;
+use tokio::time::sleep;
+use unic_langid::LanguageIdentifier;
+
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_MOVED_TO
+    | libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Change {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl Change {
+    fn from_mask(mask: u32) -> Option<Self> {
+        if mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+            Some(Change::Created)
+        } else if mask & (libc::IN_MODIFY | libc::IN_CLOSE_WRITE) != 0 {
+            Some(Change::Modified)
+        } else if mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0 {
+            Some(Change::Deleted)
+        } else {
+            None
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self> {
+        match name.trim() {
+            "created" => Ok(Change::Created),
+            "modified" => Ok(Change::Modified),
+            "deleted" => Ok(Change::Deleted),
+            other => Err(anyhow!("\"{}\" is not a known change", other)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Change::Created => "created",
+            Change::Modified => "modified",
+            Change::Deleted => "deleted",
+        }
+    }
+
+    // What is reported when more than one change happens in the debounce
+    // time, a file that is created and then written is just created
+    fn then(self, next: Change) -> Change {
+        match (self, next) {
+            (Change::Created, Change::Modified) => Change::Created,
+            (_, next) => next,
+        }
+    }
+}
+
+struct Watch {
+    dir: PathBuf,
+    // Against the file name, none means any
+    filter: Option<Regex>,
+    changes: Vec<Change>,
+    debounce: Duration,
+    satellite: Option<String>,
+    act_set: ActionSet,
+    // Changes waiting for the debounce, by name, with a counter so that only
+    // the last one fires
+    pending: HashMap<String, (u64, Change)>,
+}
+
+impl Watch {
+    fn new(mut data: HashMap<String, String>, act_set: ActionSet) -> Result<Self> {
+        let path = PathBuf::from(
+            data.remove("path")
+                .ok_or_else(|| anyhow!("A \"path\" to watch is needed"))?,
+        );
+
+        // Files are watched through their folder, that way it doesn't matter
+        // if they don't exist yet or if they are replaced
+        let (dir, filter) = if path.is_dir() {
+            let filter = data.remove("glob").map(|g| glob_regex(&g)).transpose()?;
+            (path, filter)
+        } else {
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .ok_or_else(|| anyhow!("{:?} has no file name", path))?;
+            let filter = Regex::new(&format!("^{}$", regex::escape(name)))?;
+            let dir = match path.parent() {
+                Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            (dir, Some(filter))
+        };
+
+        let changes = match data.remove("changes") {
+            Some(changes) => changes
+                .split(',')
+                .map(Change::parse)
+                .collect::<Result<Vec<_>>>()?,
+            None => vec![Change::Created, Change::Modified, Change::Deleted],
+        };
+        let debounce = match data.remove("debounce") {
+            Some(ms) => ms
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Debounce \"{}\" is not a number of milliseconds", ms))?,
+            None => DEFAULT_FS_DEBOUNCE,
+        };
+
+        Ok(Self {
+            dir,
+            filter,
+            changes,
+            debounce: Duration::from_millis(debounce),
+            satellite: data.remove("satellite"),
+            act_set,
+            pending: HashMap::new(),
+        })
+    }
+
+    fn wants(&self, name: &str) -> bool {
+        self.filter.as_ref().map_or(true, |f| f.is_match(name))
+    }
+}
+
+struct WatchState {
+    fd: OwnedFd,
+    // By inotify watch descriptor, the same folder is only watched once
+    watches: HashMap<i32, Vec<Watch>>,
+    lang: Option<LanguageIdentifier>,
+}
+
+/// Calls actions when files are created, modified or deleted. Takes "path"
+/// (a folder, or a file), and optionally "glob" (e.g: "*.pdf", only for
+/// folders), "changes" (a comma separated list of created, modified and
+/// deleted), "debounce" (in milliseconds) and "satellite" to say the answers
+/// there instead of on every satellite. Folders aren't watched recursively.
+/// Actions get "path", "name" and "change" as event data.
+pub struct FsWatchSignal {
+    // Not made until some skill asks for a watch
+    state: Option<Arc<Mutex<WatchState>>>,
+    running: bool,
+    // Only known once the event loop is running
+    def_lang: Option<LanguageIdentifier>,
+}
+
+impl FsWatchSignal {
+    pub fn new() -> Self {
+        Self {
+            state: None,
+            running: false,
+            def_lang: None,
+        }
+    }
+
+    fn state(&mut self) -> Result<&Arc<Mutex<WatchState>>> {
+        if self.state.is_none() {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            self.state = Some(Arc::new(Mutex::new(WatchState {
+                fd: unsafe { OwnedFd::from_raw_fd(fd) },
+                watches: HashMap::new(),
+                lang: None,
+            })));
+        }
+
+        Ok(self.state.as_ref().unwrap())
+    }
+
+    fn start(&mut self) -> Result<()> {
+        let state = match self.state {
+            Some(ref state) if !self.running => state.clone(),
+            _ => return Ok(()),
+        };
+        // Shares the inotify instance, but can be owned by the reader
+        let fd = AsyncFd::new(state.lock_it().fd.try_clone()?)?;
+        self.running = true;
+
+        spawn_local(async move {
+            if let Err(e) = read_loop(fd, state).await {
+                error!("Filesystem watch stopped: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl Signal for FsWatchSignal {
+    fn end_load(&mut self, _curr_lang: &[LanguageIdentifier]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn event_loop(
+        &mut self,
+        _signal_event: SignalEventShared,
+        _config: &Config,
+        curr_lang: &[LanguageIdentifier],
+    ) -> Result<()> {
+        self.def_lang = Some(curr_lang[0].clone());
+        if let Some(ref state) = self.state {
+            state.lock_it().lang = self.def_lang.clone();
+            self.start()?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl UserSignal for FsWatchSignal {
+    fn add(
+        &mut self,
+        data: HashMap<String, String>,
+        _skill_name: &str,
+        act_set: ActionSet,
+    ) -> Result<()> {
+        let watch = Watch::new(data, act_set)?;
+        let dir = CString::new(watch.dir.as_os_str().as_bytes())?;
+
+        let state = self.state()?.clone();
+        {
+            let mut state = state.lock_it();
+            let wd =
+                unsafe { libc::inotify_add_watch(state.fd.as_raw_fd(), dir.as_ptr(), WATCH_MASK) };
+            if wd < 0 {
+                return Err(anyhow!(
+                    "Can't watch {:?}: {}",
+                    watch.dir,
+                    io::Error::last_os_error()
+                ));
+            }
+            state.watches.entry(wd).or_default().push(watch);
+            state.lang = self.def_lang.clone();
+        }
+
+        // Watches added while running (or the first one) start right away
+        if self.def_lang.is_some() {
+            self.start()?;
+        }
+        Ok(())
+    }
+}
+
+async fn read_loop(fd: AsyncFd<OwnedFd>, state: Arc<Mutex<WatchState>>) -> Result<()> {
+    let mut buf = vec![0u8; 4096];
+    loop {
+        let mut guard = fd.readable().await?;
+        let len = match guard.try_io(|inner| read_fd(inner.get_ref().as_raw_fd(), &mut buf)) {
+            Ok(res) => res?,
+            Err(_would_block) => continue,
+        };
+
+        for (wd, mask, name) in parse_events(&buf[..len]) {
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                warn!("Too many filesystem changes, some were lost");
+                continue;
+            }
+            if let Some(change) = Change::from_mask(mask) {
+                on_change(&state, wd, &name, change);
+            }
+        }
+    }
+}
+
+fn read_fd(fd: i32, buf: &mut [u8]) -> io::Result<usize> {
+    let res = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res as usize)
+    }
+}
+
+// Each event is an inotify_event header followed by a nul padded name
+fn parse_events(buf: &[u8]) -> Vec<(i32, u32, String)> {
+    let header = std::mem::size_of::<libc::inotify_event>();
+    let mut events = Vec::new();
+    let mut pos = 0;
+    while pos + header <= buf.len() {
+        let ev: libc::inotify_event =
+            unsafe { std::ptr::read_unaligned(buf[pos..].as_ptr() as *const _) };
+        let name_end = (pos + header + ev.len as usize).min(buf.len());
+        let name = buf[pos + header..name_end]
+            .split(|b| *b == 0)
+            .next()
+            .map(|n| String::from_utf8_lossy(n).into_owned())
+            .unwrap_or_default();
+        events.push((ev.wd, ev.mask, name));
+        pos = name_end;
+    }
+    events
+}
+
+fn on_change(state: &Arc<Mutex<WatchState>>, wd: i32, name: &str, change: Change) {
+    let mut grd = state.lock_it();
+    let watches = match grd.watches.get_mut(&wd) {
+        Some(watches) => watches,
+        None => return,
+    };
+
+    for (index, watch) in watches.iter_mut().enumerate() {
+        if !watch.wants(name) {
+            continue;
+        }
+
+        let entry = watch.pending.entry(name.to_string()).or_insert((0, change));
+        *entry = (entry.0 + 1, entry.1.then(change));
+        let count = entry.0;
+        let debounce = watch.debounce;
+
+        let state = state.clone();
+        let name = name.to_string();
+        spawn_local(async move {
+            sleep(debounce).await;
+            fire(&state, wd, index, &name, count).await;
+        });
+    }
+}
+
+// Call the actions unless something else changed while waiting
+async fn fire(state: &Arc<Mutex<WatchState>>, wd: i32, index: usize, name: &str, count: u64) {
+    let (act_set, satellite, lang, data) = {
+        let mut grd = state.lock_it();
+        let lang = match grd.lang {
+            Some(ref lang) => lang.clone(),
+            None => return,
+        };
+        let watch = match grd.watches.get_mut(&wd).and_then(|w| w.get_mut(index)) {
+            Some(watch) => watch,
+            None => return,
+        };
+        let change = match watch.pending.get(name) {
+            Some((c, change)) if *c == count => *change,
+            _ => return,
+        };
+        watch.pending.remove(name);
+        if !watch.changes.contains(&change) {
+            return;
+        }
+
+        let mut data = HashMap::new();
+        data.insert(
+            "path".to_string(),
+            watch.dir.join(name).to_string_lossy().into_owned(),
+        );
+        data.insert("name".to_string(), name.to_string());
+        data.insert("change".to_string(), change.name().to_string());
+        (watch.act_set.clone(), watch.satellite.clone(), lang, data)
+    };
+
+    let context = ActionContext {
+        locale: lang.to_string(),
+        satellite: None,
+        data: ContextData::Event {
+            event: "fs_change".into(),
+            data,
+        },
+    };
+    let ans = act_set.call_all(&context).await;
+    announce_answers(ans, &lang, satellite.as_deref());
+}
+
+// Globs are matched against file names: "*" is any text, "?" any character
+// and "{a,b}" any of them
+fn glob_regex(glob: &str) -> Result<Regex> {
+    let mut re = String::from("^");
+    let mut in_group = false;
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '{' => {
+                in_group = true;
+                re.push_str("(?:");
+            }
+            '}' if in_group => {
+                in_group = false;
+                re.push(')');
+            }
+            ',' if in_group => re.push('|'),
+            other => re.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    re.push('$');
+    Ok(Regex::new(&re)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watch(data: &[(&str, &str)]) -> Result<Watch> {
+        let data = data
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Watch::new(data, ActionSet::empty())
+    }
+
+    fn event(wd: i32, mask: u32, name: &str) -> Vec<u8> {
+        // Names are padded with nuls, as inotify does
+        let len = (name.len() / 16 + 1) * 16;
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&wd.to_ne_bytes());
+        buf.extend_from_slice(&mask.to_ne_bytes());
+        buf.extend_from_slice(&0u32.to_ne_bytes());
+        buf.extend_from_slice(&(len as u32).to_ne_bytes());
+        buf.extend_from_slice(name.as_bytes());
+        buf.resize(buf.len() + len - name.len(), 0);
+        buf
+    }
+
+    #[test]
+    fn globs() {
+        let re = glob_regex("*.{pdf,txt}").unwrap();
+        assert!(re.is_match("report.pdf"));
+        assert!(re.is_match("notes.txt"));
+        assert!(!re.is_match("report.pdf.bak"));
+        assert!(!re.is_match("reportXpdf"));
+
+        let re = glob_regex("log?.txt").unwrap();
+        assert!(re.is_match("log1.txt"));
+        assert!(!re.is_match("log10.txt"));
+    }
+
+    #[test]
+    fn changes() {
+        assert_eq!(Change::from_mask(libc::IN_MOVED_TO), Some(Change::Created));
+        assert_eq!(
+            Change::from_mask(libc::IN_CLOSE_WRITE),
+            Some(Change::Modified)
+        );
+        assert_eq!(
+            Change::from_mask(libc::IN_MOVED_FROM),
+            Some(Change::Deleted)
+        );
+        assert_eq!(Change::from_mask(libc::IN_ACCESS), None);
+
+        assert_eq!(Change::Created.then(Change::Modified), Change::Created);
+        assert_eq!(Change::Modified.then(Change::Deleted), Change::Deleted);
+        assert_eq!(Change::Deleted.then(Change::Created), Change::Created);
+    }
+
+    #[test]
+    fn watches_from_data() {
+        let w = watch(&[
+            ("path", "/nowhere/notes.txt"),
+            ("changes", "created, deleted"),
+        ])
+        .unwrap();
+        assert_eq!(w.dir, PathBuf::from("/nowhere"));
+        assert!(w.wants("notes.txt"));
+        assert!(!w.wants("notes.txt.swp"));
+        assert_eq!(w.changes, vec![Change::Created, Change::Deleted]);
+        assert_eq!(w.debounce, Duration::from_millis(DEFAULT_FS_DEBOUNCE));
+
+        let dir = std::env::temp_dir();
+        let dir = dir.to_str().unwrap();
+        let w = watch(&[("path", dir), ("glob", "*.pdf"), ("debounce", "50")]).unwrap();
+        assert!(w.wants("report.pdf"));
+        assert!(!w.wants("report.txt"));
+        assert_eq!(w.debounce, Duration::from_millis(50));
+        assert!(watch(&[("path", dir)]).unwrap().wants("anything"));
+
+        assert!(watch(&[]).is_err());
+        assert!(watch(&[("path", dir), ("changes", "renamed")]).is_err());
+        assert!(watch(&[("path", dir), ("debounce", "soon")]).is_err());
+    }
+
+    #[test]
+    fn inotify_events() {
+        let mut buf = event(1, libc::IN_CREATE, "report.pdf");
+        buf.extend(event(2, libc::IN_DELETE, "a-rather-long-file-name.txt"));
+        assert_eq!(
+            parse_events(&buf),
+            vec![
+                (1, libc::IN_CREATE, "report.pdf".to_string()),
+                (
+                    2,
+                    libc::IN_DELETE,
+                    "a-rather-long-file-name.txt".to_string()
+                ),
+            ]
+        );
+        assert!(parse_events(&buf[..8]).is_empty());
+    }
+}
@@ -1,24 +1,37 @@
+#[cfg(target_os = "linux")]
+pub mod fswatch;
 pub mod order;
 pub mod poll;
 pub mod registries;
+pub mod system;
 pub mod time;
+pub mod topic;
+pub mod webhook;
 
+#[cfg(target_os = "linux")]
+pub use self::fswatch::*;
 pub use self::order::*;
 pub use self::poll::*;
 pub use self::registries::*;
+pub use self::system::*;
 pub use self::time::*;
+pub use self::topic::*;
+pub use self::webhook::*;
 
 // Standard library
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 // This crate
-use crate::actions::{Action, ActionAnswer, ActionContext, ActionSet, ContextData, ACT_REG};
+use crate::actions::{
+    Action, ActionAnswer, ActionContext, ActionSet, ContextData, TranslatedAnswer, ACT_REG,
+};
 use crate::config::Config;
 use crate::exts::LockIt;
+use crate::vars::is_mangled_from;
 
 // Other crates
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use lazy_static::lazy_static;
 use unic_langid::LanguageIdentifier;
@@ -42,8 +55,14 @@ impl SignalEvent {
         }
     }
 
+    // Multiple skills can react to the same event, so actions are accumulated
     pub fn add(&mut self, event_name: &str, act_set: ActionSet) {
-        self.event_map.add_mapping(event_name, act_set)
+        self.event_map.extend_mapping(event_name, act_set)
+    }
+
+    /// The actions of an event, to call them without keeping this locked
+    pub fn get(&self, event_name: &str) -> Option<ActionSet> {
+        self.event_map.get(event_name).cloned()
     }
 
     pub async fn call(
@@ -51,8 +70,14 @@ impl SignalEvent {
         event_name: &str,
         mut context: ActionContext,
     ) -> Option<Vec<ActionAnswer>> {
+        // Keep the data if the caller already gave some
+        let data = match context.data {
+            ContextData::Event { data, .. } => data,
+            ContextData::Intent { .. } => HashMap::new(),
+        };
         context.data = ContextData::Event {
             event: event_name.to_string(),
+            data,
         };
         self.event_map.call_mapping(event_name, &context).await
     }
@@ -78,6 +103,27 @@ impl ActMap {
         *action_entry = act_set;
     }
 
+    /// Forget all the mappings of a skill's intents
+    pub fn remove_skill(&mut self, skill_name: &str) {
+        self.map
+            .retain(|order_name, _| !is_mangled_from(skill_name, order_name));
+    }
+
+    pub fn get(&self, order_name: &str) -> Option<&ActionSet> {
+        self.map.get(order_name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.map.keys()
+    }
+
+    pub fn extend_mapping(&mut self, order_name: &str, act_set: ActionSet) {
+        self.map
+            .entry(order_name.to_string())
+            .or_insert(ActionSet::empty())
+            .extend(act_set);
+    }
+
     pub async fn call_mapping(
         &mut self,
         act_name: &str,
@@ -115,29 +161,62 @@ pub trait UserSignal: Signal {
 pub struct ActSignal {
     s: Arc<Mutex<dyn UserSignal + Send>>,
     name: String,
+    skill_name: String,
 }
 
 impl ActSignal {
-    pub fn new(s: Arc<Mutex<dyn UserSignal + Send>>, name: String) -> Arc<Mutex<Self>> {
-        Arc::new(Mutex::new(Self { s, name }))
+    pub fn new(
+        s: Arc<Mutex<dyn UserSignal + Send>>,
+        name: String,
+        skill_name: String,
+    ) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            s,
+            name,
+            skill_name,
+        }))
     }
-}
 
-#[async_trait(?Send)]
-impl Action for ActSignal {
-    async fn call(&mut self, _context: &ActionContext) -> Result<ActionAnswer> {
-        // TODO: In theory, Lily should ask which parameters for the signal and
-        // which action to be executed but we can't do that right now
-        let m = HashMap::new();
+    /// The action to be executed when the signal triggers, it can be
+    /// requested through the "action" slot either as "skill/action" or just
+    /// "action" for one of this same skill. The slot is left as
+    /// "skill/action" for signals that need to find it again later.
+    fn action_for(&self, data: &mut HashMap<String, String>) -> Result<ActionSet> {
+        let (skill, name) = match data.get("action") {
+            Some(action) => match action.split_once('/') {
+                Some((skill, name)) => (skill.to_string(), name.to_string()),
+                None => (self.skill_name.clone(), action.clone()),
+            },
+            None => ("embedded".to_string(), "timer_ring".to_string()),
+        };
+
         let act_grd = ACT_REG.lock_it();
         let act = act_grd
-            .get("embedded", "say_hello")
-            .expect("Embedded skill 'say_hello' is not available");
+            .get(&skill, &name)
+            .ok_or_else(|| anyhow!("There's no action '{}' in skill '{}'", name, skill))?;
+        data.insert("action".into(), format!("{}/{}", skill, name));
+        Ok(ActionSet::create(Arc::downgrade(act)))
+    }
+}
 
-        let acts = ActionSet::create(Arc::downgrade(act));
+#[async_trait(?Send)]
+impl Action for ActSignal {
+    async fn call(&mut self, context: &ActionContext) -> Result<ActionAnswer> {
+        // Slots (duration, date, label, action...) are what the signal is
+        // configured with
+        let mut m = context
+            .data
+            .as_intent()
+            .map(|i| i.slot_texts())
+            .unwrap_or_default();
+        m.insert("locale".into(), context.locale.clone());
+        if let Some(ref satellite) = context.satellite {
+            m.insert("satellite".into(), satellite.uuid.clone());
+        }
 
-        self.s.lock_it().add(m, "ActSignal", acts)?;
-        ActionAnswer::send_text("Whenever this signals we'll say hello".into(), true)
+        let acts = self.action_for(&mut m)?;
+        self.s.lock_it().add(m, &self.skill_name, acts)?;
+        TranslatedAnswer::new("embedded", "signal_set").build(context, true)
     }
     fn get_name(&self) -> String {
         self.name.clone()
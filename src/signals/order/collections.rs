@@ -8,17 +8,22 @@
 // Standard library
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 // This crate
-use crate::nlu::{IntentData, NluManager, NluManagerStatic, OrderKind};
+use crate::nlu::{AnyNlu, AnyNluManager, IntentData, NluConf, NluManager, OrderKind};
 use crate::signals::order::NluState;
 use crate::vars::mangle;
 
 // Other crates
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
+use tokio::sync::Mutex as AsyncMutex;
 use unic_langid::LanguageIdentifier;
 
+/// A trained NLU, taken out of the map so that it isn't locked while parsing
+pub type SharedNlu = Arc<AsyncMutex<AnyNlu>>;
+
 /*** Config ********************************************************************/
 #[derive(Clone, Debug, Deserialize)]
 pub enum Hook {
@@ -33,35 +38,33 @@ pub enum Hook {
 /*** NluMap *******************************************************************/
 
 #[derive(Debug)]
-pub struct NluMap<M: NluManager + NluManagerStatic + Debug + Send> {
-    map: HashMap<LanguageIdentifier, NluState<M>>,
+pub struct NluMap {
+    map: HashMap<LanguageIdentifier, NluState>,
 }
 
-impl<M: NluManager + NluManagerStatic + Debug + Send> NluMap<M> {
-    pub fn new(langs: Vec<LanguageIdentifier>) -> Self {
+impl NluMap {
+    pub fn new(langs: Vec<LanguageIdentifier>, conf: &NluConf) -> Self {
         let mut managers = HashMap::new();
 
-        // Create a nlu manager per language
+        // Create a nlu manager per language, each might use a different one
         for lang in langs {
-            managers.insert(lang.to_owned(), NluState::new(M::new()));
+            let manager = AnyNluManager::new(conf.backend_for(&lang));
+            managers.insert(lang.to_owned(), NluState::new(manager));
         }
 
         NluMap { map: managers }
     }
 
-    pub fn get_nlu(&mut self, lang: &LanguageIdentifier) -> &mut <M as NluManager>::NluType {
-        const ERR_MSG: &str = "Received language to the NLU was not registered";
-        const NO_NLU_MSG: &str = "received_order can't be called before end_loading";
-
+    pub fn get_nlu(&self, lang: &LanguageIdentifier) -> Result<SharedNlu> {
         self.map
-            .get_mut(lang)
-            .expect(ERR_MSG)
+            .get(lang)
+            .ok_or_else(|| anyhow!("Received language '{}' has not been registered", lang))?
             .nlu
-            .as_mut()
-            .expect(NO_NLU_MSG)
+            .clone()
+            .ok_or_else(|| anyhow!("The NLU for '{}' has not been trained yet", lang))
     }
 
-    pub fn get_mut(&mut self, lang: &LanguageIdentifier) -> Result<&mut NluState<M>> {
+    pub fn get_mut(&mut self, lang: &LanguageIdentifier) -> Result<&mut NluState> {
         let err = || {
             anyhow!(
                 "Received language '{}' has not been registered",
@@ -71,7 +74,7 @@ impl<M: NluManager + NluManagerStatic + Debug + Send> NluMap<M> {
         self.map.get_mut(lang).ok_or_else(err)
     }
 
-    pub fn get_mut_nlu_man(&mut self, lang: &LanguageIdentifier) -> &mut M {
+    pub fn get_mut_nlu_man(&mut self, lang: &LanguageIdentifier) -> &mut AnyNluManager {
         self.map
             .get_mut(lang)
             .expect("Language not registered")
@@ -5,11 +5,14 @@ use std::mem::take;
 use std::sync::{Arc, Mutex, Weak};
 
 // This crate
+use crate::actions::SessionData;
+use crate::skills::hermes;
 use crate::stt::{SttPoolItem, SttSet};
 
 // Other crates
 use anyhow::{anyhow, Result};
 use log::debug;
+use unic_langid::LanguageIdentifier;
 
 thread_local! {
     pub static CAPS_MANAGER: RefCell<CapsManager> = RefCell::new(CapsManager::new());
@@ -17,7 +20,17 @@ thread_local! {
 
 /*** Session*******************************************************************/
 pub struct SessionManager {
-    sessions: HashMap<String, Arc<Mutex<Session>>>,
+    sessions: HashMap<String, ActiveSession>,
+    // The language each satellite was last heard in
+    langs: HashMap<String, LanguageIdentifier>,
+}
+
+// Kept apart from the session itself, which stays locked while it's decoding
+struct ActiveSession {
+    session: Arc<Mutex<Session>>,
+    data: SessionData,
+    // Only these intents will be accepted, if there's any
+    intent_filter: Option<Vec<String>>,
 }
 
 // Session
@@ -25,27 +38,105 @@ impl SessionManager {
     pub fn new() -> Self {
         Self {
             sessions: HashMap::new(),
+            langs: HashMap::new(),
         }
     }
 
     pub fn session_for(&mut self, uuid: String) -> Weak<Mutex<Session>> {
         match self.sessions.entry(uuid.clone()) {
-            Entry::Occupied(o) => Arc::downgrade(o.get()),
+            Entry::Occupied(o) => Arc::downgrade(&o.get().session),
             Entry::Vacant(v) => {
-                let arc = Arc::new(Mutex::new(Session::new(uuid)));
-                Arc::downgrade(v.insert(arc))
+                Arc::downgrade(&v.insert(ActiveSession::new(uuid, None, None)).session)
             }
         }
     }
 
+    /// Start a session on a satellite from outside (e.g: Hermes'
+    /// startSession), returns the session id
+    pub fn start_session(
+        &mut self,
+        uuid: String,
+        intent_filter: Option<Vec<String>>,
+        custom_data: Option<String>,
+    ) -> Result<String> {
+        match self.sessions.entry(uuid.clone()) {
+            Entry::Occupied(_) => Err(anyhow!("{} already has an active session", uuid)),
+            Entry::Vacant(v) => {
+                let active = v.insert(ActiveSession::new(uuid, intent_filter, custom_data));
+                Ok(active.data.id.clone())
+            }
+        }
+    }
+
+    /// Change which intents are accepted for the rest of the session, returns
+    /// the satellite the session is on
+    pub fn continue_session(
+        &mut self,
+        id: &str,
+        intent_filter: Option<Vec<String>>,
+    ) -> Option<String> {
+        let (uuid, active) = self.sessions.iter_mut().find(|(_, a)| a.data.id == id)?;
+        active.intent_filter = intent_filter;
+        Some(uuid.clone())
+    }
+
+    pub fn find_by_id(&self, id: &str) -> Option<String> {
+        self.sessions
+            .iter()
+            .find(|(_, a)| a.data.id == id)
+            .map(|(uuid, _)| uuid.clone())
+    }
+
+    pub fn session_data(&self, uuid: &str) -> Option<SessionData> {
+        self.sessions.get(uuid).map(|a| a.data.clone())
+    }
+
+    pub fn intent_filter(&self, uuid: &str) -> Option<Vec<String>> {
+        self.sessions.get(uuid)?.intent_filter.clone()
+    }
+
+    pub fn set_lang(&mut self, uuid: &str, lang: &LanguageIdentifier) {
+        self.langs.insert(uuid.to_string(), lang.clone());
+    }
+
+    /// Language of a satellite, by it's uuid or by the id of it's session
+    pub fn lang_of(&self, uuid: &str, session_id: Option<&str>) -> Option<LanguageIdentifier> {
+        let from_session = session_id.and_then(|id| self.find_by_id(id));
+        let uuid = from_session.as_deref().unwrap_or(uuid);
+        self.langs.get(uuid).cloned()
+    }
+
     pub fn end_session(&mut self, uuid: &str) -> Result<()> {
         match self.sessions.remove(uuid) {
-            Some(_) => Ok(()),
+            Some(active) => {
+                hermes::notify_session_ended(&active.data, uuid);
+                Ok(())
+            }
             None => Err(anyhow!("{} had no active session", uuid)),
         }
     }
 }
 
+impl ActiveSession {
+    fn new(
+        device: String,
+        intent_filter: Option<Vec<String>>,
+        custom_data: Option<String>,
+    ) -> Self {
+        let data = SessionData {
+            id: new_session_id(),
+            custom_data,
+        };
+        hermes::notify_session_started(&data, &device);
+
+        Self {
+            session: Arc::new(Mutex::new(Session::new(device))),
+            data,
+            intent_filter,
+        }
+    }
+}
+
 pub struct Session {
     device: String,
     curr_utt: Option<SttPoolItem>,
@@ -84,6 +175,12 @@ impl Session {
     }
 }
 
+/// A random id as Hermes uses them (32 hex digits), for sessions and for any
+/// other request which needs one
+pub fn new_session_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
 /*** Capabilities *************************************************************/
 pub struct CapsManager {
     // For now just a map of capabilities, which is a map in which if exists is true
@@ -114,6 +211,11 @@ impl CapsManager {
         }
     }
 
+    /// Uuids of all connected satellites
+    pub fn satellites(&self) -> Vec<String> {
+        self.clients_caps.keys().cloned().collect()
+    }
+
     pub fn disconnected(&mut self, uuid: &str) -> Result<()> {
         match self.clients_caps.remove(uuid) {
             Some(_) => Ok(()),
@@ -124,3 +226,46 @@ impl CapsManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lang_by_satellite_or_session() {
+        let es: LanguageIdentifier = "es-ES".parse().unwrap();
+        let mut sessions = SessionManager::new();
+        sessions.set_lang("kitchen", &es);
+        let id = sessions
+            .start_session("kitchen".to_string(), None, None)
+            .unwrap();
+
+        assert_eq!(sessions.lang_of("kitchen", None), Some(es.clone()));
+        assert_eq!(sessions.lang_of("default", Some(&id)), Some(es));
+        assert_eq!(sessions.lang_of("default", None), None);
+        assert_eq!(sessions.lang_of("default", Some("unknown")), None);
+    }
+
+    #[test]
+    fn session_filter_and_end() {
+        let mut sessions = SessionManager::new();
+        let filter = Some(vec!["say_time".to_string()]);
+        let id = sessions
+            .start_session("kitchen".to_string(), filter.clone(), None)
+            .unwrap();
+        assert!(sessions
+            .start_session("kitchen".to_string(), None, None)
+            .is_err());
+        assert_eq!(sessions.intent_filter("kitchen"), filter);
+
+        assert_eq!(
+            sessions.continue_session(&id, None),
+            Some("kitchen".to_string())
+        );
+        assert_eq!(sessions.intent_filter("kitchen"), None);
+
+        sessions.end_session("kitchen").unwrap();
+        assert_eq!(sessions.find_by_id(&id), None);
+        assert!(sessions.end_session("kitchen").is_err());
+    }
+}
@@ -7,8 +7,10 @@ use std::sync::{atomic::AtomicBool, Mutex, Weak};
 // This crate
 use crate::actions::Action;
 use crate::exts::LockIt;
-use crate::nlu::{EntityDef, IntentData, NluManager, NluManagerStatic};
-use crate::signals::{collections::NluMap, ActMap, ActionSet, SignalOrder};
+use crate::nlu::{EntityDef, IntentData, NluManager};
+use crate::signals::{
+    collections::NluMap, emit, langs_str, nlu_failed, ActMap, ActionSet, SignalOrder, SystemEvent,
+};
 use crate::vars::{mangle, NLU_TRAINING_DELAY};
 
 // Other crates
@@ -19,6 +21,7 @@ use tokio::time::sleep_until;
 use tokio::{
     spawn,
     sync::mpsc,
+    task::spawn_blocking,
     time::{Duration, Instant},
 };
 use unic_langid::LanguageIdentifier;
@@ -56,6 +59,10 @@ enum DynamicNluRequest {
         intent_name: String,
         action: WeakActionRef,
     },
+
+    RemoveSkill {
+        skill: String,
+    },
 }
 
 struct WeakActionRef {
@@ -76,15 +83,17 @@ fn init_dynamic_nlu() -> Result<mpsc::Receiver<DynamicNluRequest>> {
     Ok(consumer)
 }
 
-fn schedule_nlu_compilation<M: NluManager + NluManagerStatic + Debug + Send + 'static>(
-    shared_nlu: Weak<Mutex<NluMap<M>>>,
+pub fn schedule_nlu_compilation(
+    shared_nlu: Weak<Mutex<NluMap>>,
     curr_langs: Vec<LanguageIdentifier>,
 ) {
     *NEXT_NLU_COMPILATION.lock_it() = Instant::now() + Duration::from_millis(NLU_TRAINING_DELAY);
 
-    if IS_NLU_COMPILATION_SCHEDULED.load(Ordering::SeqCst) {
-        IS_NLU_COMPILATION_SCHEDULED.store(true, Ordering::SeqCst);
-
+    // Only one compilation at a time, later requests just bump the time
+    if IS_NLU_COMPILATION_SCHEDULED
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok()
+    {
         spawn(async move {
             let next_compilation = *NEXT_NLU_COMPILATION.lock_it();
             while next_compilation > Instant::now() {
@@ -95,18 +104,33 @@ fn schedule_nlu_compilation<M: NluManager + NluManagerStatic + Debug + Send + 's
             // be useful
             IS_NLU_COMPILATION_SCHEDULED.store(false, Ordering::SeqCst);
 
+            // Training might take a long time, meanwhile everything else
+            // keeps going
             let arc = shared_nlu.upgrade().unwrap();
-            if let Err(e) = SignalOrder::end_loading(&arc, &curr_langs) {
-                error!("Failed to end loading: {}", e);
+            let langs = curr_langs.clone();
+            let res = spawn_blocking(move || SignalOrder::end_loading(&arc, &langs))
+                .await
+                .unwrap_or_else(|e| Err(anyhow!("NLU training panicked: {}", e)));
+            match res {
+                Ok(()) => {
+                    let mut data = HashMap::new();
+                    data.insert("languages".to_string(), langs_str(&curr_langs));
+                    emit(SystemEvent::NluRetrained, None, data);
+                }
+                Err(e) => {
+                    error!("Failed to end loading: {}", e);
+                    nlu_failed(&langs_str(&curr_langs), None, &e);
+                }
             }
         });
     }
 }
 
-pub async fn on_dyn_nlu<M: NluManager + NluManagerStatic + Debug + Send + 'static>(
-    shared_nlu: Weak<Mutex<NluMap<M>>>,
+pub async fn on_dyn_nlu(
+    shared_nlu: Weak<Mutex<NluMap>>,
     intent_map: Weak<Mutex<ActMap>>,
     curr_langs: Vec<LanguageIdentifier>,
+    removed_skills: mpsc::Sender<String>,
 ) -> Result<()> {
     let mut channel = init_dynamic_nlu()?;
     loop {
@@ -143,10 +167,11 @@ pub async fn on_dyn_nlu<M: NluManager + NluManagerStatic + Debug + Send + 'stati
             } => {
                 let arc = shared_nlu.upgrade().unwrap();
                 let mut m = arc.lock_it();
+                let mangled = mangle(&skill, &intent_name);
                 for (lang, intent) in by_lang {
-                    let man = m.get_mut_nlu_man(&lang);
-                    let mangled = mangle(&skill, &intent_name);
-                    man.add_intent(&mangled, intent.into_utterances(&skill));
+                    if let Err(e) = m.add_intent_to_nlu(intent, &mangled, &skill, &lang) {
+                        error!("Failed to add intent {}: {}", &mangled, e);
+                    }
                 }
 
                 schedule_nlu_compilation(shared_nlu.clone(), curr_langs.clone());
@@ -161,6 +186,23 @@ pub async fn on_dyn_nlu<M: NluManager + NluManagerStatic + Debug + Send + 'stati
                 ActionSet::create(action.act_ref),
             ),
 
+            DynamicNluRequest::RemoveSkill { skill } => {
+                {
+                    let arc = shared_nlu.upgrade().unwrap();
+                    let mut m = arc.lock_it();
+                    for lang in &curr_langs {
+                        m.get_mut_nlu_man(lang).remove_skill(&skill);
+                    }
+                }
+                intent_map.upgrade().unwrap().lock_it().remove_skill(&skill);
+                // The order keeps some state of it's own about the intents
+                if removed_skills.send(skill).await.is_err() {
+                    error!("Order is gone, couldn't tell it about a removed skill");
+                }
+
+                schedule_nlu_compilation(shared_nlu.clone(), curr_langs.clone());
+            }
+
             DynamicNluRequest::AddEntity {
                 skill,
                 entity_name,
@@ -229,6 +271,10 @@ pub fn add_entity(
     })
 }
 
+pub fn remove_skill(skill: String) -> Result<()> {
+    send_in_channel(DynamicNluRequest::RemoveSkill { skill })
+}
+
 fn send_in_channel(request: DynamicNluRequest) -> Result<()> {
     DYNAMIC_NLU_CHANNEL
         .lock_it()
@@ -4,74 +4,92 @@ pub mod dynamic_nlu;
 pub mod mqtt;
 
 mod server_actions;
+mod slot_filling;
 
 // Standard library
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 // This crate
 use self::{
-    dev_mgmt::SessionManager,
-    dynamic_nlu::on_dyn_nlu,
+    dev_mgmt::{SessionManager, CAPS_MANAGER},
+    dynamic_nlu::{on_dyn_nlu, schedule_nlu_compilation},
     mqtt::MSG_OUTPUT,
-    server_actions::{on_event, on_nlu_request},
+    server_actions::{on_event, on_nlu_query, on_nlu_request},
+    slot_filling::{extract_from_utterance, next_missing_slot, PendingIntent, SlotFilling},
 };
 use crate::actions::{
-    Action, ActionAnswer, ActionContext, ActionSet, ContextData, MainAnswer, SatelliteData, ACT_REG,
+    Action, ActionAnswer, ActionContext, ActionSet, ContextData, MainAnswer, SatelliteData,
+    SessionData, TranslatedAnswer, ACT_REG,
 };
-use crate::config::Config;
+use crate::config::{Config, GLOBAL_CONF};
 use crate::exts::LockIt;
 use crate::mqtt::MqttApi;
-use crate::nlu::{EntityDef, IntentData, Nlu, NluManager, NluManagerStatic, NluResponseSlot};
+use crate::nlu::{
+    AnyNluManager, EntityDef, IntentData, Nlu, NluConf, NluContext, NluManager, NluResponseSlot,
+    OrderKind, SlotData, SlotValue,
+};
 use crate::queries::{ActQuery, Query};
 use crate::signals::{
-    collections::NluMap, ActMap, ActSignal, Signal, SignalEventShared, UserSignal,
+    collections::{NluMap, SharedNlu},
+    nlu_failed, ActMap, ActSignal, Signal, SignalEventShared, UserSignal,
 };
 use crate::stt::DecodeRes;
-use crate::vars::{mangle, MIN_SCORE_FOR_ACTION};
+use crate::vars::{
+    demangle, is_mangled_from, mangle, FOLLOW_UP_TIMEOUT, MAX_SLOT_FILLING_TRIES,
+    MIN_SCORE_FOR_ACTION,
+};
 
 // Other crates
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use log::{debug, error, info};
-use tokio::{select, sync::mpsc};
+use tokio::{
+    select,
+    sync::{mpsc, Mutex as AsyncMutex},
+};
 use unic_langid::LanguageIdentifier;
 
-#[cfg(not(feature = "devel_rasa_nlu"))]
-use crate::nlu::SnipsNluManager;
-
-#[cfg(feature = "devel_rasa_nlu")]
-use crate::nlu::RasaNluManager;
-
 #[derive(Debug)]
-pub struct NluState<M: NluManager + NluManagerStatic + Send> {
-    manager: M,
-    nlu: Option<M::NluType>,
+pub struct NluState {
+    manager: AnyNluManager,
+    nlu: Option<SharedNlu>,
 }
 
-impl<M: NluManager + NluManagerStatic + Send> NluState<M> {
-    fn get_mut_nlu_man(&mut self) -> &mut M {
+impl NluState {
+    fn get_mut_nlu_man(&mut self) -> &mut AnyNluManager {
         &mut self.manager
     }
 
-    fn new(manager: M) -> Self {
+    fn new(manager: AnyNluManager) -> Self {
         Self { manager, nlu: None }
     }
 }
 #[derive(Debug)]
-pub struct SignalOrder<M: NluManager + NluManagerStatic + Debug + Send> {
+pub struct SignalOrder {
     intent_map: Arc<Mutex<ActMap>>,
-    nlu: Arc<Mutex<NluMap<M>>>,
+    nlu: Arc<Mutex<NluMap>>,
     demangled_names: HashMap<String, String>,
+
+    // Slots of each intent (by mangled name), needed to ask for those missing
+    slots_data: HashMap<String, HashMap<LanguageIdentifier, HashMap<String, SlotData>>>,
+    // Satellites which we are asking for a slot
+    slot_filling: HashMap<String, SlotFilling>,
+    // Last intent of each satellite, for follow-ups of an ongoing session
+    follow_ups: HashMap<String, (NluContext, Instant)>,
 }
 
-impl<M: NluManager + NluManagerStatic + Debug + Send + 'static> SignalOrder<M> {
-    pub fn new(langs: Vec<LanguageIdentifier>) -> Self {
+impl SignalOrder {
+    pub fn new(langs: Vec<LanguageIdentifier>, conf: &NluConf) -> Self {
         SignalOrder {
             intent_map: Arc::new(Mutex::new(ActMap::new())),
-            nlu: Arc::new(Mutex::new(NluMap::new(langs))),
+            nlu: Arc::new(Mutex::new(NluMap::new(langs, conf))),
             demangled_names: HashMap::new(),
+            slots_data: HashMap::new(),
+            slot_filling: HashMap::new(),
+            follow_ups: HashMap::new(),
         }
     }
 
@@ -81,116 +99,442 @@ impl<M: NluManager + NluManagerStatic + Debug + Send + 'static> SignalOrder<M> {
         event_signal: SignalEventShared,
         lang: &LanguageIdentifier,
         satellite: String,
+        session: Option<SessionData>,
+        intent_filter: Option<Vec<String>>,
     ) -> Result<bool> {
         debug!("Heard from user: {:?}", decode_res);
 
-        fn make_context(lang: &LanguageIdentifier, uuid: String) -> ActionContext {
-            ActionContext {
-                locale: lang.to_string(),
-                satellite: Some(SatelliteData { uuid }),
-                data: ContextData::Event {
-                    event: "__TO_FILL_THIS__".to_string(),
-                },
+        let hypothesis = decode_res.map(|d| d.hypothesis).filter(|h| !h.is_empty());
+
+        let ans = match self.take_slot_filling(&satellite) {
+            Some(filling) => {
+                self.continue_slot_filling(filling, hypothesis, lang, satellite.clone(), session)
+                    .await?
             }
+            None => match hypothesis {
+                Some(hypothesis) => {
+                    self.parse_order(
+                        hypothesis,
+                        event_signal,
+                        lang,
+                        satellite.clone(),
+                        session,
+                        intent_filter.as_deref(),
+                    )
+                    .await?
+                }
+                None => {
+                    event_signal
+                        .lock_it()
+                        .call("empty_reco", make_context(lang, satellite.clone()))
+                        .await
+                }
+            },
+        };
+
+        let s_end = process_answers(ans, lang, satellite.clone())?;
+        if s_end {
+            self.follow_ups.remove(&satellite);
         }
 
-        let ans = match decode_res {
+        Ok(s_end)
+    }
+
+    /// Call an intent directly, as if the NLU had understood it with these
+    /// slots. Without a satellite the answers are said on all of them.
+    pub async fn trigger_intent(
+        &mut self,
+        intent_name: &str,
+        slots: HashMap<String, String>,
+        lang: &LanguageIdentifier,
+        satellite: Option<String>,
+        session: Option<SessionData>,
+    ) -> Result<bool> {
+        let mangled = self.find_intent(intent_name)?;
+        let intent = PendingIntent {
+            name: mangled,
+            input: String::new(),
+            confidence: 1.0,
+            // Whoever triggers it only gives us text
+            slots: slots
+                .into_iter()
+                .map(|(name, value)| (name, SlotValue::Text(value)))
+                .collect(),
+        };
+
+        match satellite {
+            Some(satellite) => {
+                let ans = self
+                    .call_intent(intent, lang, satellite.clone(), session)
+                    .await?;
+                let s_end = process_answers(ans, lang, satellite.clone())?;
+                if s_end {
+                    self.follow_ups.remove(&satellite);
+                }
+                Ok(s_end)
+            }
             None => {
-                event_signal
-                    .lock_it()
-                    .call("empty_reco", make_context(lang, satellite.clone()))
+                let context = ActionContext {
+                    locale: lang.to_string(),
+                    satellite: None,
+                    data: ContextData::Intent {
+                        intent: crate::actions::IntentData {
+                            name: self.demangle(&intent.name).to_string(),
+                            input: intent.input,
+                            slots: intent.slots,
+                            confidence: intent.confidence,
+                        },
+                    },
+                };
+                let ans = self.call_mapping(&intent.name, &context).await;
+                announce_answers(ans.unwrap_or_default(), lang, None);
+                Ok(true)
+            }
+        }
+    }
+
+    // Intents are asked for as "skill/intent" or just "intent" if there's
+    // only one with that name
+    fn find_intent(&self, name: &str) -> Result<String> {
+        let map = self.intent_map.lock_it();
+        let found: Vec<String> = match name.split_once('/') {
+            Some((skill, intent)) => {
+                let mangled = mangle(skill, intent);
+                map.get(&mangled).map(|_| mangled).into_iter().collect()
+            }
+            None => map
+                .names()
+                .filter(|mangled| self.demangle(mangled) == name)
+                .cloned()
+                .collect(),
+        };
+
+        match found.as_slice() {
+            [mangled] => Ok(mangled.clone()),
+            [] => Err(anyhow!("There's no intent \"{}\"", name)),
+            _ => Err(anyhow!(
+                "More than one skill has intent \"{}\", use \"skill/intent\"",
+                name
+            )),
+        }
+    }
+
+    async fn parse_order(
+        &mut self,
+        hypothesis: String,
+        event_signal: SignalEventShared,
+        lang: &LanguageIdentifier,
+        satellite: String,
+        session: Option<SessionData>,
+        intent_filter: Option<&[String]>,
+    ) -> Result<Option<Vec<ActionAnswer>>> {
+        let context = self
+            .follow_ups
+            .remove(&satellite)
+            .filter(|(_, when)| when.elapsed() < Duration::from_millis(FOLLOW_UP_TIMEOUT))
+            .map(|(context, _)| context);
+
+        let nlu = self.nlu.lock_it().get_nlu(lang)?;
+        let result = {
+            let nlu = nlu.lock().await;
+            match &context {
+                Some(context) => nlu.parse_with_context(&hypothesis, context).await,
+                None => nlu.parse(&hypothesis).await,
+            }
+            .map_err(|err| {
+                nlu_failed(&lang.to_string(), Some(&satellite), &err);
+                anyhow!("Failed to parse: {:?}", err)
+            })?
+        };
+        info!("{:?}", result);
+
+        // Do action if at least we are 80% confident on
+        // what we got
+        if result.confidence >= MIN_SCORE_FOR_ACTION {
+            // Sessions might only accept some intents
+            let intent_name = result
+                .name
+                .filter(|name| self.passes_filter(name, intent_filter));
+            if let Some(intent_name) = intent_name {
+                let intent = PendingIntent {
+                    name: intent_name,
+                    input: hypothesis,
+                    confidence: result.confidence,
+                    slots: add_slots(result.slots),
+                };
+                return self.call_intent(intent, lang, satellite, session).await;
+            }
+        }
+
+        Ok(event_signal
+            .lock_it()
+            .call("unrecognized", make_context(lang, satellite))
+            .await)
+    }
+
+    // Calls the intent's actions, unless some required slot is missing, in
+    // that case we'll ask the user for it.
+    async fn call_intent(
+        &mut self,
+        intent: PendingIntent,
+        lang: &LanguageIdentifier,
+        satellite: String,
+        session: Option<SessionData>,
+    ) -> Result<Option<Vec<ActionAnswer>>> {
+        let missing = self
+            .get_slots_data(&intent.name, lang)
+            .and_then(|slots_data| next_missing_slot(slots_data, &intent.slots));
+
+        if let Some(slot) = missing {
+            debug!(
+                "Intent {} lacks slot {}, asking for it",
+                &intent.name, &slot
+            );
+            let filling = SlotFilling::new(intent, slot);
+            let ans = self.slot_prompt(&filling, lang, satellite.clone(), false)?;
+            self.slot_filling.insert(satellite, filling);
+            return Ok(Some(vec![ans]));
+        }
+
+        info!("Let's call an action");
+        let context = NluContext {
+            intent: intent.name.clone(),
+            slots: intent
+                .slots
+                .iter()
+                .map(|(name, value)| NluResponseSlot {
+                    name: name.clone(),
+                    raw_value: value.raw(),
+                    value: value.clone(),
+                    entity: None,
+                    range: None,
+                })
+                .collect(),
+        };
+        self.follow_ups
+            .insert(satellite.clone(), (context, Instant::now()));
+
+        let intent_data = crate::actions::IntentData {
+            name: self.demangle(&intent.name).to_string(),
+            input: intent.input,
+            slots: intent.slots,
+            confidence: intent.confidence,
+        };
+
+        let mut intent_context = make_context(lang, satellite.clone());
+        intent_context.satellite = Some(SatelliteData {
+            uuid: satellite,
+            session,
+        });
+        intent_context.data = ContextData::Intent {
+            intent: intent_data,
+        };
+
+        let answers = self.call_mapping(&intent.name, &intent_context).await;
+        info!("Action called");
+        Ok(answers)
+    }
+
+    // The map isn't kept locked while the actions run, some (e.g: Hermes
+    // ones) wait for others which need it
+    async fn call_mapping(
+        &self,
+        intent_name: &str,
+        context: &ActionContext,
+    ) -> Option<Vec<ActionAnswer>> {
+        let act_set = self.intent_map.lock_it().get(intent_name).cloned()?;
+        Some(act_set.call_all(context).await)
+    }
+
+    async fn continue_slot_filling(
+        &mut self,
+        mut filling: SlotFilling,
+        hypothesis: Option<String>,
+        lang: &LanguageIdentifier,
+        satellite: String,
+        session: Option<SessionData>,
+    ) -> Result<Option<Vec<ActionAnswer>>> {
+        let value = match hypothesis {
+            Some(hypothesis) => {
+                self.extract_slot_value(&filling, &hypothesis, lang, &satellite)
+                    .await?
+            }
+            None => None,
+        };
+
+        match value {
+            Some(value) => {
+                filling.intent.slots.insert(filling.slot, value);
+                self.call_intent(filling.intent, lang, satellite, session)
                     .await
             }
-            Some(decode_res) => {
-                if !decode_res.hypothesis.is_empty() {
-                    let mut m = self.nlu.lock_it();
-                    let nlu = m.get_nlu(lang);
-                    let result = nlu
-                        .parse(&decode_res.hypothesis)
-                        .await
-                        .map_err(|err| anyhow!("Failed to parse: {:?}", err))?;
-                    info!("{:?}", result);
-
-                    // Do action if at least we are 80% confident on
-                    // what we got
-                    if result.confidence >= MIN_SCORE_FOR_ACTION {
-                        if let Some(intent_name) = result.name {
-                            info!("Let's call an action");
-
-                            let intent_data = crate::actions::IntentData {
-                                name: self.demangle(&intent_name).to_string(),
-                                input: decode_res.hypothesis,
-                                slots: add_slots(result.slots),
-                                confidence: result.confidence,
-                            };
-
-                            let mut intent_context = make_context(lang, satellite.clone());
-                            intent_context.data = ContextData::Intent {
-                                intent: intent_data,
-                            };
-
-                            let answers = self
-                                .intent_map
-                                .lock_it()
-                                .call_mapping(&intent_name, &intent_context)
-                                .await;
-                            info!("Action called");
-                            answers
-                        } else {
-                            event_signal
-                                .lock_it()
-                                .call("unrecognized", make_context(lang, satellite.clone()))
-                                .await
-                        }
-                    } else {
-                        event_signal
-                            .lock_it()
-                            .call("unrecognized", make_context(lang, satellite.clone()))
-                            .await
-                    }
+            None => {
+                filling.failed_tries += 1;
+                if filling.failed_tries >= MAX_SLOT_FILLING_TRIES {
+                    info!(
+                        "Couldn't get slot {} for {}, giving up",
+                        &filling.slot, &filling.intent.name
+                    );
+                    let ans = TranslatedAnswer::new("embedded", "slot_filling_failed")
+                        .build(&make_context(lang, satellite), true)?;
+                    Ok(Some(vec![ans]))
                 } else {
-                    event_signal
-                        .lock_it()
-                        .call("empty_reco", make_context(lang, satellite.clone()))
-                        .await
+                    let ans = self.slot_prompt(&filling, lang, satellite.clone(), true)?;
+                    self.slot_filling.insert(satellite, filling);
+                    Ok(Some(vec![ans]))
                 }
             }
+        }
+    }
+
+    async fn extract_slot_value(
+        &mut self,
+        filling: &SlotFilling,
+        hypothesis: &str,
+        lang: &LanguageIdentifier,
+        satellite: &str,
+    ) -> Result<Option<SlotValue>> {
+        let nlu = self.nlu.lock_it().get_nlu(lang)?;
+
+        // The NLU might know better how to get the value out of the sentence,
+        // telling it which intent we are at lets it look just for slots
+        let context = NluContext {
+            intent: filling.intent.name.clone(),
+            slots: Vec::new(),
+        };
+        let from_nlu = nlu
+            .lock()
+            .await
+            .parse_with_context(hypothesis, &context)
+            .await
+            .map_err(|err| {
+                nlu_failed(&lang.to_string(), Some(satellite), &err);
+                anyhow!("Failed to parse: {:?}", err)
+            })?
+            .slots
+            .into_iter()
+            .find(|s| s.name == filling.slot)
+            .map(|s| s.value);
+        if from_nlu.is_some() {
+            return Ok(from_nlu);
+        }
+
+        let slot = match self
+            .get_slots_data(&filling.intent.name, lang)
+            .and_then(|slots_data| slots_data.get(&filling.slot))
+        {
+            Some(slot) => slot,
+            None => return Ok(None),
         };
+        let value = match &slot.slot_type {
+            OrderKind::Def(def) => extract_from_utterance(def, hypothesis),
+            // Builtin entities (e.g: snips/number) are not known here, only
+            // the NLU can make sense of them
+            OrderKind::Ref(name) => self
+                .nlu
+                .lock_it()
+                .get_mut_nlu_man(lang)
+                .entity(name)
+                .and_then(|def| extract_from_utterance(def, hypothesis)),
+        };
+
+        Ok(value)
+    }
 
-        process_answers(ans, lang, satellite.clone())
+    fn slot_prompt(
+        &self,
+        filling: &SlotFilling,
+        lang: &LanguageIdentifier,
+        satellite: String,
+        is_reprompt: bool,
+    ) -> Result<ActionAnswer> {
+        let slot = self
+            .get_slots_data(&filling.intent.name, lang)
+            .and_then(|slots_data| slots_data.get(&filling.slot));
+        let text = slot.and_then(|s| {
+            if is_reprompt {
+                s.reprompt.clone().or_else(|| s.prompt.clone())
+            } else {
+                s.prompt.clone()
+            }
+        });
+
+        // The slot's name is meant for the skill, not for the user
+        match text {
+            Some(text) => ActionAnswer::send_text(text, false),
+            None => TranslatedAnswer::new("embedded", "slot_prompt")
+                .build(&make_context(lang, satellite), false),
+        }
+    }
+
+    /// Forget anything about the intents of a skill which is gone
+    pub fn remove_skill(&mut self, skill_name: &str) {
+        let is_other = |mangled: &str| !is_mangled_from(skill_name, mangled);
+        self.demangled_names.retain(|mangled, _| is_other(mangled));
+        self.slots_data.retain(|mangled, _| is_other(mangled));
+        self.slot_filling
+            .retain(|_, filling| is_other(&filling.intent.name));
+        self.follow_ups
+            .retain(|_, (context, _)| is_other(&context.intent));
     }
 
-    pub fn end_loading(nlu: &Arc<Mutex<NluMap<M>>>, langs: &[LanguageIdentifier]) -> Result<()> {
+    fn take_slot_filling(&mut self, satellite: &str) -> Option<SlotFilling> {
+        self.slot_filling
+            .remove(satellite)
+            .filter(|filling| !filling.is_expired())
+    }
+
+    #[cfg(test)]
+    pub fn nlu_map(&self) -> Arc<Mutex<NluMap>> {
+        self.nlu.clone()
+    }
+
+    fn get_slots_data(
+        &self,
+        intent_name: &str,
+        lang: &LanguageIdentifier,
+    ) -> Option<&HashMap<String, SlotData>> {
+        self.slots_data.get(intent_name)?.get(lang)
+    }
+
+    pub fn end_loading(nlu: &Arc<Mutex<NluMap>>, langs: &[LanguageIdentifier]) -> Result<()> {
         for lang in langs {
-            let (train_path, model_path) = M::get_paths();
-            let mut m = nlu.lock_it();
-            let nlu = m.get_mut(lang)?;
-            if M::is_lang_compatible(lang) {
-                nlu.manager.ready_lang(lang)?;
-                nlu.nlu = Some(nlu.manager.train(
-                    &train_path,
-                    &model_path.join("main_model.json"),
-                    lang,
-                )?);
+            // Training takes it's time, the NLU keeps being usable (and
+            // intents can still be added) until the new one is ready
+            let mut manager = nlu.lock_it().get_mut(lang)?.manager.clone();
+            let (train_path, model_path) = manager.get_paths();
+            if manager.is_lang_compatible(lang) {
+                manager.ready_lang(lang)?;
+                let trained =
+                    manager.train(&train_path, &model_path.join("main_model.json"), lang)?;
+                nlu.lock_it().get_mut(lang)?.nlu = Some(Arc::new(AsyncMutex::new(trained)));
             } else {
                 return Err(anyhow!(
-                    "{} NLU is not compatible with the selected language",
-                    M::name()
+                    "{} NLU is not compatible with \"{}\"",
+                    manager.name(),
+                    lang
                 ));
             }
 
-            info!("Initted Nlu");
+            info!("Initted {} Nlu for {}", manager.name(), lang);
         }
 
         Ok(())
     }
 }
 
-impl<M: NluManager + NluManagerStatic + Debug + Send> SignalOrder<M> {
-    fn demangle<'a>(&'a self, mangled: &str) -> &'a str {
+impl SignalOrder {
+    fn demangle<'a>(&'a self, mangled: &'a str) -> &'a str {
+        // Intents added while running (dynamic NLU) are not in the map
         self.demangled_names
             .get(mangled)
-            .expect("Mangled name was not found")
+            .map(String::as_str)
+            .unwrap_or_else(|| demangle(mangled))
+    }
+
+    fn passes_filter(&self, mangled: &str, intent_filter: Option<&[String]>) -> bool {
+        let name = self.demangle(mangled);
+        intent_filter.map_or(true, |filter| filter.iter().any(|i| i == name))
     }
 
     fn add_intent(
@@ -205,6 +549,10 @@ impl<M: NluManager + NluManagerStatic + Debug + Send> SignalOrder<M> {
         {
             let mut nlu_grd = self.nlu.lock_it();
             for (lang, sig_arg) in sig_arg {
+                self.slots_data
+                    .entry(mangled.clone())
+                    .or_default()
+                    .insert(lang.clone(), sig_arg.slots.clone());
                 nlu_grd.add_intent_to_nlu(sig_arg, &mangled, skill_name, lang)?;
             }
         }
@@ -223,7 +571,7 @@ impl<M: NluManager + NluManagerStatic + Debug + Send> SignalOrder<M> {
         signal_name: String,
         signal: Arc<Mutex<dyn UserSignal + Send>>,
     ) -> Result<()> {
-        let arc = ActSignal::new(signal, signal_name);
+        let arc = ActSignal::new(signal, signal_name, skill_name.to_string());
         let weak = Arc::downgrade(&arc);
         ACT_REG
             .lock_it()
@@ -272,9 +620,11 @@ impl<M: NluManager + NluManagerStatic + Debug + Send> SignalOrder<M> {
 }
 
 #[async_trait(?Send)]
-impl<M: NluManager + NluManagerStatic + Debug + Send + 'static> Signal for SignalOrder<M> {
+impl Signal for SignalOrder {
     fn end_load(&mut self, curr_langs: &[LanguageIdentifier]) -> Result<()> {
-        Self::end_loading(&self.nlu, curr_langs)
+        // Trained in the background, along with anything added until then
+        schedule_nlu_compilation(Arc::downgrade(&self.nlu), curr_langs.to_vec());
+        Ok(())
     }
 
     async fn event_loop(
@@ -284,27 +634,56 @@ impl<M: NluManager + NluManagerStatic + Debug + Send + 'static> Signal for Signa
         curr_langs: &[LanguageIdentifier],
     ) -> Result<()> {
         let def_lang = curr_langs.get(0);
-        let mut mqtt = MqttApi::new(def_lang.expect("We need at least one language").clone())?;
+        let (nlu_query_sender, nlu_query_receiver) = mpsc::channel(100);
+        let mut mqtt = MqttApi::new(
+            def_lang.expect("We need at least one language").clone(),
+            config,
+            nlu_query_sender,
+        )?;
 
         let (nlu_sender, nlu_receiver) = mpsc::channel(100);
         let (event_sender, event_receiver) = mpsc::channel(100);
+        let (trigger_sender, trigger_receiver) = mpsc::channel(100);
+        let (removed_sender, removed_receiver) = mpsc::channel(100);
         let sessions = Arc::new(Mutex::new(SessionManager::new()));
+        let nlu_query_fut = on_nlu_query(
+            nlu_query_receiver,
+            Arc::downgrade(&self.nlu),
+            def_lang,
+            sessions.clone(),
+        );
         let dyn_ent_fut = on_dyn_nlu(
             Arc::downgrade(&self.nlu),
             Arc::downgrade(&self.intent_map),
             curr_langs.to_vec(),
+            removed_sender,
         );
         select! {
             e = dyn_ent_fut => {Err(anyhow!("Dynamic entitying failed: {:?}",e))}
-            e = mqtt.api_loop(config, curr_langs, def_lang, sessions.clone(), nlu_sender, event_sender) => {e}
-            e = on_nlu_request(config, nlu_receiver, signal_event.clone(), curr_langs, self, sessions) => {Err(anyhow!("Nlu request failed: {:?}", e))}
+            e = mqtt.api_loop(config, curr_langs, def_lang, sessions.clone(), nlu_sender, event_sender, trigger_sender) => {e}
+            e = on_nlu_request(config, nlu_receiver, trigger_receiver, removed_receiver, signal_event.clone(), curr_langs, self, sessions) => {Err(anyhow!("Nlu request failed: {:?}", e))}
             e = on_event(event_receiver, signal_event, def_lang) => {Err(anyhow!("Event handling failed: {:?}", e))}
+            e = nlu_query_fut => {Err(anyhow!("Hermes NLU query handling failed: {:?}", e))}
         }
     }
 }
 
+fn make_context(lang: &LanguageIdentifier, uuid: String) -> ActionContext {
+    ActionContext {
+        locale: lang.to_string(),
+        satellite: Some(SatelliteData {
+            uuid,
+            session: None,
+        }),
+        data: ContextData::Event {
+            event: "__TO_FILL_THIS__".to_string(),
+            data: HashMap::new(),
+        },
+    }
+}
+
 /// Transform the in the response into a HashMap for sending
-fn add_slots(slots: Vec<NluResponseSlot>) -> HashMap<String, String> {
+fn add_slots(slots: Vec<NluResponseSlot>) -> HashMap<String, SlotValue> {
     let mut result = HashMap::new();
     for slot in slots.into_iter() {
         result.insert(slot.name, slot.value);
@@ -321,6 +700,7 @@ fn process_answer(ans: ActionAnswer, lang: &LanguageIdentifier, uuid: String) ->
                 match ans.answer {
                     MainAnswer::Sound(s) => output.send_audio(s, uuid),
                     MainAnswer::Text(t) => output.answer(t, lang, uuid),
+                    MainAnswer::Nothing => Ok(()),
                 }?;
             }
             _ => {}
@@ -361,13 +741,26 @@ pub fn process_answers(
     }
 }
 
-#[cfg(not(feature = "devel_rasa_nlu"))]
-pub type CurrentNluManager = SnipsNluManager;
-#[cfg(feature = "devel_rasa_nlu")]
-pub type CurrentNluManager = RasaNluManager;
-
-pub type SignalOrderCurrent = SignalOrder<CurrentNluManager>;
+/// Say the answers of something that wasn't asked by any satellite (a sensor,
+/// a script...), on the one given or on every connected satellite otherwise
+pub fn announce_answers(
+    ans: Vec<ActionAnswer>,
+    lang: &LanguageIdentifier,
+    satellite: Option<&str>,
+) {
+    let satellites = match satellite {
+        Some(uuid) => vec![uuid.to_string()],
+        None => CAPS_MANAGER.with(|c| c.borrow().satellites()),
+    };
+
+    for uuid in satellites {
+        if let Err(e) = process_answers(Some(ans.clone()), lang, uuid.clone()) {
+            error!("Couldn't announce on {}: {}", uuid, e);
+        }
+    }
+}
 
-pub fn new_signal_order(langs: Vec<LanguageIdentifier>) -> SignalOrder<CurrentNluManager> {
-    SignalOrder::new(langs)
+pub fn new_signal_order(langs: Vec<LanguageIdentifier>) -> SignalOrder {
+    let conf = GLOBAL_CONF.with(|c| c.borrow().nlu.clone());
+    SignalOrder::new(langs, &conf)
 }
@@ -8,6 +8,8 @@ use crate::signals::order::{
     dev_mgmt::{SessionManager, CAPS_MANAGER},
     server_actions::SendData,
 };
+use crate::signals::{emit, SystemEvent};
+use crate::skills::hermes::AUDIO_SERVER_SITES;
 use crate::tts::{Gender, Tts, TtsData, TtsFactory, VoiceDescr};
 
 use anyhow::Result;
@@ -16,9 +18,19 @@ use lily_common::{audio::Audio, communication::*, vars::DEFAULT_SAMPLES_PER_SECO
 use log::{error, info, warn};
 use rmp_serde::{decode, encode};
 use rumqttc::{AsyncClient, QoS};
+use serde::de::DeserializeOwned;
 use tokio::sync::mpsc;
 use unic_langid::LanguageIdentifier;
 
+// Messages that can come from outside of Lily (dashboards, buttons...) can be
+// JSON too, which is easier to make than MessagePack
+fn decode_external<T: DeserializeOwned>(payload: &Bytes) -> Result<T> {
+    match decode::from_read(std::io::Cursor::new(payload)) {
+        Ok(msg) => Ok(msg),
+        Err(_) => Ok(serde_json::from_slice(payload)?),
+    }
+}
+
 thread_local! {
     pub static MSG_OUTPUT: RefCell<Option<MqttInterfaceOutput>> = RefCell::new(None);
 }
@@ -40,15 +52,21 @@ impl MqttInterfaceIn {
         let input: MsgNewSatellite = decode::from_read(std::io::Cursor::new(payload))?;
         let uuid2 = &input.uuid;
         let caps = input.caps;
+        let mut data = HashMap::new();
+        data.insert("satellite".to_string(), uuid2.clone());
+        data.insert("caps".to_string(), caps.join(","));
         CAPS_MANAGER.with(|c| c.borrow_mut().add_client(uuid2, caps));
         let output = encode::to_vec(&MsgWelcome {
             conf: config.to_client_conf(),
-            satellite: input.uuid,
+            satellite: input.uuid.clone(),
         })?;
-        Ok(client
+        client
             .lock_it()
             .publish("lily/satellite_welcome", QoS::AtMostOnce, false, output)
-            .await?)
+            .await?;
+
+        emit(SystemEvent::SatelliteConnected, Some(input.uuid), data);
+        Ok(())
     }
 
     pub async fn handle_nlu_process(
@@ -65,16 +83,30 @@ impl MqttInterfaceIn {
         payload: &Bytes,
         channel_event: &mpsc::Sender<MsgEvent>,
     ) -> Result<()> {
-        let msg: MsgEvent = decode::from_read(std::io::Cursor::new(payload))?;
+        let msg: MsgEvent = decode_external(payload)?;
         Ok(channel_event.send(msg).await?)
     }
 
+    pub async fn handle_intent_trigger(
+        &mut self,
+        payload: &Bytes,
+        channel_trigger: &mpsc::Sender<MsgTriggerIntent>,
+    ) -> Result<()> {
+        let msg: MsgTriggerIntent = decode_external(payload)?;
+        Ok(channel_trigger.send(msg).await?)
+    }
+
     pub async fn handle_disconnected(&mut self, payload: &Bytes) -> Result<()> {
         let msg: MsgGoodbye = decode::from_read(std::io::Cursor::new(payload))?;
 
         // This error has nothing to do with connection, shouldn't break it
-        if let Err(e) = CAPS_MANAGER.with(|c| c.borrow_mut().disconnected(&msg.satellite)) {
-            warn!("{}", &e.to_string())
+        match CAPS_MANAGER.with(|c| c.borrow_mut().disconnected(&msg.satellite)) {
+            Ok(()) => {
+                let mut data = HashMap::new();
+                data.insert("satellite".to_string(), msg.satellite);
+                emit(SystemEvent::SatelliteLost, None, data);
+            }
+            Err(e) => warn!("{}", &e.to_string()),
         }
 
         Ok(())
@@ -89,6 +121,9 @@ impl MqttInterfaceIn {
             .subscribe("lily/nlu_process", QoS::AtMostOnce)
             .await?;
         client_raw.subscribe("lily/event", QoS::AtMostOnce).await?;
+        client_raw
+            .subscribe("lily/intent/trigger", QoS::AtMostOnce)
+            .await?;
         client_raw
             .subscribe("lily/disconnected", QoS::ExactlyOnce)
             .await?;
@@ -159,27 +194,34 @@ impl MqttInterfaceOut {
         sessions: &Arc<Mutex<SessionManager>>,
         client: &Arc<Mutex<AsyncClient>>,
     ) -> Result<()> {
+        // Hermes audio server sites are talked to in their own way
+        let is_hermes_site = AUDIO_SERVER_SITES.with(|s| s.borrow().is_site(&uuid_str));
+
         let audio_data = match msg_data {
             SendData::Audio(audio) => audio,
             SendData::String((str, lang)) => {
-                async fn synth_text(tts: &mut Box<dyn Tts>, input: &str) -> Audio {
+                async fn synth_text(tts: &mut Box<dyn Tts>, input: &str, uuid: &str) -> Audio {
                     match tts.synth_text(input).await {
                         Ok(a) => a,
                         Err(e) => {
                             error!("Error while synthing voice: {}", e);
+                            let mut data = HashMap::new();
+                            data.insert("satellite".to_string(), uuid.to_string());
+                            data.insert("error".to_string(), e.to_string());
+                            emit(SystemEvent::TtsFailed, None, data);
                             Audio::new_empty(DEFAULT_SAMPLES_PER_SECOND)
                         }
                     }
                 }
 
                 match tts_set.get_mut(&lang) {
-                    Some(tts) => synth_text(tts, &str).await,
+                    Some(tts) => synth_text(tts, &str, &uuid_str).await,
                     None => {
                         warn!("Received answer for language {:?} not in the config or that has no TTS, using default", lang);
                         let def = def_lang
                             .expect("There's no language assigned, need one at least");
                         match tts_set.get_mut(def) {
-                            Some(tts) => synth_text(tts, &str).await,
+                            Some(tts) => synth_text(tts, &str, &uuid_str).await,
                             None => {
                                 warn!("Default has no tts either, sending empty audio");
                                 Audio::new_empty(DEFAULT_SAMPLES_PER_SECOND)
@@ -188,11 +230,29 @@ impl MqttInterfaceOut {
                     }
                 }
             }
+            SendData::EndSession => {
+                if let Err(e) = sessions.lock().expect("POISON_MSG").end_session(&uuid_str) {
+                    warn!("{}", e);
+                }
+                if is_hermes_site {
+                    AUDIO_SERVER_SITES.with(|s| s.borrow_mut().stop_listening(&uuid_str));
+                    return Ok(());
+                }
+                return Self::publish_session(&uuid_str, "session_end", client).await;
+            }
+            SendData::KeepListening => {
+                if is_hermes_site {
+                    AUDIO_SERVER_SITES.with(|s| s.borrow_mut().start_listening(&uuid_str));
+                    return Ok(());
+                }
+                return Self::publish_session(&uuid_str, "keep_listening", client).await;
+            }
         };
 
-        if let Err(e) = sessions.lock().expect("POISON_MSG").end_session(&uuid_str) {
-            warn!("{}", e);
+        if is_hermes_site {
+            return Self::play_in_hermes_site(audio_data, &uuid_str, client).await;
         }
+
         let msg_pack = encode::to_vec(&MsgAnswer {
             audio: Some(audio_data.into_encoded()?),
             text: None,
@@ -208,6 +268,50 @@ impl MqttInterfaceOut {
             .await?;
         Ok(())
     }
+
+    async fn play_in_hermes_site(
+        audio: Audio,
+        site_id: &str,
+        client: &Arc<Mutex<AsyncClient>>,
+    ) -> Result<()> {
+        let wav = match audio.into_wav() {
+            Ok(wav) => wav,
+            Err(e) => {
+                warn!("Answer for site {} can't be played: {}", site_id, e);
+                return Ok(());
+            }
+        };
+
+        let request_id = AUDIO_SERVER_SITES.with(|s| s.borrow_mut().play(site_id));
+        client
+            .lock_it()
+            .publish(
+                &format!("hermes/audioServer/{}/playBytes/{}", site_id, request_id),
+                QoS::AtLeastOnce,
+                false,
+                wav,
+            )
+            .await?;
+        Ok(())
+    }
+
+    // Tell the satellite whether it should go back to waiting for the hotword
+    async fn publish_session(
+        uuid_str: &str,
+        kind: &str,
+        client: &Arc<Mutex<AsyncClient>>,
+    ) -> Result<()> {
+        client
+            .lock_it()
+            .publish(
+                &format!("lily/{}/{}", uuid_str, kind),
+                QoS::AtLeastOnce,
+                false,
+                Vec::new(),
+            )
+            .await?;
+        Ok(())
+    }
 }
 
 pub struct MqttInterfaceOutput {
@@ -230,4 +334,16 @@ impl MqttInterfaceOutput {
         self.client.try_send((SendData::Audio(audio), to)).unwrap();
         Ok(())
     }
+
+    /// Either end the session or keep the satellite listening for a follow-up,
+    /// this goes after the answers so that the satellite says them first.
+    pub fn session_status(&mut self, should_end: bool, to: String) -> Result<()> {
+        let data = if should_end {
+            SendData::EndSession
+        } else {
+            SendData::KeepListening
+        };
+        self.client.try_send((data, to)).unwrap();
+        Ok(())
+    }
 }
@@ -1,14 +1,19 @@
 // Standard library
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::Cursor;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 
 use crate::actions::SatelliteData;
 // This crate
 use crate::config::Config;
 use crate::exts::LockIt;
-use crate::nlu::{NluManager, NluManagerStatic};
-use crate::signals::{dev_mgmt::SessionManager, process_answers, SignalEventShared, SignalOrder};
+use crate::nlu::Nlu;
+use crate::signals::{
+    collections::NluMap, dev_mgmt::SessionManager, emit, nlu_failed, order::mqtt::MSG_OUTPUT,
+    process_answers, SignalEventShared, SignalOrder, SystemEvent,
+};
+use crate::skills::hermes::{self, NluQueryMessage};
 use crate::stt::{SttPool, SttSet};
 use crate::{
     actions::{ActionContext, ContextData},
@@ -16,13 +21,13 @@ use crate::{
 };
 
 // Other crates
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use lily_common::audio::{Audio, AudioRaw};
 use lily_common::communication::*;
 use lily_common::vars::{PathRef, DEFAULT_SAMPLES_PER_SECOND};
 use log::{error, warn};
 use ogg_opus::decode as opus_decode;
-use tokio::sync::mpsc;
+use tokio::{select, sync::mpsc};
 use unic_langid::LanguageIdentifier;
 
 mod language_detection {
@@ -40,12 +45,14 @@ mod language_detection {
 
 /*** Reactions ****************************************************************/
 
-pub async fn on_nlu_request<M: NluManager + NluManagerStatic + Debug + Send + 'static>(
+pub async fn on_nlu_request(
     config: &Config,
     mut channel: mpsc::Receiver<MsgRequest>,
+    mut triggers: mpsc::Receiver<MsgTriggerIntent>,
+    mut removed_skills: mpsc::Receiver<String>,
     signal_event: SignalEventShared,
     curr_langs: &[LanguageIdentifier],
-    order: &mut SignalOrder<M>,
+    order: &mut SignalOrder,
     sessions: Arc<Mutex<SessionManager>>,
 ) -> Result<()> {
     let mut stt_set = SttSet::new();
@@ -59,8 +66,20 @@ pub async fn on_nlu_request<M: NluManager + NluManagerStatic + Debug + Send + 's
     let audio_debug_path = PathRef::user_cfg("stt_audio.ogg").resolve();
 
     loop {
-        let msg_nlu = channel.recv().await.expect("Channel closed!");
-        match msg_nlu.data {
+        // Triggered intents go here too, as they need the order
+        let msg_nlu = select! {
+            msg = channel.recv() => msg.expect("Channel closed!"),
+            trigger = triggers.recv() => {
+                let trigger = trigger.expect("Channel closed!");
+                do_triggered_intent(order, trigger, curr_langs, &sessions).await;
+                continue;
+            }
+            skill = removed_skills.recv() => {
+                order.remove_skill(&skill.expect("Channel closed!"));
+                continue;
+            }
+        };
+        let (as_raw, is_final) = match msg_nlu.data {
             RequestData::Text(text) => {
                 let lang = &curr_langs[0];
                 let decoded = Some(DecodeRes {
@@ -77,69 +96,84 @@ pub async fn on_nlu_request<M: NluManager + NluManagerStatic + Debug + Send + 's
                     &sessions,
                 )
                 .await;
+                continue;
             }
             RequestData::Audio {
                 data: audio,
                 is_final,
             } => {
                 let (as_raw, _) = opus_decode::<_, DEFAULT_SAMPLES_PER_SECOND>(Cursor::new(audio))?;
+                (as_raw, is_final)
+            }
+            RequestData::RawAudio { data, is_final } => (data, is_final),
+        };
 
-                if cfg!(debug_assertions) {
-                    stt_audio.append_audio(&as_raw, DEFAULT_SAMPLES_PER_SECOND)?;
-                }
+        if cfg!(debug_assertions) {
+            stt_audio.append_audio(&as_raw, DEFAULT_SAMPLES_PER_SECOND)?;
+        }
 
-                let session = sessions
-                    .lock_it()
-                    .session_for(msg_nlu.satellite.clone())
-                    .upgrade()
-                    .expect("Session has been deleted right now?");
-
-                {
-                    match session
-                        .lock_it()
-                        .get_stt_or_make(&mut stt_set, &as_raw)
-                        .await
-                    {
-                        Ok(stt) => {
-                            if let Err(e) = stt.process(&as_raw).await {
-                                error!("Stt failed to process audio: {}", e);
-                            } else if is_final {
-                                if cfg!(debug_assertions) {
-                                    stt_audio.save_to_disk(&audio_debug_path)?;
-                                    stt_audio.clear();
-                                }
-
-                                match stt.end_decoding().await {
-                                    Ok(decoded) => {
-                                        do_received_order(
-                                            order,
-                                            decoded,
-                                            signal_event.clone(),
-                                            stt.lang(),
-                                            msg_nlu.satellite.clone(),
-                                            &sessions,
-                                        )
-                                        .await;
-                                    }
-                                    Err(e) => error!("Stt failed while doing final decode: {}", e),
-                                }
-                            }
+        let session = sessions
+            .lock_it()
+            .session_for(msg_nlu.satellite.clone())
+            .upgrade()
+            .expect("Session has been deleted right now?");
+
+        {
+            match session
+                .lock_it()
+                .get_stt_or_make(&mut stt_set, &as_raw)
+                .await
+            {
+                Ok(stt) => {
+                    if let Err(e) = stt.process(&as_raw).await {
+                        error!("Stt failed to process audio: {}", e);
+                        stt_failed(&msg_nlu.satellite, &e);
+                    } else if is_final {
+                        if cfg!(debug_assertions) {
+                            stt_audio.save_to_disk(&audio_debug_path)?;
+                            stt_audio.clear();
                         }
-                        Err(e) => {
-                            error!("Failed to obtain Stt for this session: {}", e);
+
+                        match stt.end_decoding().await {
+                            Ok(decoded) => {
+                                do_received_order(
+                                    order,
+                                    decoded,
+                                    signal_event.clone(),
+                                    stt.lang(),
+                                    msg_nlu.satellite.clone(),
+                                    &sessions,
+                                )
+                                .await;
+                            }
+                            Err(e) => {
+                                error!("Stt failed while doing final decode: {}", e);
+                                stt_failed(&msg_nlu.satellite, &e);
+                            }
                         }
                     }
                 }
-                if is_final {
-                    if let Err(e) = session.lock_it().end_utt() {
-                        warn!("{}", e);
-                    }
+                Err(e) => {
+                    error!("Failed to obtain Stt for this session: {}", e);
+                    stt_failed(&msg_nlu.satellite, &e);
                 }
             }
         }
+        if is_final {
+            if let Err(e) = session.lock_it().end_utt() {
+                warn!("{}", e);
+            }
+        }
     }
 }
 
+fn stt_failed<E: std::fmt::Display>(satellite: &str, error: &E) {
+    let mut data = HashMap::new();
+    data.insert("satellite".to_string(), satellite.to_string());
+    data.insert("error".to_string(), error.to_string());
+    emit(SystemEvent::SttFailed, Some(satellite.to_string()), data);
+}
+
 pub async fn on_event(
     mut channel: mpsc::Receiver<MsgEvent>,
     signal_event: SignalEventShared,
@@ -152,8 +186,12 @@ pub async fn on_event(
             locale: def_lang.to_string(),
             satellite: Some(SatelliteData {
                 uuid: msg.satellite.to_string(),
+                session: None,
             }),
-            data: ContextData::Event { event: "".into() },
+            data: ContextData::Event {
+                event: "".into(),
+                data: msg.data,
+            },
         };
         let ans = signal_event.lock_it().call(&msg.event, context).await;
         if let Err(e) = process_answers(ans, def_lang, msg.satellite) {
@@ -162,28 +200,131 @@ pub async fn on_event(
     }
 }
 
-async fn do_received_order<M: NluManager + NluManagerStatic + Debug + Send + 'static>(
-    order: &mut SignalOrder<M>,
+/// Parse what Hermes asked for in "hermes/nlu/query", in the language the
+/// satellite was last heard in
+pub async fn on_nlu_query(
+    mut channel: mpsc::Receiver<NluQueryMessage>,
+    nlu: Weak<Mutex<NluMap>>,
+    def_lang: Option<&LanguageIdentifier>,
+    sessions: Arc<Mutex<SessionManager>>,
+) -> Result<()> {
+    let def_lang = def_lang.unwrap();
+    loop {
+        let query = channel.recv().await.expect("Channel closed!");
+        let lang = sessions
+            .lock_it()
+            .lang_of(&query.site_id, query.session_id.as_deref())
+            .unwrap_or_else(|| def_lang.clone());
+        let arc = nlu.upgrade().expect("NLU has been dropped");
+        // The map's guard must be gone before parsing
+        let nlu = arc.lock_it().get_nlu(&lang);
+        let nlu = match nlu {
+            Ok(nlu) => nlu,
+            Err(e) => {
+                // Most likely it's still being trained
+                warn!("Hermes NLU query can't be parsed: {}", e);
+                if let Err(e) = hermes::nlu_query_not_recognized(query) {
+                    error!("Failed to answer Hermes NLU query: {}", e);
+                }
+                continue;
+            }
+        };
+
+        let response = nlu.lock().await.parse(&query.input).await;
+        let res = match response {
+            Ok(response) => hermes::answer_nlu_query(query, response),
+            Err(e) => {
+                error!("Failed to parse Hermes NLU query: {}", e);
+                nlu_failed(&lang.to_string(), Some(&query.site_id), &e);
+                hermes::nlu_query_not_recognized(query)
+            }
+        };
+        if let Err(e) = res {
+            error!("Failed to answer Hermes NLU query: {}", e);
+        }
+    }
+}
+
+async fn do_received_order(
+    order: &mut SignalOrder,
     decoded: Option<DecodeRes>,
     signal_event: SignalEventShared,
     lang: &LanguageIdentifier,
     satellite: String,
     sessions: &Arc<Mutex<SessionManager>>,
 ) {
-    match order
-        .received_order(decoded, signal_event, lang, satellite.clone())
+    let (session, intent_filter) = {
+        let mut sessions = sessions.lock_it();
+        sessions.set_lang(&satellite, lang);
+        (
+            sessions.session_data(&satellite),
+            sessions.intent_filter(&satellite),
+        )
+    };
+
+    // On errors the session is ended, the satellite shouldn't be left hanging
+    let s_end = order
+        .received_order(
+            decoded,
+            signal_event,
+            lang,
+            satellite.clone(),
+            session,
+            intent_filter,
+        )
         .await
-    {
-        Ok(s_end) => {
-            if s_end {
-                if let Err(e) = sessions.lock_it().end_session(&satellite) {
-                    error!("Failed to end session for {}: {}", &satellite, e);
-                }
+        .unwrap_or_else(|e| {
+            error!("Actions processing had an error: {}", e);
+            true
+        });
+
+    send_session_status(s_end, satellite);
+}
+
+async fn do_triggered_intent(
+    order: &mut SignalOrder,
+    msg: MsgTriggerIntent,
+    curr_langs: &[LanguageIdentifier],
+    sessions: &Arc<Mutex<SessionManager>>,
+) {
+    let lang = match msg.lang {
+        Some(ref lang) => match lang.parse::<LanguageIdentifier>() {
+            Ok(lang) if curr_langs.contains(&lang) => lang,
+            _ => {
+                error!("Intent trigger asked for unavailable language \"{}\"", lang);
+                return;
             }
-        }
-        Err(e) => {
-            error!("Actions processing had an error: {}", e)
-        }
+        },
+        None => curr_langs[0].clone(),
+    };
+    let session = msg
+        .satellite
+        .as_ref()
+        .and_then(|s| sessions.lock_it().session_data(s));
+
+    let res = order
+        .trigger_intent(
+            &msg.intent,
+            msg.slots,
+            &lang,
+            msg.satellite.clone(),
+            session,
+        )
+        .await;
+    match (res, msg.satellite) {
+        (Ok(s_end), Some(satellite)) => send_session_status(s_end, satellite),
+        (Ok(_), None) => {}
+        (Err(e), _) => error!("Intent \"{}\" couldn't be triggered: {}", msg.intent, e),
+    }
+}
+
+fn send_session_status(s_end: bool, satellite: String) {
+    let res = MSG_OUTPUT.with::<_, Result<()>>(|m| match *m.borrow_mut() {
+        Some(ref mut output) => output.session_status(s_end, satellite),
+        None => Err(anyhow!("No output channel")),
+    });
+    if let Err(e) = res {
+        error!("Failed to send session status: {}", e);
     }
 }
 
@@ -191,4 +332,6 @@ async fn do_received_order<M: NluManager + NluManagerStatic + Debug + Send + 'st
 pub enum SendData {
     String((String, LanguageIdentifier)),
     Audio(Audio),
+    EndSession,
+    KeepListening,
 }
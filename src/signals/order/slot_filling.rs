@@ -0,0 +1,160 @@
+// Standard library
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// This crate
+use crate::nlu::{EntityDef, SlotData, SlotValue};
+use crate::vars::SLOT_FILLING_TIMEOUT;
+
+/// An intent which has been recognized but can't be called yet
+#[derive(Debug)]
+pub struct PendingIntent {
+    // Mangled name of the intent
+    pub name: String,
+    pub input: String,
+    pub confidence: f32,
+    pub slots: HashMap<String, SlotValue>,
+}
+
+/// Conversation going on with a satellite to get the value of a required slot
+#[derive(Debug)]
+pub struct SlotFilling {
+    pub intent: PendingIntent,
+    pub slot: String,
+    pub failed_tries: u8,
+    started: Instant,
+}
+
+impl SlotFilling {
+    pub fn new(intent: PendingIntent, slot: String) -> Self {
+        Self {
+            intent,
+            slot,
+            failed_tries: 0,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.started.elapsed() > Duration::from_millis(SLOT_FILLING_TIMEOUT)
+    }
+}
+
+/// Returns the first required slot (alphabetically, to be predictable) that
+/// has not been given a value yet
+pub fn next_missing_slot(
+    slots_data: &HashMap<String, SlotData>,
+    slots: &HashMap<String, SlotValue>,
+) -> Option<String> {
+    let mut missing: Vec<&String> = slots_data
+        .iter()
+        .filter(|(name, data)| data.required && !slots.contains_key(*name))
+        .map(|(name, _)| name)
+        .collect();
+    missing.sort();
+    missing.first().map(|s| s.to_string())
+}
+
+/// Try to get a value for a slot of this entity out of a whole utterance,
+/// without the NLU
+pub fn extract_from_utterance(def: &EntityDef, input: &str) -> Option<SlotValue> {
+    let input = input.trim();
+    match match_entity(def, input) {
+        Some(value) => Some(SlotValue::Entity {
+            raw: input.to_string(),
+            value,
+        }),
+        None if def.automatically_extensible => Some(SlotValue::Text(input.to_string())),
+        None => None,
+    }
+}
+
+/// Find any of the entity's values (or their synonyms) in the input, the
+/// canonical value is returned
+fn match_entity(def: &EntityDef, input: &str) -> Option<String> {
+    let input = input.to_lowercase();
+    def.data
+        .iter()
+        .find(|d| {
+            std::iter::once(&d.value)
+                .chain(d.synonyms.iter())
+                .filter(|v| !v.is_empty())
+                .any(|v| input.contains(&v.to_lowercase()))
+        })
+        .map(|d| d.value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nlu::{EntityData, OrderKind};
+
+    fn rooms(extensible: bool) -> EntityDef {
+        EntityDef::new(
+            vec![
+                EntityData {
+                    value: "living room".into(),
+                    synonyms: vec!["lounge".into()],
+                },
+                EntityData {
+                    value: "kitchen".into(),
+                    synonyms: vec![],
+                },
+            ],
+            extensible,
+        )
+    }
+
+    fn slot(required: bool) -> SlotData {
+        SlotData {
+            slot_type: OrderKind::Ref("rooms".into()),
+            required,
+            prompt: None,
+            reprompt: None,
+        }
+    }
+
+    #[test]
+    fn missing_slots_in_order() {
+        let mut slots_data = HashMap::new();
+        slots_data.insert("room".to_string(), slot(true));
+        slots_data.insert("duration".to_string(), slot(true));
+        slots_data.insert("label".to_string(), slot(false));
+
+        let mut slots = HashMap::new();
+        assert_eq!(
+            next_missing_slot(&slots_data, &slots),
+            Some("duration".to_string())
+        );
+        slots.insert("duration".to_string(), SlotValue::Text("5 min".into()));
+        assert_eq!(
+            next_missing_slot(&slots_data, &slots),
+            Some("room".to_string())
+        );
+        slots.insert("room".to_string(), SlotValue::Text("kitchen".into()));
+        assert_eq!(next_missing_slot(&slots_data, &slots), None);
+    }
+
+    #[test]
+    fn values_out_of_utterances() {
+        assert_eq!(
+            extract_from_utterance(&rooms(false), " In the Lounge "),
+            Some(SlotValue::Entity {
+                raw: "In the Lounge".into(),
+                value: "living room".into(),
+            })
+        );
+        assert_eq!(
+            extract_from_utterance(&rooms(false), "kitchen please"),
+            Some(SlotValue::Entity {
+                raw: "kitchen please".into(),
+                value: "kitchen".into(),
+            })
+        );
+        assert_eq!(extract_from_utterance(&rooms(false), "the garage"), None);
+        assert_eq!(
+            extract_from_utterance(&rooms(true), "the garage"),
+            Some(SlotValue::Text("the garage".into()))
+        );
+    }
+}
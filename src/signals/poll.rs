@@ -1,4 +1,3 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, Mutex};
@@ -6,44 +5,128 @@ use std::time::Duration;
 
 use crate::actions::{ActionContext, ActionSet, ContextData};
 use crate::config::Config;
-use crate::queries::{Condition, Query};
+use crate::exts::LockIt;
+use crate::queries::{Condition, Query, QueryData};
 use crate::signals::{Signal, SignalEventShared};
+use crate::vars::DEFAULT_POLL_INTERVAL;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use tokio::time::sleep;
+use log::error;
+use tokio::{task::spawn_local, time::sleep};
 use unic_langid::LanguageIdentifier;
 
 impl std::fmt::Debug for UserTask {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt.debug_struct("SnipsNlu").finish()
+        fmt.debug_struct("UserTask")
+            .field("condition", &self.condition)
+            .field("interval", &self.interval)
+            .finish()
     }
 }
 
+#[derive(Clone)]
 struct UserTask {
     query: Arc<Mutex<dyn Query + Send>>,
     condition: Condition,
+    interval: Duration,
+    // Passed to the query on each execution
+    data: QueryData,
     act_set: ActionSet,
 }
 
 impl UserTask {
-    fn new(query: Arc<Mutex<dyn Query + Send>>, act_set: ActionSet) -> Self {
-        Self {
+    /// "interval" (in seconds), "condition" and "value" configure the task,
+    /// anything else is data for the query
+    fn new(
+        query: Arc<Mutex<dyn Query + Send>>,
+        act_set: ActionSet,
+        mut data: HashMap<String, String>,
+    ) -> Result<Self> {
+        {
+            let q = query.lock_it();
+            if !q.is_monitorable() {
+                return Err(anyhow!("Query '{}' can't be polled", q.get_name()));
+            }
+        }
+
+        let condition = Condition::from_data(&data)?;
+        let interval = match data.remove("interval") {
+            Some(secs) => secs
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Interval \"{}\" is not a number of seconds", secs))?,
+            None => DEFAULT_POLL_INTERVAL,
+        };
+        if interval == 0 {
+            return Err(anyhow!("Interval can't be 0"));
+        }
+        data.remove("condition");
+        data.remove("value");
+
+        Ok(Self {
             query,
+            condition,
+            interval: Duration::from_secs(interval),
+            data,
             act_set,
-            condition: Condition::Changed(RefCell::new(Vec::new())),
-        }
+        })
+    }
+
+    fn start(self, lang: LanguageIdentifier) {
+        spawn_local(async move {
+            let mut last = None;
+            loop {
+                let res = {
+                    let q = self.query.lock_it().clone_query();
+                    let res = q.execute(self.data.clone()).await;
+                    if let Err(ref e) = res {
+                        error!("Query '{}' had an error: {}", q.get_name(), e);
+                    }
+                    res
+                };
+
+                if let Ok(curr) = res {
+                    let fire = match last {
+                        Some(ref prev) => self.condition.check(prev, &curr),
+                        // The first result is just the baseline
+                        None => false,
+                    };
+                    last = Some(curr);
+
+                    if fire {
+                        let context = ActionContext {
+                            locale: lang.to_string(),
+                            satellite: None,
+                            data: ContextData::Event {
+                                event: "called by user signal".into(),
+                                data: HashMap::new(),
+                            },
+                        };
+                        self.act_set.call_all(&context).await;
+                    }
+                }
+
+                sleep(self.interval).await;
+            }
+        });
     }
 }
 
 #[derive(Debug)]
 pub struct PollQuery {
     tasks: Vec<UserTask>,
+    // Only known once the event loop is running, tasks added after that are
+    // started right away
+    def_lang: Option<LanguageIdentifier>,
 }
 
 impl PollQuery {
     pub fn new() -> Self {
-        Self { tasks: Vec::new() }
+        Self {
+            tasks: Vec::new(),
+            def_lang: None,
+        }
     }
 }
 
@@ -58,27 +141,26 @@ impl Signal for PollQuery {
         _config: &Config,
         curr_lang: &[LanguageIdentifier],
     ) -> Result<()> {
-        loop {
-            sleep(Duration::from_secs(30)).await;
-            for task in &mut self.tasks {
-                if task.condition.check(&task.query, HashMap::new()) {
-                    let context = ActionContext {
-                        locale: curr_lang[0].to_string(),
-                        satellite: None,
-                        data: ContextData::Event {
-                            event: "called by user signal".into(),
-                        },
-                    };
-                    task.act_set.call_all(&context).await;
-                }
-            }
+        // Each task polls on its own, this signal mustn't stay locked
+        for task in &self.tasks {
+            task.clone().start(curr_lang[0].clone());
         }
+        self.def_lang = Some(curr_lang[0].clone());
+        Ok(())
     }
 }
 
 impl PollQuery {
-    pub fn add(&mut self, query: Arc<Mutex<dyn Query + Send>>, act_set: ActionSet) -> Result<()> {
-        let task = UserTask::new(query, act_set);
+    pub fn add(
+        &mut self,
+        query: Arc<Mutex<dyn Query + Send>>,
+        act_set: ActionSet,
+        data: HashMap<String, String>,
+    ) -> Result<()> {
+        let task = UserTask::new(query, act_set, data)?;
+        if let Some(ref lang) = self.def_lang {
+            task.clone().start(lang.clone());
+        }
         self.tasks.push(task);
 
         Ok(())
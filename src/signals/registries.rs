@@ -1,45 +1,73 @@
 // Standard library
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use crate::collections::BaseRegistry;
 use crate::config::Config;
 use crate::exts::LockIt;
 use crate::signals::poll::PollQuery;
-use crate::signals::{Signal, SignalEvent, SignalEventShared, SignalOrderCurrent, UserSignal};
+use crate::signals::{
+    emit, on_shutdown, on_system_event, Signal, SignalEvent, SignalEventShared, SignalOrder,
+    SystemEvent, UserSignal,
+};
+use crate::vars::is_mangled_from;
 
 use anyhow::Result;
-use delegate::delegate;
 use lazy_static::lazy_static;
 use log::{error, warn};
-use tokio::task::LocalSet;
+use tokio::task::{JoinHandle, LocalSet};
 use unic_langid::LanguageIdentifier;
 
 lazy_static! {
     pub static ref POLL_SIGNAL: Mutex<Option<Arc<Mutex<PollQuery>>>> = Mutex::new(None);
+    // Reachable without the registry, which stays locked while running
+    pub static ref SIG_EVENT: SignalEventShared = Arc::new(Mutex::new(SignalEvent::new()));
+    // Same for skills' signals, as skills can be unregistered while running
+    static ref USER_SIGNALS: Mutex<UserSignals> = Mutex::new(UserSignals::new());
+}
+
+#[derive(Debug)]
+struct UserSignals {
+    base: BaseRegistry<dyn UserSignal + Send>,
+    // Event loops of the signals, by mangled name
+    loops: HashMap<String, JoinHandle<()>>,
+}
+
+impl UserSignals {
+    fn new() -> Self {
+        Self {
+            base: BaseRegistry::new(),
+            loops: HashMap::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct SignalRegistry {
     event: SignalEventShared,
-    order: Option<Arc<Mutex<SignalOrderCurrent>>>,
+    order: Option<Arc<Mutex<SignalOrder>>>,
     poll: Option<Arc<Mutex<PollQuery>>>,
-    base: BaseRegistry<dyn UserSignal + Send>,
 }
 
 impl SignalRegistry {
     pub fn new() -> Self {
         Self {
-            event: Arc::new(Mutex::new(SignalEvent::new())),
+            event: SIG_EVENT.clone(),
             order: None,
             poll: None,
-            base: BaseRegistry::new(),
         }
     }
 
     pub fn end_load(&mut self, curr_langs: &[LanguageIdentifier]) -> Result<()> {
+        // Skills loaded from disk add their intents straight to the order
+        if let Some(order) = &self.order {
+            order.lock_it().end_load(curr_langs)?;
+        }
+
+        let mut user_signals = USER_SIGNALS.lock_it();
         let mut to_remove = Vec::new();
 
-        for (sig_name, signal) in self.base.iter_mut() {
+        for (sig_name, signal) in user_signals.base.iter_mut() {
             if let Err(e) = signal.lock_it().end_load(curr_langs) {
                 warn!(
                     "Signal \"{}\" had trouble in \"end_load\", will be disabled, error: {}",
@@ -52,7 +80,7 @@ impl SignalRegistry {
 
         // Delete any signals which had problems during end_load
         for sig_name in &to_remove {
-            self.base.remove_mangled(sig_name);
+            user_signals.base.remove_mangled(sig_name);
         }
 
         Ok(())
@@ -88,7 +116,7 @@ impl SignalRegistry {
                 if let Err(e) = res {
                     error!("Signal '{}' had an error: {}", n, e.to_string());
                 }
-            });
+            })
         };
 
         spawn_on_local(
@@ -105,15 +133,24 @@ impl SignalRegistry {
                 .expect("Poll signal had problems during init")
                 .clone(),
         );
-        for (sig_name, sig) in self.base.clone() {
-            spawn_on_local_u(sig_name, sig);
+        let user_signals = USER_SIGNALS.lock_it().base.clone();
+        for (sig_name, sig) in user_signals {
+            let handle = spawn_on_local_u(sig_name.clone(), sig);
+            USER_SIGNALS.lock_it().loops.insert(sig_name, handle);
         }
 
+        local.spawn_local(on_system_event(curr_lang[0].clone()));
+        local.spawn_local(on_shutdown(curr_lang[0].clone()));
+        let mut data = HashMap::new();
+        data.insert("version".into(), env!("CARGO_PKG_VERSION").into());
+        data.insert("languages".into(), langs_str(curr_lang));
+        emit(SystemEvent::Start, None, data);
+
         local.await;
         Ok(())
     }
 
-    pub fn set_order(&mut self, sig_order: Arc<Mutex<SignalOrderCurrent>>) -> Result<()> {
+    pub fn set_order(&mut self, sig_order: Arc<Mutex<SignalOrder>>) -> Result<()> {
         self.order = Some(sig_order);
         Ok(())
     }
@@ -124,11 +161,58 @@ impl SignalRegistry {
         Ok(())
     }
 
-    pub fn get_sig_order(&self) -> Option<&Arc<Mutex<SignalOrderCurrent>>> {
+    pub fn get_sig_order(&self) -> Option<&Arc<Mutex<SignalOrder>>> {
         self.order.as_ref()
     }
 
-    delegate! {to self.base{
-        pub fn insert(&mut self, skill_name: &str, sig_name: &str, signal: Arc<Mutex<dyn UserSignal + Send>>) -> Result<()>;
-    }}
+    pub fn get_sig_event(&self) -> &SignalEventShared {
+        &self.event
+    }
+
+    pub fn insert(
+        &mut self,
+        skill_name: &str,
+        sig_name: &str,
+        signal: Arc<Mutex<dyn UserSignal + Send>>,
+    ) -> Result<()> {
+        USER_SIGNALS
+            .lock_it()
+            .base
+            .insert(skill_name, sig_name, signal)
+    }
+
+    pub fn get(
+        &self,
+        skill_name: &str,
+        sig_name: &str,
+    ) -> Option<Arc<Mutex<dyn UserSignal + Send>>> {
+        USER_SIGNALS
+            .lock_it()
+            .base
+            .get(skill_name, sig_name)
+            .cloned()
+    }
+}
+
+/// Drop every signal of a skill and stop their loops, returns how many were
+/// removed. This doesn't need `SIG_REG`, so it can be called while running.
+pub fn remove_skill_signals(skill_name: &str) -> usize {
+    let mut user_signals = USER_SIGNALS.lock_it();
+    user_signals.loops.retain(|sig_name, handle| {
+        let keep = !is_mangled_from(skill_name, sig_name);
+        if !keep {
+            handle.abort();
+        }
+        keep
+    });
+    user_signals.base.remove_skill(skill_name)
+}
+
+/// Languages as a comma separated list, e.g: "en-US,es-ES"
+pub fn langs_str(langs: &[LanguageIdentifier]) -> String {
+    langs
+        .iter()
+        .map(|l| l.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
 }
@@ -0,0 +1,205 @@
+// Standard library
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// This crate
+use crate::actions::{ActionContext, ContextData, SatelliteData};
+use crate::exts::LockIt;
+use crate::signals::{announce_answers, SIG_EVENT};
+use crate::vars::{SHUTDOWN_ANSWERS_WAIT, SHUTDOWN_TIMEOUT};
+
+// Other crates
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout};
+use unic_langid::LanguageIdentifier;
+
+type EventsChannel = (
+    mpsc::UnboundedSender<EmittedEvent>,
+    Mutex<Option<mpsc::UnboundedReceiver<EmittedEvent>>>,
+);
+
+lazy_static! {
+    // Events emitted before the loop runs (e.g: start) just wait for it
+    static ref SYSTEM_EVENTS: EventsChannel = {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (sender, Mutex::new(Some(receiver)))
+    };
+}
+
+/// Everything Lily itself tells skills about, they are handled like any other
+/// event (e.g: in the "events" of a skill's model)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SystemEvent {
+    /// Lily is ready, data: "version" and "languages"
+    Start,
+    /// Lily is about to exit
+    Shutdown,
+    /// Data: "satellite" and "caps" (comma separated)
+    SatelliteConnected,
+    /// Data: "satellite"
+    SatelliteLost,
+    /// Intents changed and the NLU has been trained again, data: "languages"
+    NluRetrained,
+    /// Data: "satellite" and "error"
+    SttFailed,
+    /// Data: "satellite" and "error"
+    TtsFailed,
+    /// Parsing or training failed, data: "languages", "error" and "satellite"
+    /// when it came from one
+    NluFailed,
+}
+
+impl SystemEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SystemEvent::Start => "lily_start",
+            SystemEvent::Shutdown => "lily_shutdown",
+            SystemEvent::SatelliteConnected => "satellite_connected",
+            SystemEvent::SatelliteLost => "satellite_lost",
+            SystemEvent::NluRetrained => "nlu_retrained",
+            SystemEvent::SttFailed => "stt_failed",
+            SystemEvent::TtsFailed => "tts_failed",
+            SystemEvent::NluFailed => "nlu_failed",
+        }
+    }
+}
+
+struct EmittedEvent {
+    event: SystemEvent,
+    // Where the answers are said, every satellite if there's none
+    satellite: Option<String>,
+    data: HashMap<String, String>,
+}
+
+/// Let skills know something happened. Can be called from anywhere, the
+/// actions are called later from the signals loop.
+pub fn emit(event: SystemEvent, satellite: Option<String>, data: HashMap<String, String>) {
+    let emitted = EmittedEvent {
+        event,
+        satellite,
+        data,
+    };
+    if SYSTEM_EVENTS.0.send(emitted).is_err() {
+        debug!(
+            "System event \"{}\" emitted with no one listening",
+            event.name()
+        );
+    }
+}
+
+pub async fn on_system_event(lang: LanguageIdentifier) {
+    let mut receiver = match SYSTEM_EVENTS.1.lock_it().take() {
+        Some(receiver) => receiver,
+        None => {
+            warn!("System events are already being handled");
+            return;
+        }
+    };
+
+    while let Some(emitted) = receiver.recv().await {
+        call_event(emitted, &lang).await;
+    }
+}
+
+/// Wait for Ctrl-C (or a termination request), let skills say goodbye and exit.
+/// A second request exits right away.
+pub async fn on_shutdown(lang: LanguageIdentifier) {
+    wait_for_exit().await;
+
+    // Even if the runtime itself gets stuck we still exit
+    std::thread::spawn(|| {
+        std::thread::sleep(Duration::from_millis(
+            SHUTDOWN_TIMEOUT + SHUTDOWN_ANSWERS_WAIT,
+        ));
+        warn!("Shutdown got stuck, exiting anyway");
+        std::process::exit(1);
+    });
+
+    let emitted = EmittedEvent {
+        event: SystemEvent::Shutdown,
+        satellite: None,
+        data: HashMap::new(),
+    };
+    let goodbye = async {
+        match timeout(
+            Duration::from_millis(SHUTDOWN_TIMEOUT),
+            call_event(emitted, &lang),
+        )
+        .await
+        {
+            // Give the answers some time to be sent
+            Ok(true) => sleep(Duration::from_millis(SHUTDOWN_ANSWERS_WAIT)).await,
+            Ok(false) => {}
+            Err(_) => warn!("Skills took too long to handle shutdown"),
+        }
+    };
+
+    tokio::select! {
+        _ = goodbye => {}
+        _ = wait_for_exit() => warn!("Asked again to exit, not waiting for skills"),
+    }
+
+    std::process::exit(0);
+}
+
+// Whether there was something to call
+async fn call_event(emitted: EmittedEvent, lang: &LanguageIdentifier) -> bool {
+    let name = emitted.event.name();
+    // Don't keep the events locked while the actions run
+    let act_set = match SIG_EVENT.lock_it().get(name) {
+        Some(act_set) => act_set,
+        None => return false,
+    };
+
+    let context = ActionContext {
+        locale: lang.to_string(),
+        satellite: emitted.satellite.as_ref().map(|uuid| SatelliteData {
+            uuid: uuid.clone(),
+            session: None,
+        }),
+        data: ContextData::Event {
+            event: name.to_string(),
+            data: emitted.data,
+        },
+    };
+    let ans = act_set.call_all(&context).await;
+    announce_answers(ans, lang, emitted.satellite.as_deref());
+    true
+}
+
+/// Let skills know the NLU couldn't parse or train
+pub fn nlu_failed<E: std::fmt::Display>(langs: &str, satellite: Option<&str>, error: &E) {
+    let mut data = HashMap::new();
+    data.insert("languages".to_string(), langs.to_string());
+    data.insert("error".to_string(), error.to_string());
+    if let Some(satellite) = satellite {
+        data.insert("satellite".to_string(), satellite.to_string());
+    }
+    emit(SystemEvent::NluFailed, satellite.map(str::to_string), data);
+}
+
+#[cfg(unix)]
+async fn wait_for_exit() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut term) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = term.recv() => {}
+            }
+        }
+        Err(e) => {
+            warn!("Can't listen for termination requests: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_exit() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,222 @@
+// Standard library
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// This crate
+use super::{parse_duration, TimeWords, Timer, TimerInfo, TimerSelector};
+use crate::actions::{Action, ActionAnswer, ActionContext, TranslatedAnswer};
+use crate::exts::LockIt;
+use crate::nlu::SlotValue;
+use crate::skills::translations::get_translations;
+use crate::vars::DEFAULT_SNOOZE;
+
+// Other crates
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use fluent_bundle::FluentArgs;
+use unic_langid::LanguageIdentifier;
+
+/// What a timer set by voice does when it's done, just let the user know
+pub struct TimerRingAction {}
+
+impl TimerRingAction {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait(?Send)]
+impl Action for TimerRingAction {
+    async fn call(&mut self, context: &ActionContext) -> Result<ActionAnswer> {
+        TranslatedAnswer::new("embedded", "timer_ring").build(context, true)
+    }
+
+    fn get_name(&self) -> String {
+        "timer_ring".into()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimerOp {
+    List,
+    Cancel,
+    Pause,
+    Resume,
+    Snooze,
+}
+
+impl TimerOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimerOp::List => "timer_list",
+            TimerOp::Cancel => "timer_cancel",
+            TimerOp::Pause => "timer_pause",
+            TimerOp::Resume => "timer_resume",
+            TimerOp::Snooze => "timer_snooze",
+        }
+    }
+}
+
+/// Lets the user manage their timers, a "label" slot chooses which one,
+/// without it the only one that makes sense is used. Snoozing takes an
+/// optional "duration" slot. Only timers created while running (e.g: by
+/// voice) are managed, those declared by skills are left alone and the
+/// answers say so.
+pub struct TimerManageAction {
+    timer: Arc<Mutex<Timer>>,
+    op: TimerOp,
+}
+
+impl TimerManageAction {
+    pub fn new(timer: Arc<Mutex<Timer>>, op: TimerOp) -> Self {
+        Self { timer, op }
+    }
+
+    fn list(&self, context: &ActionContext) -> Result<ActionAnswer> {
+        let timers = self.timer.lock_it().list();
+        if timers.is_empty() {
+            return TranslatedAnswer::new("embedded", "timers_none").build(context, true);
+        }
+
+        let lang: LanguageIdentifier = context.locale.parse()?;
+        let entries = timers
+            .iter()
+            .map(|t| describe(t, &lang))
+            .collect::<Result<Vec<_>>>()?;
+        TranslatedAnswer::new("embedded", "timers_list")
+            .arg("count", timers.len().to_string())
+            .arg("timers", entries.join(", "))
+            .build(context, true)
+    }
+
+    fn snooze(&self, context: &ActionContext) -> Result<ActionAnswer> {
+        let satellite = context
+            .satellite
+            .as_ref()
+            .ok_or_else(|| anyhow!("Only timers on a satellite can be snoozed"))?;
+        let dur = slot_duration(context, "duration")
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_SNOOZE));
+
+        match self.timer.lock_it().snooze(&satellite.uuid, dur) {
+            Ok(_) => TranslatedAnswer::new("embedded", "timer_snoozed")
+                .arg("minutes", ((dur.as_secs() + 59) / 60).to_string())
+                .build(context, true),
+            Err(_) => {
+                TranslatedAnswer::new("embedded", "timer_nothing_to_snooze").build(context, true)
+            }
+        }
+    }
+
+    // Cancel, pause or resume
+    fn change(&self, context: &ActionContext) -> Result<ActionAnswer> {
+        let label = slot(context, "label");
+        let selector = match label {
+            Some(ref label) => TimerSelector::Label(label.clone()),
+            None => {
+                let candidates: Vec<_> = self
+                    .timer
+                    .lock_it()
+                    .list()
+                    .into_iter()
+                    .filter(|t| match self.op {
+                        TimerOp::Pause => !t.paused,
+                        TimerOp::Resume => t.paused,
+                        _ => true,
+                    })
+                    .collect();
+                match candidates.as_slice() {
+                    [] => {
+                        return TranslatedAnswer::new("embedded", "timers_none")
+                            .build(context, true)
+                    }
+                    [only] => TimerSelector::Id(only.id),
+                    _ => {
+                        return TranslatedAnswer::new("embedded", "timer_which")
+                            .build(context, true)
+                    }
+                }
+            }
+        };
+
+        let ids = {
+            let mut timer = self.timer.lock_it();
+            match self.op {
+                TimerOp::Cancel => timer.cancel(&selector),
+                TimerOp::Pause => timer.pause(&selector),
+                _ => timer.resume(&selector)?,
+            }
+        };
+
+        let msg = match (ids.is_empty(), self.op) {
+            (true, _) => "timer_not_found",
+            (false, TimerOp::Cancel) => "timer_cancelled",
+            (false, TimerOp::Pause) => "timer_paused",
+            (false, _) => "timer_resumed",
+        };
+        TranslatedAnswer::new("embedded", msg)
+            .arg("label", label.unwrap_or_default())
+            .build(context, true)
+    }
+}
+
+#[async_trait(?Send)]
+impl Action for TimerManageAction {
+    async fn call(&mut self, context: &ActionContext) -> Result<ActionAnswer> {
+        match self.op {
+            TimerOp::List => self.list(context),
+            TimerOp::Snooze => self.snooze(context),
+            _ => self.change(context),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        self.op.name().into()
+    }
+}
+
+fn slot(context: &ActionContext, name: &str) -> Option<String> {
+    context
+        .data
+        .as_intent()
+        .and_then(|i| i.slots.get(name))
+        .map(|s| s.to_string())
+        .filter(|s| !s.is_empty())
+}
+
+// NLUs resolving durations already give us one, otherwise it's parsed
+fn slot_duration(context: &ActionContext, name: &str) -> Option<Duration> {
+    match context.data.as_intent().and_then(|i| i.slots.get(name)) {
+        Some(SlotValue::Duration(dur)) => Some(dur.approx()),
+        Some(other) => {
+            let words = TimeWords::load(&context.locale.parse().ok()?).ok()?;
+            parse_duration(&other.to_string(), &words)
+        }
+        None => None,
+    }
+}
+
+// How a timer is told to the user, e.g: "pasta, 5 minutes left"
+fn describe(timer: &TimerInfo, lang: &LanguageIdentifier) -> Result<String> {
+    let trans = get_translations("embedded")?;
+    let mut args = FluentArgs::new();
+    let label = match timer.label {
+        Some(ref label) => label.clone(),
+        None => trans.get_random(lang, "timer_unnamed", None)?,
+    };
+    args.set("label", label);
+
+    if timer.paused {
+        return trans.get_random(lang, "timer_entry_paused", Some(&args));
+    }
+
+    let secs = timer.remaining.as_secs();
+    let (msg, amount) = if secs >= 3600 {
+        ("timer_entry_hours", secs / 3600)
+    } else if secs >= 60 {
+        ("timer_entry_minutes", (secs + 59) / 60)
+    } else {
+        ("timer_entry_seconds", secs)
+    };
+    args.set("amount", amount.to_string());
+    trans.get_random(lang, msg, Some(&args))
+}
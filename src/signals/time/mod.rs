@@ -0,0 +1,1292 @@
+mod actions;
+mod words;
+
+pub use self::actions::*;
+use self::words::TimeWords;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::actions::{
+    ActionContext, ActionSet, ContextData, SatelliteData, TranslatedAnswer, ACT_REG,
+};
+use crate::config::{Config, GLOBAL_CONF};
+use crate::exts::LockIt;
+use crate::signals::{announce_answers, process_answers, Signal, SignalEventShared, UserSignal};
+use crate::vars::{MISSED_TIMERS_DELAY, SNOOZE_WINDOW, TIMERS_PATH};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{
+    DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use cron::Schedule;
+use log::{error, warn};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use tokio::{
+    task::{spawn_local, JoinHandle},
+    time::sleep,
+};
+use unic_langid::LanguageIdentifier;
+
+pub struct Timer {
+    // Timers declared by skills, they are declared again on each start
+    timers: Vec<TimerEntry>,
+    // Timers created while running (e.g: by voice), kept on disk
+    store: Arc<Mutex<TimerStore>>,
+    // Only known once the event loop is running, timers added after that are
+    // started right away
+    def_lang: Option<LanguageIdentifier>,
+    // Language of the timers declared by skills, unless they say otherwise
+    main_lang: LanguageIdentifier,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TimersConf {
+    // What to do with timers that should have rung while Lily was down
+    #[serde(default = "def_missed")]
+    pub missed: MissedTimers,
+    // Dates and rules are understood in this time zone (e.g: "Europe/Madrid"),
+    // the system's one is used if there's none
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedTimers {
+    /// Ring them as soon as Lily starts
+    Ring,
+    /// Tell the user that they were missed
+    Announce,
+    /// Forget about them
+    Discard,
+}
+
+fn def_missed() -> MissedTimers {
+    MissedTimers::Ring
+}
+
+impl Default for TimersConf {
+    fn default() -> Self {
+        Self {
+            missed: def_missed(),
+            timezone: None,
+        }
+    }
+}
+
+struct TimerEntry {
+    kind: TimerKind,
+    actions: ActionSet,
+    target: RingTarget,
+}
+
+/// A timer as it's saved on disk
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct StoredTimer {
+    id: u64,
+    // Unix timestamp (in seconds) of the next time it will ring
+    due: i64,
+    // Seconds between rings, for those that repeat
+    #[serde(default)]
+    every: Option<u64>,
+    // Calendar rule, for those that repeat on certain days or times
+    #[serde(default)]
+    recurrence: Option<Recurrence>,
+    // Action to be called as "skill/action"
+    action: String,
+    #[serde(default)]
+    lang: Option<String>,
+    #[serde(default)]
+    satellite: Option<String>,
+    #[serde(default)]
+    label: Option<String>,
+    // Seconds that were left when it was paused
+    #[serde(default)]
+    paused: Option<i64>,
+}
+
+impl StoredTimer {
+    fn target(&self) -> RingTarget {
+        RingTarget {
+            lang: self.lang.as_ref().and_then(|l| l.parse().ok()),
+            satellite: self.satellite.clone(),
+        }
+    }
+
+    fn actions(&self) -> Result<ActionSet> {
+        let (skill, name) = self
+            .action
+            .split_once('/')
+            .ok_or_else(|| anyhow!("'{}' is not a valid action", self.action))?;
+        let act_grd = ACT_REG.lock_it();
+        let act = act_grd
+            .get(skill, name)
+            .ok_or_else(|| anyhow!("There's no action '{}' in skill '{}'", name, skill))?;
+        Ok(ActionSet::create(Arc::downgrade(act)))
+    }
+}
+
+struct TimerStore {
+    path: PathBuf,
+    timers: Vec<StoredTimer>,
+    // Tasks waiting for each timer to ring, so that they can be stopped
+    tasks: HashMap<u64, JoinHandle<()>>,
+    // Last timer that rang on each satellite and when, for snoozing it
+    rung: HashMap<String, (StoredTimer, i64)>,
+}
+
+impl TimerStore {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            timers: Vec::new(),
+            tasks: HashMap::new(),
+            rung: HashMap::new(),
+        }
+    }
+
+    fn load(&mut self) -> Result<()> {
+        if self.path.is_file() {
+            self.timers = serde_json::from_str(&fs::read_to_string(&self.path)?)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string(&self.timers)?)?;
+        Ok(())
+    }
+
+    fn add(&mut self, mut timer: StoredTimer) -> u64 {
+        timer.id = self.timers.iter().map(|t| t.id + 1).max().unwrap_or(0);
+        let id = timer.id;
+        self.timers.push(timer);
+        self.save_or_log();
+        id
+    }
+
+    fn get(&self, id: u64) -> Option<&StoredTimer> {
+        self.timers.iter().find(|t| t.id == id)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Option<&mut StoredTimer> {
+        self.timers.iter_mut().find(|t| t.id == id)
+    }
+
+    /// Stop waiting for a timer, it won't ring unless it's started again
+    fn stop(&mut self, id: u64) {
+        if let Some(task) = self.tasks.remove(&id) {
+            task.abort();
+        }
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.stop(id);
+        self.timers.retain(|t| t.id != id);
+        // Otherwise snoozing would bring it back
+        self.rung.retain(|_, (timer, _)| timer.id != id);
+        self.save_or_log();
+    }
+
+    /// A timer has rung (or has been skipped), returns the timestamp of the
+    /// next time it has to ring, if any
+    fn rang(&mut self, id: u64, now: i64) -> Option<i64> {
+        let pos = self.timers.iter().position(|t| t.id == id)?;
+        let next = {
+            let timer = &self.timers[pos];
+            match (timer.every, &timer.recurrence) {
+                (Some(every), _) if every > 0 => {
+                    // Skip all the rings that were missed
+                    let every = every as i64;
+                    let missed = (now - timer.due).max(0) / every + 1;
+                    Some(timer.due + missed * every)
+                }
+                (_, Some(rule)) => Utc
+                    .timestamp_opt(now.max(timer.due), 0)
+                    .single()
+                    .and_then(|after| rule.next_after(&after, Zone::current()))
+                    .map(|date| date.timestamp()),
+                _ => None,
+            }
+        };
+
+        let timer = match next {
+            Some(due) => {
+                self.timers[pos].due = due;
+                self.timers[pos].clone()
+            }
+            None => {
+                self.tasks.remove(&id);
+                self.timers.remove(pos)
+            }
+        };
+        if let Some(ref satellite) = timer.satellite {
+            self.rung.insert(satellite.clone(), (timer, now));
+        }
+        self.save_or_log();
+        next
+    }
+
+    fn save_or_log(&self) {
+        if let Err(e) = self.save() {
+            error!("Timers couldn't be saved to disk: {}", e);
+        }
+    }
+}
+
+/// Where and how a timer should ring
+#[derive(Clone, Debug, Default)]
+struct RingTarget {
+    lang: Option<LanguageIdentifier>,
+    satellite: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct MyDateTime {
+    inner: DateTime<Utc>,
+}
+
+struct MyDateTimeVisitor;
+
+impl<'de> Visitor<'de> for MyDateTimeVisitor {
+    type Value = MyDateTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string with format \"yyyy-mm-dd hh:mm:ss")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let naive = NaiveDateTime::parse_from_str(v, "%F %T")
+            .map_err(|_| E::custom(format!("date '{}' is not correctly formatted", v)))?;
+        let inner = DateTime::<Utc>::from_utc(naive, Utc);
+        Ok(MyDateTime { inner })
+    }
+}
+impl<'de> Deserialize<'de> for MyDateTime {
+    fn deserialize<D>(deserializer: D) -> Result<MyDateTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_i32(MyDateTimeVisitor)
+    }
+}
+impl MyDateTime {
+    fn parse(date_str: &str, zone: Zone) -> Result<Self> {
+        // NLUs like Snips append the offset to the date
+        let inner = match NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S") {
+            Ok(naive) => zone
+                .from_local(&naive)
+                .ok_or_else(|| anyhow!("date '{}' doesn't exist in this time zone", date_str))?,
+            Err(_) => DateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S %:z")
+                .or_else(|_| DateTime::parse_from_rfc3339(date_str))
+                .map_err(|_| anyhow!("date '{}' is not correctly formatted", date_str))?
+                .with_timezone(&Utc),
+        };
+        Ok(Self { inner })
+    }
+}
+#[derive(Clone, Debug)]
+enum TimerKind {
+    Once(Duration),
+    Every(Duration),
+    On(MyDateTime),
+    Recurring(Recurrence),
+}
+
+/// Time zone in which dates and rules are understood
+#[derive(Clone, Copy, Debug)]
+enum Zone {
+    Local,
+    Named(Tz),
+}
+
+impl Zone {
+    /// The one configured, or the system's one
+    fn current() -> Self {
+        let name = GLOBAL_CONF.with(|c| c.borrow().timers.timezone.clone());
+        match name {
+            Some(name) => match Tz::from_str(&name) {
+                Ok(tz) => Zone::Named(tz),
+                Err(_) => {
+                    warn!(
+                        "'{}' is not a known time zone, using the system's one",
+                        name
+                    );
+                    Zone::Local
+                }
+            },
+            None => Zone::Local,
+        }
+    }
+
+    fn from_local(&self, naive: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Local => Local
+                .from_local_datetime(naive)
+                .earliest()
+                .map(|d| d.with_timezone(&Utc)),
+            Zone::Named(tz) => tz
+                .from_local_datetime(naive)
+                .earliest()
+                .map(|d| d.with_timezone(&Utc)),
+        }
+    }
+
+    fn to_local(&self, date: &DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Local => date.with_timezone(&Local).naive_local(),
+            Zone::Named(tz) => date.with_timezone(tz).naive_local(),
+        }
+    }
+}
+
+/// Rules for timers that ring on certain days or times
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Recurrence {
+    // A cron expression, either with seconds or the classic 5 fields one
+    Cron(String),
+    // Days are numbered from Monday (1) to Sunday (7), time is in seconds
+    // since midnight
+    Weekly { days: Vec<u32>, time: u32 },
+}
+
+impl Recurrence {
+    fn cron(expr: &str) -> Result<Self> {
+        // Validate it right now, so that errors reach whoever made it
+        Schedule::from_str(&full_cron(expr))
+            .map_err(|e| anyhow!("'{}' is not a valid cron expression: {}", expr, e))?;
+        Ok(Recurrence::Cron(expr.to_string()))
+    }
+
+    fn weekly(days: &str, time: &str, words: &TimeWords) -> Result<Self> {
+        Ok(Recurrence::Weekly {
+            days: parse_days(days, words)?,
+            time: parse_time(time, words)?.num_seconds_from_midnight(),
+        })
+    }
+
+    /// The first time this happens after a date
+    fn next_after(&self, after: &DateTime<Utc>, zone: Zone) -> Option<DateTime<Utc>> {
+        match self {
+            Recurrence::Cron(expr) => {
+                let schedule = Schedule::from_str(&full_cron(expr)).ok()?;
+                match zone {
+                    Zone::Local => schedule
+                        .after(&after.with_timezone(&Local))
+                        .next()
+                        .map(|d| d.with_timezone(&Utc)),
+                    Zone::Named(tz) => schedule
+                        .after(&after.with_timezone(&tz))
+                        .next()
+                        .map(|d| d.with_timezone(&Utc)),
+                }
+            }
+            Recurrence::Weekly { days, time } => {
+                let time = NaiveTime::from_num_seconds_from_midnight_opt(*time, 0)?;
+                let today = zone.to_local(after).date();
+                (0..=7)
+                    .map(|d| today + chrono::Duration::days(d))
+                    .filter(|date| days.contains(&date.weekday().number_from_monday()))
+                    .filter_map(|date| zone.from_local(&date.and_time(time)))
+                    .find(|date| date > after)
+            }
+        }
+    }
+}
+
+// The cron crate wants seconds, but most people know cron without them
+fn full_cron(expr: &str) -> String {
+    if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    }
+}
+
+#[async_trait(?Send)]
+impl Signal for Timer {
+    fn end_load(&mut self, _curr_lang: &[LanguageIdentifier]) -> Result<()> {
+        Ok(())
+    }
+    async fn event_loop(
+        &mut self,
+        _signal_event: SignalEventShared,
+        config: &Config,
+        curr_lang: &[LanguageIdentifier],
+    ) -> Result<()> {
+        let def_lang = curr_lang[0].clone();
+        for entry in &self.timers {
+            Self::start(entry, &def_lang);
+        }
+
+        let stored = {
+            let mut store = self.store.lock_it();
+            if let Err(e) = store.load() {
+                error!("Saved timers couldn't be loaded: {}", e);
+            }
+            store.timers.clone()
+        };
+
+        let now = Utc::now().timestamp();
+        let (pending, missed): (Vec<_>, Vec<_>) = stored
+            .into_iter()
+            // Paused ones wait until someone resumes them
+            .filter(|timer| timer.paused.is_none())
+            .partition(|timer| timer.due > now);
+        for timer in pending {
+            Self::start_stored(&self.store, timer.id, &def_lang);
+        }
+
+        if !missed.is_empty() {
+            let store = self.store.clone();
+            let behaviour = config.timers.missed;
+            let def_lang = def_lang.clone();
+            spawn_local(async move {
+                // Give satellites some time to connect again
+                sleep(Duration::from_millis(MISSED_TIMERS_DELAY)).await;
+                for timer in missed {
+                    if behaviour == MissedTimers::Ring {
+                        Self::start_stored(&store, timer.id, &def_lang);
+                        continue;
+                    }
+
+                    if behaviour == MissedTimers::Announce {
+                        announce_missed(&timer, &def_lang);
+                    }
+                    let next = store.lock_it().rang(timer.id, Utc::now().timestamp());
+                    if next.is_some() {
+                        Self::start_stored(&store, timer.id, &def_lang);
+                    }
+                }
+            });
+        }
+
+        self.def_lang = Some(def_lang);
+        Ok(())
+    }
+}
+#[async_trait(?Send)]
+impl UserSignal for Timer {
+    fn add(
+        &mut self,
+        data: HashMap<String, String>,
+        _skill_name: &str,
+        act_set: ActionSet,
+    ) -> Result<()> {
+        let target = RingTarget {
+            lang: data.get("locale").and_then(|l| l.parse().ok()),
+            satellite: data.get("satellite").cloned(),
+        };
+        let lang = target
+            .lang
+            .clone()
+            .unwrap_or_else(|| self.main_lang.clone());
+
+        match (&self.def_lang, data.get("action").cloned()) {
+            // Created while running, those are kept, actions can't be saved
+            // so they are referenced by name
+            (Some(def_lang), Some(action)) => {
+                let label = data.get("label").cloned();
+                let now = Utc::now();
+                let (due, every, recurrence) = match Self::from_data(data, &lang)? {
+                    TimerKind::Once(dur) => (now.timestamp() + dur.as_secs() as i64, None, None),
+                    TimerKind::Every(dur) => (
+                        now.timestamp() + dur.as_secs() as i64,
+                        Some(dur.as_secs()),
+                        None,
+                    ),
+                    TimerKind::On(date) => (date.inner.timestamp(), None, None),
+                    TimerKind::Recurring(rule) => {
+                        let next = rule
+                            .next_after(&now, Zone::current())
+                            .ok_or_else(|| anyhow!("Timer rule {:?} never happens", rule))?;
+                        (next.timestamp(), None, Some(rule))
+                    }
+                };
+
+                let id = self.store.lock_it().add(StoredTimer {
+                    id: 0,
+                    due,
+                    every,
+                    recurrence,
+                    action,
+                    lang: target.lang.map(|l| l.to_string()),
+                    satellite: target.satellite,
+                    label,
+                    paused: None,
+                });
+                Self::start_stored(&self.store, id, def_lang);
+            }
+            (def_lang, _) => {
+                let entry = TimerEntry {
+                    kind: Self::from_data(data, &lang)?,
+                    actions: act_set,
+                    target,
+                };
+
+                if let Some(def_lang) = def_lang {
+                    Self::start(&entry, def_lang);
+                }
+                self.timers.push(entry);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Timer {
+    pub fn new(main_lang: LanguageIdentifier) -> Self {
+        Self {
+            timers: Vec::new(),
+            store: Arc::new(Mutex::new(TimerStore::new(TIMERS_PATH.resolve()))),
+            def_lang: None,
+            main_lang,
+        }
+    }
+
+    fn start(entry: &TimerEntry, def_lang: &LanguageIdentifier) {
+        let actions = entry.actions.clone();
+        let target = entry.target.clone();
+        let lang = target.lang.clone().unwrap_or_else(|| def_lang.clone());
+
+        match entry.kind.clone() {
+            TimerKind::Once(dur) => {
+                spawn_local(async move {
+                    sleep(dur).await;
+                    ring(&actions, &lang, &target).await;
+                });
+            }
+            TimerKind::Every(dur) => {
+                spawn_local(async move {
+                    loop {
+                        sleep(dur).await;
+                        ring(&actions, &lang, &target).await;
+                    }
+                });
+            }
+            TimerKind::On(date) => {
+                spawn_local(async move {
+                    // A date that just passed rings right away
+                    let dur = date
+                        .inner
+                        .signed_duration_since(Utc::now())
+                        .to_std()
+                        .unwrap_or_default();
+                    sleep(dur).await;
+                    ring(&actions, &lang, &target).await;
+                });
+            }
+            TimerKind::Recurring(rule) => {
+                spawn_local(async move {
+                    while let Some(next) = rule.next_after(&Utc::now(), Zone::current()) {
+                        let dur = next
+                            .signed_duration_since(Utc::now())
+                            .to_std()
+                            .unwrap_or_default();
+                        sleep(dur).await;
+                        ring(&actions, &lang, &target).await;
+                    }
+                });
+            }
+        }
+    }
+
+    fn start_stored(store: &Arc<Mutex<TimerStore>>, id: u64, def_lang: &LanguageIdentifier) {
+        let task_store = store.clone();
+        let def_lang = def_lang.clone();
+        let task = spawn_local(async move {
+            let store = task_store;
+            loop {
+                let timer = match store.lock_it().get(id) {
+                    Some(timer) if timer.paused.is_none() => timer.clone(),
+                    _ => break,
+                };
+
+                let wait = (timer.due - Utc::now().timestamp()).max(0) as u64;
+                sleep(Duration::from_secs(wait)).await;
+
+                let target = timer.target();
+                let lang = target.lang.clone().unwrap_or_else(|| def_lang.clone());
+                match timer.actions() {
+                    Ok(actions) => ring(&actions, &lang, &target).await,
+                    Err(e) => error!("Timer {} can't ring: {}", id, e),
+                }
+
+                if store.lock_it().rang(id, Utc::now().timestamp()).is_none() {
+                    break;
+                }
+            }
+        });
+
+        if let Some(old) = store.lock_it().tasks.insert(id, task) {
+            old.abort();
+        }
+    }
+
+    /// Timers created while running (those from skills can't be managed),
+    /// the ones that will ring first go first
+    pub fn list(&self) -> Vec<TimerInfo> {
+        let now = Utc::now().timestamp();
+        let mut list: Vec<_> = self
+            .store
+            .lock_it()
+            .timers
+            .iter()
+            .map(|t| TimerInfo::new(t, now))
+            .collect();
+        list.sort_by_key(|t| (t.paused, t.remaining));
+        list
+    }
+
+    /// Returns the ids of the timers cancelled
+    pub fn cancel(&mut self, selector: &TimerSelector) -> Vec<u64> {
+        let mut store = self.store.lock_it();
+        let ids = selector.select(&store.timers);
+        for id in &ids {
+            store.remove(*id);
+        }
+        ids
+    }
+
+    /// Returns the ids of the timers paused, those already paused are left
+    /// as they are
+    pub fn pause(&mut self, selector: &TimerSelector) -> Vec<u64> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock_it();
+        let ids: Vec<_> = selector
+            .select(&store.timers)
+            .into_iter()
+            .filter(|id| store.get(*id).map(|t| t.paused.is_none()).unwrap_or(false))
+            .collect();
+        for id in &ids {
+            store.stop(*id);
+            if let Some(timer) = store.get_mut(*id) {
+                timer.paused = Some((timer.due - now).max(0));
+            }
+        }
+        store.save_or_log();
+        ids
+    }
+
+    /// Returns the ids of the timers resumed
+    pub fn resume(&mut self, selector: &TimerSelector) -> Result<Vec<u64>> {
+        let def_lang = self.running_lang()?;
+        let now = Utc::now();
+        let ids: Vec<_> = {
+            let mut store = self.store.lock_it();
+            let ids: Vec<_> = selector
+                .select(&store.timers)
+                .into_iter()
+                .filter(|id| store.get(*id).map(|t| t.paused.is_some()).unwrap_or(false))
+                .collect();
+            for id in &ids {
+                if let Some(timer) = store.get_mut(*id) {
+                    let left = timer.paused.take().unwrap_or(0);
+                    // Rules go on with their schedule
+                    timer.due = match timer.recurrence {
+                        Some(ref rule) => rule
+                            .next_after(&now, Zone::current())
+                            .map(|d| d.timestamp())
+                            .unwrap_or(now.timestamp() + left),
+                        None => now.timestamp() + left,
+                    };
+                }
+            }
+            store.save_or_log();
+            ids
+        };
+
+        for id in &ids {
+            Self::start_stored(&self.store, *id, &def_lang);
+        }
+        Ok(ids)
+    }
+
+    /// Ring again after some time the last timer that rang on a satellite,
+    /// returns the id of the new timer
+    pub fn snooze(&mut self, satellite: &str, dur: Duration) -> Result<u64> {
+        let def_lang = self.running_lang()?;
+        let now = Utc::now().timestamp();
+        let id = {
+            let mut store = self.store.lock_it();
+            let timer = match store.rung.get(satellite) {
+                Some((timer, when)) if now - when <= SNOOZE_WINDOW => timer.clone(),
+                _ => return Err(anyhow!("No timer has rung recently on {}", satellite)),
+            };
+            store.rung.remove(satellite);
+            store.add(StoredTimer {
+                due: now + dur.as_secs() as i64,
+                every: None,
+                recurrence: None,
+                paused: None,
+                ..timer
+            })
+        };
+
+        Self::start_stored(&self.store, id, &def_lang);
+        Ok(id)
+    }
+
+    fn running_lang(&self) -> Result<LanguageIdentifier> {
+        self.def_lang
+            .clone()
+            .ok_or_else(|| anyhow!("Timers are not running yet"))
+    }
+
+    fn from_data(data: HashMap<String, String>, lang: &LanguageIdentifier) -> Result<TimerKind> {
+        let words = TimeWords::load(lang)?;
+        let dur = if data.contains_key("seconds")
+            || data.contains_key("minutes")
+            || data.contains_key("hours")
+        {
+            fn get_time(
+                data: &HashMap<String, String>,
+                name: &str,
+            ) -> Result<u64, std::num::ParseIntError> {
+                data.get(name)
+                    .map(|s| {
+                        let res = s.parse();
+                        if res.is_err() {
+                            warn!("'{}' can't be parsed as number", s);
+                        }
+                        res
+                    })
+                    // If there's no record just return 0
+                    .unwrap_or(Ok(0))
+            }
+            let secs = get_time(&data, "seconds")?;
+            let mins = get_time(&data, "minutes")?;
+            let hours = get_time(&data, "hours")?;
+
+            Some(Duration::from_secs(secs + mins * 60 + hours * 3600))
+        } else if let Some(text) = data.get("duration") {
+            Some(
+                parse_duration(text, &words)
+                    .ok_or_else(|| anyhow!("'{}' is not an understandable duration", text))?,
+            )
+        } else {
+            None
+        };
+
+        if let Some(dur) = dur {
+            if data.contains_key("kind") && data["kind"] == "every" {
+                Ok(TimerKind::Every(dur))
+            } else {
+                Ok(TimerKind::Once(dur))
+            }
+        } else if data.contains_key("date") {
+            let date = MyDateTime::parse(&data["date"], Zone::current())?;
+            if date.inner < Utc::now() {
+                Err(anyhow!("Date '{}' has already passed", &data["date"]))
+            } else {
+                Ok(TimerKind::On(date))
+            }
+        } else if let Some(expr) = data.get("cron") {
+            Ok(TimerKind::Recurring(Recurrence::cron(expr)?))
+        } else if let Some(days) = data.get("days") {
+            let time = data
+                .get("time")
+                .ok_or_else(|| anyhow!("Timers on certain days need a time"))?;
+            Ok(TimerKind::Recurring(Recurrence::weekly(
+                days, time, &words,
+            )?))
+        } else if let Some(time) = data.get("time") {
+            // Just a time is the next time the clock shows it
+            let rule = Recurrence::Weekly {
+                days: (1..=7).collect(),
+                time: parse_time(time, &words)?.num_seconds_from_midnight(),
+            };
+            let inner = rule
+                .next_after(&Utc::now(), Zone::current())
+                .ok_or_else(|| anyhow!("'{}' doesn't happen in this time zone", time))?;
+            Ok(TimerKind::On(MyDateTime { inner }))
+        } else {
+            Err(anyhow!("Non-coincident format for timer"))
+        }
+    }
+}
+
+/// A timer as seen by those managing them
+#[derive(Clone, Debug)]
+pub struct TimerInfo {
+    pub id: u64,
+    pub label: Option<String>,
+    // Time left until it rings, frozen while paused
+    pub remaining: Duration,
+    pub paused: bool,
+}
+
+impl TimerInfo {
+    fn new(timer: &StoredTimer, now: i64) -> Self {
+        let left = timer.paused.unwrap_or(timer.due - now).max(0);
+        Self {
+            id: timer.id,
+            label: timer.label.clone(),
+            remaining: Duration::from_secs(left as u64),
+            paused: timer.paused.is_some(),
+        }
+    }
+}
+
+/// Which timers an operation applies to
+#[derive(Clone, Debug)]
+pub enum TimerSelector {
+    Id(u64),
+    // Labels are matched loosely, "pasta" finds "the pasta" and the other way
+    Label(String),
+}
+
+impl TimerSelector {
+    fn select(&self, timers: &[StoredTimer]) -> Vec<u64> {
+        timers
+            .iter()
+            .filter(|t| match self {
+                TimerSelector::Id(id) => t.id == *id,
+                TimerSelector::Label(label) => t
+                    .label
+                    .as_ref()
+                    .map(|l| {
+                        let (l, label) = (l.to_lowercase(), label.to_lowercase());
+                        l.contains(&label) || label.contains(&l)
+                    })
+                    .unwrap_or(false),
+            })
+            .map(|t| t.id)
+            .collect()
+    }
+}
+
+async fn ring(actions: &ActionSet, lang: &LanguageIdentifier, target: &RingTarget) {
+    let context = ActionContext {
+        locale: lang.to_string(),
+        satellite: target.satellite.as_ref().map(|uuid| SatelliteData {
+            uuid: uuid.clone(),
+            session: None,
+        }),
+        data: ContextData::Event {
+            event: "timer".to_string(),
+            data: HashMap::new(),
+        },
+    };
+    let ans = actions.call_all(&context).await;
+
+    // Timers set by a satellite ring there, the rest (e.g: those of skills) on
+    // all of them
+    announce_answers(ans, lang, target.satellite.as_deref());
+}
+
+/// Let the user know that a timer rang while Lily was down
+fn announce_missed(timer: &StoredTimer, def_lang: &LanguageIdentifier) {
+    let target = timer.target();
+    let uuid = match target.satellite {
+        Some(ref uuid) => uuid.clone(),
+        None => {
+            warn!("Timer {} was missed while Lily was down", timer.id);
+            return;
+        }
+    };
+    let lang = target.lang.clone().unwrap_or_else(|| def_lang.clone());
+    let context = ActionContext {
+        locale: lang.to_string(),
+        satellite: Some(SatelliteData {
+            uuid: uuid.clone(),
+            session: None,
+        }),
+        data: ContextData::Event {
+            event: "timer".to_string(),
+            data: HashMap::new(),
+        },
+    };
+
+    let res = TranslatedAnswer::new("embedded", "timer_missed")
+        .build(&context, true)
+        .and_then(|ans| process_answers(Some(vec![ans]), &lang, uuid));
+    if let Err(e) = res {
+        error!("Missed timer {} couldn't be announced: {}", timer.id, e);
+    }
+}
+
+/// Understand days as said by people, e.g: "weekdays", "monday and friday" or
+/// "fines de semana", returns them numbered from Monday (1)
+fn parse_days(text: &str, words: &TimeWords) -> Result<Vec<u32>> {
+    let text = text.to_lowercase();
+    let mut days = Vec::new();
+
+    for (names, group) in &words.day_groups {
+        if names.iter().any(|n| text.contains(n.as_str())) {
+            days.extend(group.iter().map(Weekday::number_from_monday));
+        }
+    }
+
+    let text_words = text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '-')
+        .filter(|w| !w.is_empty());
+    for word in text_words {
+        if let Some(day) = words.day(word) {
+            days.push(day.number_from_monday());
+        }
+    }
+
+    days.sort_unstable();
+    days.dedup();
+    if days.is_empty() {
+        Err(anyhow!("'{}' are not understandable days", text))
+    } else {
+        Ok(days)
+    }
+}
+
+/// Understand a time of the day, e.g: "7:30", "19:30:00" or "7 pm"
+fn parse_time(text: &str, words: &TimeWords) -> Result<NaiveTime> {
+    let err = || anyhow!("'{}' is not an understandable time", text);
+
+    // NLUs like Snips give full dates even for times
+    if let Ok(date) = NaiveDateTime::parse_from_str(text.get(..19).unwrap_or(""), "%F %T") {
+        return Ok(date.time());
+    }
+
+    let lower = text.trim().to_lowercase().replace('.', "");
+    let strip = |suffixes: &[String]| {
+        suffixes
+            .iter()
+            .find_map(|s| lower.strip_suffix(s.as_str()))
+            .map(str::trim)
+    };
+    let (clock, half) = if let Some(clock) = strip(&words.pm) {
+        (clock, Some(12))
+    } else if let Some(clock) = strip(&words.am) {
+        (clock, Some(0))
+    } else {
+        (lower.as_str(), None)
+    };
+
+    let mut parts = clock.split(':').map(|p| p.trim().parse::<u32>());
+    let hour = parts.next().ok_or_else(err)?.map_err(|_| err())?;
+    let min = parts.next().transpose().map_err(|_| err())?.unwrap_or(0);
+    let sec = parts.next().transpose().map_err(|_| err())?.unwrap_or(0);
+
+    let hour = match half {
+        Some(half) if (1..=12).contains(&hour) => hour % 12 + half,
+        Some(_) => return Err(err()),
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, min, sec).ok_or_else(err)
+}
+
+/// Understand a duration the way people say it, e.g: "5 minutes",
+/// "an hour and a half" or "treinta y cinco segundos"
+fn parse_duration(text: &str, words: &TimeWords) -> Option<Duration> {
+    let text = text.to_lowercase();
+    let mut total = 0.0;
+    let mut pending: Option<f64> = None;
+    let mut last_unit = None;
+    let mut found_unit = false;
+
+    let text_words = text
+        .split(|c: char| c.is_whitespace() || c == '-' || c == ',')
+        .filter(|w| !w.is_empty());
+    for word in text_words {
+        // Compact forms like "5min" or "90s"
+        let split = word
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .filter(|&i| i > 0)
+            .map(|i| word.split_at(i));
+        let parts = match split {
+            Some((n, u)) => vec![n, u],
+            None => vec![word],
+        };
+
+        for part in parts {
+            if let Some(secs) = words.unit_secs(part) {
+                total += pending.take().unwrap_or(1.0) * secs;
+                last_unit = Some(secs);
+                found_unit = true;
+            } else if words.half.iter().any(|h| h == part) {
+                match (pending, last_unit) {
+                    // "an hour and a half", "una hora y media"
+                    (_, Some(unit)) if pending.map(|p| p == 1.0).unwrap_or(true) => {
+                        total += unit / 2.0;
+                        pending = None;
+                    }
+                    // "half an hour"
+                    _ => pending = Some(0.5),
+                }
+            } else if let Some(n) = words.number(part) {
+                pending = match pending {
+                    // "twenty five", "treinta y cinco"
+                    Some(p) if p >= 20.0 && n < 10.0 => Some(p + n),
+                    // "half an hour"
+                    Some(p) if p == 0.5 && n == 1.0 => Some(p),
+                    _ => Some(n),
+                };
+            }
+        }
+    }
+
+    if found_unit && total > 0.0 {
+        Some(Duration::from_secs_f64(total))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills::translations::{register_translations, SkillTranslations};
+    use crate::vars::SKILL_TRANSLATIONS_DIR;
+    use std::path::Path;
+
+    // The words are in the embedded skill's translations
+    fn words(lang: &LanguageIdentifier) -> TimeWords {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/skills/embedded")
+            .join(SKILL_TRANSLATIONS_DIR);
+        let langs = ["en-US".parse().unwrap(), "es-ES".parse().unwrap()];
+        register_translations("embedded", SkillTranslations::load(&path, &langs).unwrap());
+        TimeWords::load(lang).unwrap()
+    }
+
+    fn en() -> LanguageIdentifier {
+        "en-US".parse().unwrap()
+    }
+
+    fn es() -> LanguageIdentifier {
+        "es-ES".parse().unwrap()
+    }
+
+    fn secs(text: &str, lang: &LanguageIdentifier) -> Option<u64> {
+        parse_duration(text, &words(lang)).map(|d| d.as_secs())
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(secs("5 minutes", &en()), Some(300));
+        assert_eq!(secs("an hour and a half", &en()), Some(5400));
+        assert_eq!(secs("half an hour", &en()), Some(1800));
+        assert_eq!(secs("twenty five seconds", &en()), Some(25));
+        assert_eq!(secs("1 hour, 10 minutes", &en()), Some(4200));
+        assert_eq!(secs("treinta y cinco segundos", &es()), Some(35));
+        assert_eq!(secs("una hora y media", &es()), Some(5400));
+    }
+
+    #[test]
+    fn compact_durations() {
+        assert_eq!(secs("90s", &en()), Some(90));
+        assert_eq!(secs("5min", &en()), Some(300));
+        assert_eq!(secs("1h 30m", &en()), Some(5400));
+    }
+
+    #[test]
+    fn not_durations() {
+        assert_eq!(secs("soon", &en()), None);
+        assert_eq!(secs("5", &en()), None);
+        assert_eq!(secs("0 minutes", &en()), None);
+    }
+
+    #[test]
+    fn timer_from_duration() {
+        words(&en());
+        let mut data = HashMap::new();
+        data.insert("duration".to_string(), "5 minutes".to_string());
+        assert!(matches!(
+            Timer::from_data(data.clone(), &en()).unwrap(),
+            TimerKind::Once(d) if d.as_secs() == 300
+        ));
+
+        data.insert("kind".to_string(), "every".to_string());
+        assert!(matches!(
+            Timer::from_data(data, &en()).unwrap(),
+            TimerKind::Every(d) if d.as_secs() == 300
+        ));
+
+        let mut data = HashMap::new();
+        data.insert("minutes".to_string(), "2".to_string());
+        data.insert("seconds".to_string(), "5".to_string());
+        assert!(matches!(
+            Timer::from_data(data, &en()).unwrap(),
+            TimerKind::Once(d) if d.as_secs() == 125
+        ));
+
+        let mut data = HashMap::new();
+        data.insert("duration".to_string(), "soon".to_string());
+        assert!(Timer::from_data(data, &en()).is_err());
+    }
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn days() {
+        let en_words = words(&en());
+        assert_eq!(
+            parse_days("weekdays", &en_words).unwrap(),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            parse_days("Monday and friday", &en_words).unwrap(),
+            vec![1, 5]
+        );
+        assert_eq!(
+            parse_days("sat, sun and weekends", &en_words).unwrap(),
+            vec![6, 7]
+        );
+        assert_eq!(
+            parse_days("los fines de semana", &words(&es())).unwrap(),
+            vec![6, 7]
+        );
+        assert!(parse_days("someday", &en_words).is_err());
+    }
+
+    #[test]
+    fn times() {
+        let en_words = words(&en());
+        let time = |text| parse_time(text, &en_words).ok();
+        assert_eq!(time("7:30"), NaiveTime::from_hms_opt(7, 30, 0));
+        assert_eq!(time("19:30:15"), NaiveTime::from_hms_opt(19, 30, 15));
+        assert_eq!(time("7 pm"), NaiveTime::from_hms_opt(19, 0, 0));
+        assert_eq!(time("12 a.m."), NaiveTime::from_hms_opt(0, 0, 0));
+        assert_eq!(
+            time("2021-05-01 19:30:00 +02:00"),
+            NaiveTime::from_hms_opt(19, 30, 0)
+        );
+        assert_eq!(time("13 pm"), None);
+        assert_eq!(time("25:00"), None);
+        assert_eq!(time("noon"), None);
+        assert_eq!(
+            parse_time("7 de la tarde", &words(&es())).ok(),
+            NaiveTime::from_hms_opt(19, 0, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_recurrence() {
+        let zone = Zone::Named(chrono_tz::UTC);
+        let rule = Recurrence::weekly("monday", "7:30", &words(&en())).unwrap();
+        // 2024-01-01 is a Monday
+        assert_eq!(
+            rule.next_after(&utc("2024-01-01T07:00:00Z"), zone),
+            Some(utc("2024-01-01T07:30:00Z"))
+        );
+        assert_eq!(
+            rule.next_after(&utc("2024-01-01T07:30:00Z"), zone),
+            Some(utc("2024-01-08T07:30:00Z"))
+        );
+
+        // Rules are kept in the timer's zone
+        let madrid = Zone::Named(chrono_tz::Europe::Madrid);
+        assert_eq!(
+            rule.next_after(&utc("2024-01-01T05:00:00Z"), madrid),
+            Some(utc("2024-01-01T06:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn cron_recurrence() {
+        let zone = Zone::Named(chrono_tz::UTC);
+        // Without seconds, the way most people know cron
+        let rule = Recurrence::cron("30 7 * * Mon").unwrap();
+        assert_eq!(
+            rule.next_after(&utc("2024-01-01T08:00:00Z"), zone),
+            Some(utc("2024-01-08T07:30:00Z"))
+        );
+        let rule = Recurrence::cron("15 0 12 * * *").unwrap();
+        assert_eq!(
+            rule.next_after(&utc("2024-01-01T08:00:00Z"), zone),
+            Some(utc("2024-01-01T12:00:15Z"))
+        );
+        assert!(Recurrence::cron("every monday").is_err());
+    }
+
+    #[test]
+    fn timer_from_rules() {
+        words(&en());
+        let mut data = HashMap::new();
+        data.insert("cron".to_string(), "0 9 * * *".to_string());
+        assert!(matches!(
+            Timer::from_data(data, &en()).unwrap(),
+            TimerKind::Recurring(Recurrence::Cron(_))
+        ));
+
+        let mut data = HashMap::new();
+        data.insert("days".to_string(), "weekend".to_string());
+        assert!(Timer::from_data(data.clone(), &en()).is_err());
+        data.insert("time".to_string(), "10:00".to_string());
+        assert!(matches!(
+            Timer::from_data(data, &en()).unwrap(),
+            TimerKind::Recurring(Recurrence::Weekly { days, time })
+                if days == vec![6, 7] && time == 36000
+        ));
+
+        let mut data = HashMap::new();
+        data.insert("date".to_string(), "2000-01-01 10:00:00".to_string());
+        assert!(Timer::from_data(data, &en()).is_err());
+    }
+
+    fn stored(due: i64, every: Option<u64>) -> StoredTimer {
+        StoredTimer {
+            id: 0,
+            due,
+            every,
+            recurrence: None,
+            action: "embedded/timer_rang".to_string(),
+            lang: Some("en-US".to_string()),
+            satellite: Some("kitchen".to_string()),
+            label: None,
+            paused: None,
+        }
+    }
+
+    #[test]
+    fn stored_timers() {
+        let path = std::env::temp_dir()
+            .join("lily_timers_test")
+            .join("timers.json");
+        let _ = fs::remove_file(&path);
+
+        let mut store = TimerStore::new(path.clone());
+        let once = store.add(stored(1000, None));
+        let every = store.add(stored(1000, Some(60)));
+        assert_ne!(once, every);
+
+        let mut loaded = TimerStore::new(path.clone());
+        loaded.load().unwrap();
+        assert_eq!(loaded.timers.len(), 2);
+        assert_eq!(loaded.get(every).unwrap().every, Some(60));
+
+        // Missed rings are skipped
+        assert_eq!(loaded.rang(every, 1130), Some(1180));
+        assert_eq!(loaded.get(every).unwrap().due, 1180);
+        assert_eq!(loaded.rang(once, 1130), None);
+        assert!(loaded.get(once).is_none());
+        assert_eq!(loaded.rung["kitchen"].0.id, once);
+
+        loaded.remove(every);
+        let mut reloaded = TimerStore::new(path.clone());
+        reloaded.load().unwrap();
+        assert!(reloaded.timers.is_empty());
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::skills::translations::get_translations;
+
+use anyhow::{anyhow, Result};
+use chrono::Weekday;
+use unic_langid::LanguageIdentifier;
+
+/// Words people use to say durations, days and times in a language, those
+/// are in the embedded skill's translations so that each language has it's
+/// own (e.g: "once" is 11 in Spanish).
+pub struct TimeWords {
+    // Each unit with it's length in seconds
+    pub units: Vec<(Vec<String>, f64)>,
+    pub half: Vec<String>,
+    pub numbers: HashMap<String, f64>,
+    pub day_groups: Vec<(Vec<String>, Vec<Weekday>)>,
+    pub days: Vec<(Vec<String>, Weekday)>,
+    pub am: Vec<String>,
+    pub pm: Vec<String>,
+}
+
+impl TimeWords {
+    pub fn load(lang: &LanguageIdentifier) -> Result<Self> {
+        let trans = get_translations("embedded")?;
+        let list = |id: &str| -> Result<Vec<String>> {
+            let text = trans
+                .get_all(lang, id, None)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("Translation \"{}\" is empty", id))?;
+            Ok(text
+                .split(',')
+                .map(|w| w.trim().to_lowercase())
+                .filter(|w| !w.is_empty())
+                .collect())
+        };
+
+        let mut numbers = HashMap::new();
+        for entry in list("time_numbers")? {
+            let (word, n) = entry
+                .split_once('=')
+                .and_then(|(w, n)| Some((w.trim().to_string(), n.trim().parse().ok()?)))
+                .ok_or_else(|| anyhow!("'{}' is not a word and it's number", entry))?;
+            numbers.insert(word, n);
+        }
+
+        let all_days = [
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ];
+        let day_ids = [
+            "time_monday",
+            "time_tuesday",
+            "time_wednesday",
+            "time_thursday",
+            "time_friday",
+            "time_saturday",
+            "time_sunday",
+        ];
+
+        Ok(Self {
+            units: vec![
+                (list("time_unit_seconds")?, 1.0),
+                (list("time_unit_minutes")?, 60.0),
+                (list("time_unit_hours")?, 3600.0),
+            ],
+            half: list("time_half")?,
+            numbers,
+            day_groups: vec![
+                (list("time_every_day")?, all_days.to_vec()),
+                (list("time_weekdays")?, all_days[..5].to_vec()),
+                (list("time_weekend")?, all_days[5..].to_vec()),
+            ],
+            days: day_ids
+                .iter()
+                .zip(all_days.iter())
+                .map(|(id, day)| Ok((list(id)?, *day)))
+                .collect::<Result<_>>()?,
+            am: list("time_am")?,
+            pm: list("time_pm")?,
+        })
+    }
+
+    pub fn unit_secs(&self, word: &str) -> Option<f64> {
+        self.units
+            .iter()
+            .find(|(words, _)| words.iter().any(|w| w == word))
+            .map(|(_, secs)| *secs)
+    }
+
+    pub fn number(&self, word: &str) -> Option<f64> {
+        word.parse()
+            .ok()
+            .or_else(|| self.numbers.get(word).copied())
+    }
+
+    pub fn day(&self, word: &str) -> Option<Weekday> {
+        self.days
+            .iter()
+            .find(|(words, _)| words.iter().any(|w| w == word))
+            .map(|(_, day)| *day)
+    }
+}
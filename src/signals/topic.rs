@@ -0,0 +1,270 @@
+// Standard library
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// This crate
+use crate::actions::{ActionContext, ActionSet, ContextData, SatelliteData};
+use crate::config::Config;
+use crate::exts::LockIt;
+use crate::signals::{announce_answers, Signal, SignalEventShared, UserSignal};
+
+// Other crates
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use log::{debug, error};
+use regex::Regex;
+use rumqttc::{matches, valid_filter, AsyncClient, QoS};
+use tokio::task::spawn_local;
+use unic_langid::LanguageIdentifier;
+
+lazy_static! {
+    // The MQTT loop needs to reach it, to pass the messages it doesn't know
+    pub static ref TOPIC_SIGNAL: Mutex<Option<Arc<Mutex<TopicSignal>>>> = Mutex::new(None);
+}
+
+struct TopicSub {
+    filter: String,
+    // Dotted path inside of a JSON payload (e.g: "state.power"), what is
+    // checked instead of the whole payload
+    field: Option<String>,
+    value: Option<String>,
+    matches: Option<Regex>,
+    satellite: Option<String>,
+    act_set: ActionSet,
+}
+
+impl TopicSub {
+    fn new(mut data: HashMap<String, String>, act_set: ActionSet) -> Result<Self> {
+        let filter = data
+            .remove("topic")
+            .ok_or_else(|| anyhow!("A \"topic\" is needed"))?;
+        if !valid_filter(&filter) {
+            return Err(anyhow!("\"{}\" is not a valid topic filter", filter));
+        }
+        let matches = data.remove("matches").map(|r| Regex::new(&r)).transpose()?;
+
+        Ok(Self {
+            filter,
+            field: data.remove("field"),
+            value: data.remove("value"),
+            matches,
+            satellite: data.remove("satellite"),
+            act_set,
+        })
+    }
+
+    /// The event data if the message is for this one
+    fn check(&self, topic: &str, payload: &str) -> Option<HashMap<String, String>> {
+        if !matches(topic, &self.filter) {
+            return None;
+        }
+
+        let mut data = HashMap::new();
+        let checked = match self.field {
+            Some(ref field) => {
+                let value = json_field(payload, field)?;
+                data.insert("value".to_string(), value.clone());
+                value
+            }
+            None => payload.to_string(),
+        };
+
+        if self.value.as_ref().map_or(false, |v| *v != checked) {
+            return None;
+        }
+        if self
+            .matches
+            .as_ref()
+            .map_or(false, |r| !r.is_match(&checked))
+        {
+            return None;
+        }
+
+        data.insert("topic".to_string(), topic.to_string());
+        data.insert("payload".to_string(), payload.to_string());
+        Some(data)
+    }
+}
+
+/// Calls actions when a message arrives to an MQTT topic of the server's
+/// broker. Takes "topic" (wildcards allowed) and optionally "field", "value"
+/// and "matches" (a regex) to filter by payload; and "satellite" to say the
+/// answers there instead of on every satellite. Actions get "topic",
+/// "payload" and "value" (the field's) as event data.
+pub struct TopicSignal {
+    subs: Vec<TopicSub>,
+    client: Option<Arc<Mutex<AsyncClient>>>,
+    def_lang: Option<LanguageIdentifier>,
+}
+
+impl TopicSignal {
+    pub fn new() -> Self {
+        Self {
+            subs: Vec::new(),
+            client: None,
+            def_lang: None,
+        }
+    }
+
+    /// Subscribe to every filter, called each time the server connects
+    pub fn connected(&mut self, client: Arc<Mutex<AsyncClient>>) -> Result<()> {
+        {
+            let c = client.lock_it();
+            for sub in &self.subs {
+                c.try_subscribe(sub.filter.clone(), QoS::AtMostOnce)?;
+            }
+        }
+        self.client = Some(client);
+        Ok(())
+    }
+
+    pub fn on_message(&self, topic: &str, payload: &[u8]) {
+        let lang = match self.def_lang {
+            Some(ref lang) => lang.clone(),
+            None => return,
+        };
+        let payload = String::from_utf8_lossy(payload);
+
+        for sub in &self.subs {
+            if let Some(data) = sub.check(topic, &payload) {
+                debug!("Message on \"{}\" matched \"{}\"", topic, sub.filter);
+                let act_set = sub.act_set.clone();
+                let satellite = sub.satellite.clone();
+                let lang = lang.clone();
+                spawn_local(async move {
+                    let context = ActionContext {
+                        locale: lang.to_string(),
+                        satellite: satellite.as_ref().map(|uuid| SatelliteData {
+                            uuid: uuid.clone(),
+                            session: None,
+                        }),
+                        data: ContextData::Event {
+                            event: "mqtt".into(),
+                            data,
+                        },
+                    };
+                    let ans = act_set.call_all(&context).await;
+                    announce_answers(ans, &lang, satellite.as_deref());
+                });
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Signal for TopicSignal {
+    fn end_load(&mut self, _curr_lang: &[LanguageIdentifier]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn event_loop(
+        &mut self,
+        _signal_event: SignalEventShared,
+        _config: &Config,
+        curr_lang: &[LanguageIdentifier],
+    ) -> Result<()> {
+        // Messages come from the server's MQTT loop, nothing to wait for here
+        self.def_lang = Some(curr_lang[0].clone());
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl UserSignal for TopicSignal {
+    fn add(
+        &mut self,
+        data: HashMap<String, String>,
+        _skill_name: &str,
+        act_set: ActionSet,
+    ) -> Result<()> {
+        let sub = TopicSub::new(data, act_set)?;
+        if let Some(ref client) = self.client {
+            if let Err(e) = client
+                .lock_it()
+                .try_subscribe(sub.filter.clone(), QoS::AtMostOnce)
+            {
+                error!("Couldn't subscribe to \"{}\": {}", sub.filter, e);
+            }
+        }
+        self.subs.push(sub);
+        Ok(())
+    }
+}
+
+// Texts are used as they are, anything else as JSON
+fn json_field(payload: &str, field: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(payload).ok()?;
+    let value = field.split('.').try_fold(&json, |v, key| match v {
+        serde_json::Value::Array(a) => a.get(key.parse::<usize>().ok()?),
+        other => other.get(key),
+    })?;
+
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub(pairs: &[(&str, &str)]) -> TopicSub {
+        let data = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        TopicSub::new(data, ActionSet::empty()).unwrap()
+    }
+
+    #[test]
+    fn fields_of_json() {
+        let payload = r#"{"state": {"power": "on", "level": 42}, "list": [1, "two"]}"#;
+        assert_eq!(json_field(payload, "state.power"), Some("on".to_string()));
+        assert_eq!(json_field(payload, "state.level"), Some("42".to_string()));
+        assert_eq!(json_field(payload, "list.1"), Some("two".to_string()));
+        assert_eq!(
+            json_field(payload, "state"),
+            Some(r#"{"level":42,"power":"on"}"#.to_string())
+        );
+        assert_eq!(json_field(payload, "state.missing"), None);
+        assert_eq!(json_field(payload, "list.5"), None);
+        assert_eq!(json_field("not json", "state"), None);
+    }
+
+    #[test]
+    fn topic_filters() {
+        let s = sub(&[("topic", "home/+/washer")]);
+        let data = s.check("home/basement/washer", "done").unwrap();
+        assert_eq!(data["topic"], "home/basement/washer");
+        assert_eq!(data["payload"], "done");
+        assert!(!data.contains_key("value"));
+        assert!(s.check("home/basement/dryer", "done").is_none());
+
+        assert!(sub(&[("topic", "home/#")])
+            .check("home/a/b/c", "")
+            .is_some());
+        assert!(TopicSub::new(
+            vec![("topic".to_string(), "home/#/bad".to_string())]
+                .into_iter()
+                .collect(),
+            ActionSet::empty()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn payload_filters() {
+        let s = sub(&[("topic", "washer"), ("field", "state"), ("value", "done")]);
+        let data = s.check("washer", r#"{"state": "done"}"#).unwrap();
+        assert_eq!(data["value"], "done");
+        assert!(s.check("washer", r#"{"state": "washing"}"#).is_none());
+        assert!(s.check("washer", r#"{"other": "done"}"#).is_none());
+        assert!(s.check("washer", "done").is_none());
+
+        let s = sub(&[("topic", "temp"), ("matches", "^[3-9][0-9]")]);
+        assert!(s.check("temp", "35.5").is_some());
+        assert!(s.check("temp", "21.0").is_none());
+    }
+}
@@ -0,0 +1,368 @@
+// Standard library
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+// This crate
+use crate::actions::{ActionAnswer, ActionContext, ActionSet, ContextData, MainAnswer};
+use crate::config::Config;
+use crate::exts::LockIt;
+use crate::signals::{announce_answers, Signal, SignalEventShared, UserSignal};
+use crate::vars::{DEFAULT_WEBHOOK_PORT, WEBHOOK_MAX_BODY};
+
+// Other crates
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use log::{error, info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::task::spawn_local;
+use unic_langid::LanguageIdentifier;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConf {
+    // Use "0.0.0.0" to accept hooks from other machines
+    #[serde(default = "def_address")]
+    pub address: String,
+    #[serde(default = "def_port")]
+    pub port: u16,
+    // Hooks are at "<path>/<name>"
+    #[serde(default = "def_path")]
+    pub path: String,
+    // Needed by every hook which doesn't have it's own
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+fn def_address() -> String {
+    "127.0.0.1".into()
+}
+
+fn def_port() -> u16 {
+    DEFAULT_WEBHOOK_PORT
+}
+
+fn def_path() -> String {
+    "/hook".into()
+}
+
+impl Default for WebhookConf {
+    fn default() -> Self {
+        Self {
+            address: def_address(),
+            port: def_port(),
+            path: def_path(),
+            secret: None,
+        }
+    }
+}
+
+struct Hook {
+    secret: Option<String>,
+    satellite: Option<String>,
+    act_set: ActionSet,
+}
+
+// What the server needs, shared with it as hooks can be added while it runs
+struct WebhookState {
+    hooks: HashMap<String, Hook>,
+    conf: WebhookConf,
+    lang: Option<LanguageIdentifier>,
+}
+
+/// Calls actions when something POSTs to "/hook/<name>" with a JSON body (or
+/// none). Takes "name", and optionally "secret" (sent in the "X-Lily-Secret"
+/// header or as a bearer token) and "satellite" to say the answers there
+/// instead of on every satellite. The fields of the body are the event data,
+/// the answers are also sent back as JSON.
+pub struct WebhookSignal {
+    state: Arc<Mutex<WebhookState>>,
+    running: bool,
+}
+
+impl WebhookSignal {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(WebhookState {
+                hooks: HashMap::new(),
+                conf: WebhookConf::default(),
+                lang: None,
+            })),
+            running: false,
+        }
+    }
+
+    // Only listen once there's some hook, no need for an open port otherwise
+    fn start(&mut self) {
+        if self.running || self.state.lock_it().hooks.is_empty() {
+            return;
+        }
+        self.running = true;
+
+        let state = self.state.clone();
+        spawn_local(async move {
+            if let Err(e) = serve(state).await {
+                error!("Webhook server stopped: {}", e);
+            }
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Signal for WebhookSignal {
+    fn end_load(&mut self, _curr_lang: &[LanguageIdentifier]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn event_loop(
+        &mut self,
+        _signal_event: SignalEventShared,
+        config: &Config,
+        curr_lang: &[LanguageIdentifier],
+    ) -> Result<()> {
+        {
+            let mut state = self.state.lock_it();
+            state.conf = config.webhook.clone();
+            state.lang = Some(curr_lang[0].clone());
+        }
+        self.start();
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl UserSignal for WebhookSignal {
+    fn add(
+        &mut self,
+        mut data: HashMap<String, String>,
+        _skill_name: &str,
+        act_set: ActionSet,
+    ) -> Result<()> {
+        let name = data
+            .remove("name")
+            .ok_or_else(|| anyhow!("A hook needs a \"name\""))?;
+        let hook = Hook {
+            secret: data.remove("secret"),
+            satellite: data.remove("satellite"),
+            act_set,
+        };
+
+        {
+            let mut state = self.state.lock_it();
+            if state.hooks.contains_key(&name) {
+                return Err(anyhow!("Hook \"{}\" already exists", name));
+            }
+            state.hooks.insert(name, hook);
+        }
+
+        if self.state.lock_it().lang.is_some() {
+            self.start();
+        }
+        Ok(())
+    }
+}
+
+async fn serve(state: Arc<Mutex<WebhookState>>) -> Result<()> {
+    let addr = {
+        let state = state.lock_it();
+        format!("{}:{}", state.conf.address, state.conf.port)
+    };
+    let listener = TcpListener::bind(&addr).await?;
+    info!("Listening for webhooks on {}", addr);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = state.clone();
+        spawn_local(async move {
+            let service = service_fn(move |req| handle(req, state.clone()));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                warn!("Webhook connection failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(
+    req: Request<Incoming>,
+    state: Arc<Mutex<WebhookState>>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if req.method() != Method::POST {
+        return Ok(reply(StatusCode::METHOD_NOT_ALLOWED, json!({})));
+    }
+
+    // Take what's needed and let go of the state, actions might take a while
+    let (name, act_set, satellite, lang) = {
+        let state = state.lock_it();
+        let name = req
+            .uri()
+            .path()
+            .strip_prefix(state.conf.path.trim_end_matches('/'))
+            .and_then(|p| p.strip_prefix('/'))
+            .unwrap_or_default();
+        let hook = match state.hooks.get(name) {
+            Some(hook) => hook,
+            None => return Ok(reply(StatusCode::NOT_FOUND, json!({}))),
+        };
+
+        let secret = hook.secret.as_ref().or_else(|| state.conf.secret.as_ref());
+        if let Some(secret) = secret {
+            if request_secret(&req) != Some(secret.as_str()) {
+                return Ok(reply(StatusCode::UNAUTHORIZED, json!({})));
+            }
+        }
+
+        let lang = match state.lang {
+            Some(ref lang) => lang.clone(),
+            None => return Ok(reply(StatusCode::SERVICE_UNAVAILABLE, json!({}))),
+        };
+        (
+            name.to_string(),
+            hook.act_set.clone(),
+            hook.satellite.clone(),
+            lang,
+        )
+    };
+
+    let body = match Limited::new(req.into_body(), WEBHOOK_MAX_BODY)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(_) => return Ok(reply(StatusCode::PAYLOAD_TOO_LARGE, json!({}))),
+    };
+    let data = match body_data(&name, &body) {
+        Some(data) => data,
+        None => {
+            return Ok(reply(
+                StatusCode::BAD_REQUEST,
+                json!({"error": "Body is not valid JSON"}),
+            ))
+        }
+    };
+
+    let context = ActionContext {
+        locale: lang.to_string(),
+        satellite: None,
+        data: ContextData::Event {
+            event: "webhook".into(),
+            data,
+        },
+    };
+    let ans = act_set.call_all(&context).await;
+    let answers = answers_json(&ans);
+    announce_answers(ans, &lang, satellite.as_deref());
+
+    Ok(reply(StatusCode::OK, json!({ "answers": answers })))
+}
+
+fn request_secret(req: &Request<Incoming>) -> Option<&str> {
+    let headers = req.headers();
+    if let Some(secret) = headers.get("X-Lily-Secret") {
+        return secret.to_str().ok();
+    }
+    headers
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+// Each field of a JSON object is one entry, texts are used as they are and
+// anything else as JSON. The whole body is in "body".
+fn body_data(name: &str, body: &[u8]) -> Option<HashMap<String, String>> {
+    let text = std::str::from_utf8(body).ok()?;
+    let mut data = HashMap::new();
+    if !text.trim().is_empty() {
+        let json: serde_json::Value = serde_json::from_str(text).ok()?;
+        if let serde_json::Value::Object(fields) = json {
+            for (key, value) in fields {
+                let value = match value {
+                    serde_json::Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                data.insert(key, value);
+            }
+        }
+    }
+    data.insert("hook".into(), name.to_string());
+    data.insert("body".into(), text.to_string());
+    Some(data)
+}
+
+fn answers_json(ans: &[ActionAnswer]) -> serde_json::Value {
+    ans.iter()
+        .map(|a| match a.answer {
+            MainAnswer::Text(ref text) => json!({
+                "text": text,
+                "end_session": a.should_end_session,
+            }),
+            MainAnswer::Sound(_) => json!({
+                "sound": true,
+                "end_session": a.should_end_session,
+            }),
+            MainAnswer::Nothing => json!({
+                "end_session": a.should_end_session,
+            }),
+        })
+        .collect()
+}
+
+fn reply(status: StatusCode, body: serde_json::Value) -> Response<Full<Bytes>> {
+    let mut res = Response::new(Full::new(Bytes::from(body.to_string())));
+    *res.status_mut() = status;
+    res.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_from_body() {
+        let data = body_data("door", br#"{"who": "Ana", "count": 2}"#).unwrap();
+        assert_eq!(data["who"], "Ana");
+        assert_eq!(data["count"], "2");
+        assert_eq!(data["hook"], "door");
+        assert_eq!(data["body"], r#"{"who": "Ana", "count": 2}"#);
+
+        let data = body_data("door", b"").unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data["body"], "");
+
+        // Not an object, only the body itself
+        let data = body_data("door", b"[1, 2]").unwrap();
+        assert_eq!(data.len(), 2);
+
+        assert!(body_data("door", b"not json").is_none());
+        assert!(body_data("door", &[0xff, 0xfe]).is_none());
+    }
+
+    #[test]
+    fn answers_as_json() {
+        let ans = vec![
+            ActionAnswer::send_text("Opening".to_string(), false).unwrap(),
+            ActionAnswer::nothing(true).unwrap(),
+        ];
+        assert_eq!(
+            answers_json(&ans),
+            json!([
+                {"text": "Opening", "end_session": false},
+                {"end_session": true},
+            ])
+        );
+    }
+}
@@ -4,12 +4,18 @@ use std::sync::{Arc, Mutex};
 // This crate
 use crate::actions::{SayHelloAction, ACT_REG};
 use crate::exts::LockIt;
-use crate::signals::{new_signal_order, poll::PollQuery, Timer, SIG_REG};
+use crate::signals::{
+    new_signal_order, poll::PollQuery, Timer, TimerManageAction, TimerOp, TimerRingAction,
+    TopicSignal, WebhookSignal, SIG_REG, TOPIC_SIGNAL,
+};
+use crate::skills::translations::{register_translations, SkillTranslations};
 use crate::skills::SkillLoader;
+use crate::vars::{SKILLS_PATH, SKILL_TRANSLATIONS_DIR};
 
 // Other crates
 use anyhow::Result;
 use async_trait::async_trait;
+use log::warn;
 use unic_langid::LanguageIdentifier;
 pub struct EmbeddedLoader {}
 
@@ -22,14 +28,27 @@ impl EmbeddedLoader {
 #[async_trait(?Send)]
 impl SkillLoader for EmbeddedLoader {
     fn load_skills(&mut self, langs: &[LanguageIdentifier]) -> Result<()> {
+        // Kept apart, so that timers can be managed by Lily's own actions
+        let timer = Arc::new(Mutex::new(Timer::new(langs[0].clone())));
         {
             let mut mut_sigreg = SIG_REG.lock_it();
             mut_sigreg.set_order(Arc::new(Mutex::new(new_signal_order(langs.to_owned()))))?;
             mut_sigreg.set_poll(Arc::new(Mutex::new(PollQuery::new())))?;
+            mut_sigreg.insert("embedded", "timer", timer.clone())?;
+
+            let topics = Arc::new(Mutex::new(TopicSignal::new()));
+            *TOPIC_SIGNAL.lock_it() = Some(topics.clone());
+            mut_sigreg.insert("embedded", "mqtt", topics)?;
             mut_sigreg.insert(
                 "embedded",
-                "timer",
-                Arc::new(Mutex::new(Timer::new())),
+                "webhook",
+                Arc::new(Mutex::new(WebhookSignal::new())),
+            )?;
+            #[cfg(target_os = "linux")]
+            mut_sigreg.insert(
+                "embedded",
+                "fs_watch",
+                Arc::new(Mutex::new(crate::signals::FsWatchSignal::new())),
             )?;
         }
 
@@ -40,6 +59,34 @@ impl SkillLoader for EmbeddedLoader {
                 "say_hello",
                 Arc::new(Mutex::new(SayHelloAction::new())),
             )?;
+            mut_actreg.insert(
+                "embedded",
+                "timer_ring",
+                Arc::new(Mutex::new(TimerRingAction::new())),
+            )?;
+            for op in &[
+                TimerOp::List,
+                TimerOp::Cancel,
+                TimerOp::Pause,
+                TimerOp::Resume,
+                TimerOp::Snooze,
+            ] {
+                mut_actreg.insert(
+                    "embedded",
+                    op.name(),
+                    Arc::new(Mutex::new(TimerManageAction::new(timer.clone(), *op))),
+                )?;
+            }
+        }
+
+        // Texts used by Lily's own actions
+        let trans_path = SKILLS_PATH
+            .resolve()
+            .join("embedded")
+            .join(SKILL_TRANSLATIONS_DIR);
+        match SkillTranslations::load(&trans_path, langs) {
+            Ok(trans) => register_translations("embedded", trans),
+            Err(e) => warn!("Embedded translations couldn't be loaded: {}", e),
         }
 
         Ok(())
@@ -0,0 +1,182 @@
+/**
+ * Rhasspy-like satellites, those just stream their microphone through
+ * "hermes/audioServer/<site>/audioFrame" and play whatever they get in
+ * "hermes/audioServer/<site>/playBytes/<request>". Since they don't know when
+ * the user stops talking that is done here.
+ */
+// Standard library
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// This crate
+use crate::signals::dev_mgmt::new_session_id;
+use crate::vars::{
+    HERMES_END_SILENCE, HERMES_LISTEN_TIMEOUT, HERMES_MAX_UTTERANCE, HERMES_PRE_ROLL_SAMPLES,
+    HERMES_VAD_MIN_RMS, HERMES_VAD_NOISE_RATIO,
+};
+
+// Other crates
+use lily_common::audio::AudioRaw;
+use log::debug;
+
+thread_local! {
+    pub static AUDIO_SERVER_SITES: RefCell<AudioServerSites> = RefCell::new(AudioServerSites::new());
+}
+
+pub struct AudioServerSites {
+    sites: HashMap<String, SiteState>,
+}
+
+#[derive(Default)]
+struct SiteState {
+    listener: Option<Endpointer>,
+    // Request id of what the site is playing right now
+    playing: Option<String>,
+    // Start listening as soon as it ends playing (so it doesn't hear itself)
+    listen_after_play: bool,
+}
+
+impl AudioServerSites {
+    fn new() -> Self {
+        Self {
+            sites: HashMap::new(),
+        }
+    }
+
+    pub fn is_site(&self, site_id: &str) -> bool {
+        self.sites.contains_key(site_id)
+    }
+
+    pub fn start_listening(&mut self, site_id: &str) {
+        let site = self.sites.entry(site_id.to_string()).or_default();
+        if site.playing.is_some() {
+            site.listen_after_play = true;
+        } else {
+            debug!("Listening to site {}", site_id);
+            site.listener = Some(Endpointer::new());
+        }
+    }
+
+    pub fn stop_listening(&mut self, site_id: &str) {
+        if let Some(site) = self.sites.get_mut(site_id) {
+            site.listener = None;
+            site.listen_after_play = false;
+        }
+    }
+
+    /// Register that something is going to be played on the site, returns
+    /// the id of the request
+    pub fn play(&mut self, site_id: &str) -> String {
+        let request_id = new_session_id();
+        let site = self.sites.entry(site_id.to_string()).or_default();
+        site.playing = Some(request_id.clone());
+        request_id
+    }
+
+    pub fn play_finished(&mut self, site_id: &str, request_id: &str) {
+        let listen = match self.sites.get_mut(site_id) {
+            Some(site) if site.playing.as_deref() == Some(request_id) => {
+                site.playing = None;
+                std::mem::take(&mut site.listen_after_play)
+            }
+            _ => false,
+        };
+
+        if listen {
+            self.start_listening(site_id);
+        }
+    }
+
+    /// Take a frame from a site, returns the audio that has to be sent to
+    /// the STT and whether the user is done talking
+    pub fn process_frame(&mut self, site_id: &str, frame: AudioRaw) -> Option<(Vec<i16>, bool)> {
+        let site = self.sites.entry(site_id.to_string()).or_default();
+        let listener = site.listener.as_mut()?;
+        if frame.len() == 0 {
+            return None;
+        }
+
+        match listener.process(frame) {
+            EndpointState::NoOneTalking => None,
+            EndpointState::Hearing(audio) => Some((audio, false)),
+            EndpointState::Done(audio) => {
+                site.listener = None;
+                Some((audio, true))
+            }
+            EndpointState::TimedOut => {
+                debug!("No one talked to site {}", site_id);
+                site.listener = None;
+                None
+            }
+        }
+    }
+}
+
+enum EndpointState {
+    NoOneTalking,
+    Hearing(Vec<i16>),
+    Done(Vec<i16>),
+    TimedOut,
+}
+
+// Energy based voice detection, the threshold adapts to the background noise
+// heard before the user starts talking
+struct Endpointer {
+    started: Instant,
+    noise_floor: f64,
+    heard_voice: bool,
+    silence: f32,
+    // What was heard just before the user started talking
+    pre_roll: VecDeque<i16>,
+}
+
+impl Endpointer {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            // Until we hear the background the minimum is used
+            noise_floor: HERMES_VAD_MIN_RMS / HERMES_VAD_NOISE_RATIO,
+            heard_voice: false,
+            silence: 0.0,
+            pre_roll: VecDeque::with_capacity(HERMES_PRE_ROLL_SAMPLES),
+        }
+    }
+
+    fn process(&mut self, frame: AudioRaw) -> EndpointState {
+        let rms = frame.rms();
+        let is_voice = rms > (self.noise_floor * HERMES_VAD_NOISE_RATIO).max(HERMES_VAD_MIN_RMS);
+        let elapsed = self.started.elapsed();
+
+        if !self.heard_voice {
+            if is_voice {
+                self.heard_voice = true;
+                let mut audio: Vec<i16> = self.pre_roll.drain(..).collect();
+                audio.extend(frame.buffer);
+                EndpointState::Hearing(audio)
+            } else if elapsed > Duration::from_millis(HERMES_LISTEN_TIMEOUT) {
+                EndpointState::TimedOut
+            } else {
+                self.noise_floor = self.noise_floor * 0.9 + rms * 0.1;
+                self.pre_roll.extend(frame.buffer);
+                let excess = self.pre_roll.len().saturating_sub(HERMES_PRE_ROLL_SAMPLES);
+                self.pre_roll.drain(..excess);
+                EndpointState::NoOneTalking
+            }
+        } else {
+            if is_voice {
+                self.silence = 0.0;
+            } else {
+                self.silence += frame.len_s();
+            }
+
+            if self.silence >= HERMES_END_SILENCE
+                || elapsed > Duration::from_millis(HERMES_MAX_UTTERANCE)
+            {
+                EndpointState::Done(frame.buffer)
+            } else {
+                EndpointState::Hearing(frame.buffer)
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+/**
+ * Snips-style datasets, the way Hermes skills declare their intents.
+ *
+ * Those are YAML files with a document per intent or entity, like:
+ *
+ * ---
+ * type: intent
+ * name: turnLightOn
+ * slots:
+ *   - name: room
+ *     entity: room
+ * utterances:
+ *   - turn on the lights in the [room](kitchen)
+ *
+ * ---
+ * type: entity
+ * name: room
+ * automatically_extensible: no
+ * values:
+ *   - bedroom
+ *   - [living room, main room, lounge]
+ */
+// Standard library
+use std::collections::HashMap;
+
+// This crate
+use crate::nlu::{EntityData, EntityDef, IntentData, OrderKind, SlotData};
+use crate::signals::collections::Hook;
+
+// Other crates
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Dataset {
+    #[serde(default)]
+    pub intents: Vec<DatasetIntent>,
+
+    #[serde(default)]
+    pub entities: Vec<DatasetEntity>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum DatasetDoc {
+    Intent(DatasetIntent),
+    Entity(DatasetEntity),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DatasetIntent {
+    pub name: String,
+
+    #[serde(default)]
+    slots: Vec<DatasetSlot>,
+
+    utterances: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatasetSlot {
+    name: String,
+    entity: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DatasetEntity {
+    pub name: String,
+
+    #[serde(default = "default_true", deserialize_with = "yes_no")]
+    automatically_extensible: bool,
+
+    #[serde(default)]
+    values: Vec<EntityValue>,
+}
+
+// Either just the value or the value followed by it's synonyms
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EntityValue {
+    Single(String),
+    WithSynonyms(Vec<String>),
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Snips datasets use YAML 1.1 booleans ("yes" and "no")
+fn yes_no<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum YesNo {
+        Bool(bool),
+        Text(String),
+    }
+
+    match YesNo::deserialize(deserializer)? {
+        YesNo::Bool(b) => Ok(b),
+        YesNo::Text(s) => match s.to_lowercase().as_str() {
+            "yes" | "true" | "on" => Ok(true),
+            "no" | "false" | "off" => Ok(false),
+            other => Err(serde::de::Error::custom(format!(
+                "\"{}\" is not a boolean",
+                other
+            ))),
+        },
+    }
+}
+
+impl Dataset {
+    /// Parse a dataset made of multiple YAML documents
+    pub fn from_yaml(source: &str) -> Result<Self> {
+        let mut dataset = Dataset::default();
+        for doc in serde_yaml::Deserializer::from_str(source) {
+            match DatasetDoc::deserialize(doc)? {
+                DatasetDoc::Intent(intent) => dataset.intents.push(intent),
+                DatasetDoc::Entity(entity) => dataset.entities.push(entity),
+            }
+        }
+
+        Ok(dataset)
+    }
+
+    /// Transform every intent into what Lily uses, each one of them calls an
+    /// action with the same name
+    pub fn into_intents(self) -> Result<Vec<(String, IntentData)>> {
+        let entities: HashMap<String, EntityDef> = self
+            .entities
+            .into_iter()
+            .map(|e| (e.name.clone(), e.into()))
+            .collect();
+
+        self.intents
+            .into_iter()
+            .map(|intent| {
+                let data = intent.to_intent_data(&entities)?;
+                Ok((intent.name, data))
+            })
+            .collect()
+    }
+}
+
+impl DatasetIntent {
+    fn to_intent_data(&self, entities: &HashMap<String, EntityDef>) -> Result<IntentData> {
+        // "[slot](example)" and "[slot:entity](example)" are turned into "($slot)"
+        let re = Regex::new(r"\[([^\]:]+)(?::([^\]]*))?\]\([^)]*\)").expect("Error on regex");
+
+        let mut slot_entities: HashMap<&str, &str> = self
+            .slots
+            .iter()
+            .map(|s| (s.name.as_str(), s.entity.as_str()))
+            .collect();
+        for utt in &self.utterances {
+            for cap in re.captures_iter(utt) {
+                let name = cap.get(1).expect("Regex lacks group").as_str();
+                match cap.get(2) {
+                    // Slots can be declared right in the utterance
+                    Some(entity) => {
+                        slot_entities.insert(name, entity.as_str());
+                    }
+                    None if !slot_entities.contains_key(name) => {
+                        return Err(anyhow!(
+                            "Intent \"{}\" uses slot \"{}\" which is not declared",
+                            self.name,
+                            name
+                        ));
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let utts = self
+            .utterances
+            .iter()
+            .map(|u| re.replace_all(u, "($$$1)").to_string())
+            .collect();
+
+        let slots = slot_entities
+            .into_iter()
+            .map(|(name, entity)| {
+                // Entities not in the dataset are builtin ones (e.g: snips/datetime)
+                let slot_type = match entities.get(entity) {
+                    Some(def) => OrderKind::Def(def.clone()),
+                    None => OrderKind::Ref(entity.to_string()),
+                };
+
+                let data = SlotData {
+                    slot_type,
+                    required: false,
+                    prompt: None,
+                    reprompt: None,
+                };
+                (name.to_string(), data)
+            })
+            .collect();
+
+        Ok(IntentData {
+            utts,
+            slots,
+            hook: Hook::Action(self.name.clone()),
+        })
+    }
+}
+
+impl From<DatasetEntity> for EntityDef {
+    fn from(entity: DatasetEntity) -> Self {
+        let data = entity
+            .values
+            .into_iter()
+            .filter_map(|v| match v {
+                EntityValue::Single(value) => Some(EntityData {
+                    value,
+                    synonyms: vec![],
+                }),
+                EntityValue::WithSynonyms(mut values) => {
+                    if values.is_empty() {
+                        None
+                    } else {
+                        let value = values.remove(0);
+                        Some(EntityData {
+                            value,
+                            synonyms: values,
+                        })
+                    }
+                }
+            })
+            .collect();
+
+        EntityDef::new(data, entity.automatically_extensible)
+    }
+}
@@ -0,0 +1,1089 @@
+mod audio_server;
+mod dataset;
+
+pub use self::audio_server::AUDIO_SERVER_SITES;
+
+// Standard library
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// This crate
+use crate::actions::{Action, ActionAnswer, ActionContext, SessionData, ACT_REG};
+use crate::exts::LockIt;
+use crate::nlu::{IntentData, NluResponse, NluResponseSlot, SlotValue};
+use crate::signals::{
+    dev_mgmt::{new_session_id, SessionManager},
+    order::mqtt::MSG_OUTPUT,
+    SIG_REG,
+};
+use crate::skills::hermes::dataset::Dataset;
+use crate::skills::hermes::messages::{IntentMessage, RegisterIntentsMessage};
+use crate::skills::translations::find_lang_dirs;
+use crate::skills::{register_skill, SkillLoader};
+use crate::vars::{
+    demangle, DEFAULT_HERMES_ACTION_TIMEOUT, HERMES_DATASET_FILE, MIN_SCORE_FOR_ACTION, SKILLS_PATH,
+};
+
+// Other crates
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use lily_common::audio::AudioRaw;
+use lily_common::communication::{MsgRequest, RequestData};
+use log::{debug, info, warn};
+use maplit::hashmap;
+use rumqttc::{AsyncClient, QoS};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use unic_langid::LanguageIdentifier;
+
+lazy_static! {
+    static ref HERMES_API_OUTPUT: Arc<Mutex<Option<HermesApiOutput>>> = Arc::new(Mutex::new(None));
+    static ref HERMES_API_INPUT: Arc<Mutex<Option<HermesApiInput>>> = Arc::new(Mutex::new(None));
+}
+
+// All Hermes intents are put together in a skill with this name
+const HERMES_SKILL: &str = "hermes";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HermesConf {
+    // Time (in milliseconds) that a Hermes action has to answer
+    #[serde(default = "def_action_timeout")]
+    pub action_timeout: u64,
+}
+
+fn def_action_timeout() -> u64 {
+    DEFAULT_HERMES_ACTION_TIMEOUT
+}
+
+impl Default for HermesConf {
+    fn default() -> Self {
+        Self {
+            action_timeout: DEFAULT_HERMES_ACTION_TIMEOUT,
+        }
+    }
+}
+
+mod messages {
+    use super::dataset::Dataset;
+    use crate::nlu::{TimeGrain, TimePrecision};
+    use serde::{Deserialize, Serialize};
+
+    /// Sent by Hermes skills to tell Lily about their intents
+    #[derive(Deserialize)]
+    pub struct RegisterIntentsMessage {
+        #[serde(default)]
+        pub lang: Option<String>,
+
+        #[serde(flatten)]
+        pub dataset: Dataset,
+    }
+
+    #[derive(Deserialize)]
+    pub struct SayMessage {
+        pub text: String,
+
+        #[serde(default)]
+        pub lang: Option<String>,
+
+        #[serde(default)]
+        pub id: Option<String>,
+
+        #[serde(default)]
+        pub volume: Option<f32>,
+
+        #[serde(default = "default_site", rename = "siteId")]
+        pub site_id: String,
+
+        #[serde(default, rename = "sessionId")]
+        pub session_id: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct HotwordDetectedMessage {
+        #[serde(default = "default_site", rename = "siteId")]
+        pub site_id: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct PlayFinishedMessage {
+        pub id: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct NluQueryMessage {
+        pub input: String,
+
+        #[serde(default, rename = "intentFilter")]
+        pub intent_filter: Option<Vec<String>>,
+
+        #[serde(default)]
+        pub id: Option<String>,
+
+        #[serde(default = "default_site", rename = "siteId")]
+        pub site_id: String,
+
+        #[serde(default, rename = "sessionId")]
+        pub session_id: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    pub struct IntentNotRecognizedMessage {
+        pub input: String,
+
+        pub id: Option<String>,
+
+        #[serde(rename = "siteId")]
+        pub site_id: String,
+
+        #[serde(rename = "sessionId")]
+        pub session_id: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct StartSessionMessage {
+        pub init: SessionInit,
+
+        #[serde(default = "default_site", rename = "siteId")]
+        pub site_id: String,
+
+        #[serde(default, rename = "customData")]
+        pub custom_data: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    pub enum SessionInit {
+        // Expects the user to say something
+        Action {
+            #[serde(default)]
+            text: Option<String>,
+
+            #[serde(default, rename = "intentFilter")]
+            intent_filter: Option<Vec<String>>,
+
+            #[serde(default, rename = "canBeEnqueued")]
+            can_be_enqueued: bool,
+
+            #[serde(default, rename = "sendIntentNotRecognized")]
+            send_intent_not_recognized: bool,
+        },
+
+        // Just says something, the session ends right after
+        Notification {
+            text: String,
+        },
+    }
+
+    #[derive(Serialize)]
+    pub struct SessionStartedMessage {
+        #[serde(rename = "sessionId")]
+        pub session_id: String,
+
+        #[serde(rename = "siteId")]
+        pub site_id: String,
+
+        #[serde(rename = "customData")]
+        pub custom_data: Option<String>,
+
+        #[serde(rename = "reactivatedFromSessionId")]
+        pub reactivated_from_session_id: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    pub struct SessionEndedMessage {
+        #[serde(rename = "sessionId")]
+        pub session_id: String,
+
+        #[serde(rename = "siteId")]
+        pub site_id: String,
+
+        #[serde(rename = "customData")]
+        pub custom_data: Option<String>,
+
+        pub termination: SessionTermination,
+    }
+
+    #[derive(Serialize)]
+    pub struct SessionTermination {
+        pub reason: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct EndSessionMessage {
+        #[serde(rename = "sessionId")]
+        pub session_id: String,
+
+        #[serde(default)]
+        pub text: Option<String>,
+
+        #[serde(default, rename = "customData")]
+        pub custom_data: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct ContinueSessionMessage {
+        #[serde(rename = "sessionId")]
+        pub session_id: String,
+
+        pub text: String,
+
+        #[serde(default, rename = "intentFilter")]
+        pub intent_filter: Option<Vec<String>>,
+
+        #[serde(default, rename = "customData")]
+        pub custom_data: Option<String>,
+
+        #[serde(default, rename = "sendIntentNotRecognized")]
+        pub send_intent_not_recognized: bool,
+    }
+
+    #[derive(Serialize)]
+    pub struct IntentMessage {
+        pub input: String,
+        pub intent: ObjectIntentMessage,
+
+        #[serde(default)]
+        pub id: Option<String>,
+
+        #[serde(default = "default_site", rename = "siteId")]
+        pub site_id: String,
+
+        #[serde(default, rename = "sessionId")]
+        pub session_id: Option<String>,
+
+        #[serde(default, rename = "customData")]
+        pub custom_data: Option<String>,
+
+        #[serde(default, rename = "asrTokens")]
+        pub asr_tokens: Vec<AsrTokenIntentMessage>,
+
+        #[serde(default, rename = "asrConfidence")]
+        pub asr_confidence: Option<f32>,
+    }
+
+    #[derive(Serialize)]
+    pub struct ObjectIntentMessage {
+        #[serde(rename = "intentName")]
+        pub intent_name: String,
+        #[serde(rename = "confidenceScore")]
+        pub confidence_score: f32,
+
+        #[serde(default)]
+        pub slots: Vec<SlotIntentMessage>,
+    }
+
+    #[derive(Serialize)]
+    pub struct SlotIntentMessage {
+        pub entity: String,
+        #[serde(rename = "slotName")]
+        pub slot_name: String,
+        #[serde(rename = "rawValue")]
+        pub raw_value: String,
+        pub value: ValueSlotIntentMessage,
+
+        #[serde(default)]
+        pub range: Option<RangeSlotIntentMessage>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(tag = "kind")]
+    pub enum ValueSlotIntentMessage {
+        Custom {
+            value: String,
+        },
+        Number {
+            value: f64,
+        },
+        Percentage {
+            value: f64,
+        },
+        InstantTime {
+            value: String,
+            grain: TimeGrain,
+            precision: TimePrecision,
+        },
+        TimeInterval {
+            from: Option<String>,
+            to: Option<String>,
+        },
+        Duration {
+            years: i64,
+            quarters: i64,
+            months: i64,
+            weeks: i64,
+            days: i64,
+            hours: i64,
+            minutes: i64,
+            seconds: i64,
+            precision: TimePrecision,
+        },
+    }
+
+    #[derive(Serialize)]
+    pub struct RangeSlotIntentMessage {
+        pub start: i32,
+        pub end: i32,
+    }
+
+    #[derive(Serialize)]
+    pub struct AsrTokenIntentMessage {
+        value: String,
+        confidence: f32,
+        range_start: i32,
+        range_end: i32,
+    }
+
+    fn default_site() -> String {
+        "default".into()
+    }
+}
+
+/// Hermes skills declare their intents either with a Snips-style dataset in
+/// the skills folder ("hermes/<lang>/dataset.yaml") or by sending it through
+/// MQTT ("lily/hermes/register_intents"). Every intent gets an action which
+/// publishes it in "hermes/intent/<name>".
+pub struct HermesLoader {}
+
+impl HermesLoader {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn load_dataset(path: &Path) -> Result<Vec<(String, IntentData)>> {
+        Dataset::from_yaml(&fs::read_to_string(path)?)?.into_intents()
+    }
+}
+
+#[async_trait(?Send)]
+impl SkillLoader for HermesLoader {
+    fn load_skills(&mut self, langs: &[LanguageIdentifier]) -> Result<()> {
+        let hermes_path = SKILLS_PATH.resolve().join(HERMES_SKILL);
+        if !hermes_path.is_dir() {
+            return Ok(());
+        }
+
+        let lang_dirs = find_lang_dirs(&hermes_path, langs)?;
+        let mut intents: HashMap<String, Vec<(&LanguageIdentifier, IntentData)>> = HashMap::new();
+        for lang in langs {
+            let dataset_path = match lang_dirs.get(lang) {
+                Some(dir) => dir.join(HERMES_DATASET_FILE),
+                None => continue,
+            };
+
+            match Self::load_dataset(&dataset_path) {
+                Ok(lang_intents) => {
+                    for (intent_name, data) in lang_intents {
+                        intents.entry(intent_name).or_default().push((lang, data));
+                    }
+                }
+                Err(e) => warn!(
+                    "Hermes dataset {:?} couldn't be loaded: {}",
+                    dataset_path, e
+                ),
+            }
+        }
+
+        let sig_order = SIG_REG
+            .lock_it()
+            .get_sig_order()
+            .expect("Order signal was not initialized")
+            .clone();
+
+        for (intent_name, sig_arg) in intents {
+            let action = HermesAction::new_shared(&intent_name);
+            ACT_REG
+                .lock_it()
+                .insert(HERMES_SKILL, &intent_name, action.clone())?;
+            sig_order
+                .lock_it()
+                .add_intent_action(sig_arg, HERMES_SKILL, &intent_name, &action)?;
+            info!("Registered Hermes intent \"{}\"", intent_name);
+        }
+
+        Ok(())
+    }
+
+    async fn run_loader(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub use self::messages::NluQueryMessage;
+
+pub struct HermesApiIn {
+    def_lang: LanguageIdentifier,
+    nlu_queries: mpsc::Sender<NluQueryMessage>,
+}
+
+impl HermesApiIn {
+    pub fn new(
+        def_lang: LanguageIdentifier,
+        conf: &HermesConf,
+        nlu_queries: mpsc::Sender<NluQueryMessage>,
+    ) -> Self {
+        let timeout = Duration::from_millis(conf.action_timeout);
+        HERMES_API_INPUT
+            .lock_it()
+            .replace(HermesApiInput::new(timeout));
+
+        Self {
+            def_lang,
+            nlu_queries,
+        }
+    }
+
+    pub async fn subscribe(client: &Arc<Mutex<AsyncClient>>) -> Result<()> {
+        let client_raw = client.lock_it();
+        client_raw
+            .subscribe("hermes/tts/say", QoS::AtLeastOnce)
+            .await?;
+        client_raw
+            .subscribe("lily/hermes/register_intents", QoS::AtLeastOnce)
+            .await?;
+        client_raw
+            .subscribe("hermes/nlu/query", QoS::AtLeastOnce)
+            .await?;
+        client_raw
+            .subscribe("hermes/hotword/+/detected", QoS::AtLeastOnce)
+            .await?;
+        client_raw
+            .subscribe("hermes/audioServer/+/audioFrame", QoS::AtMostOnce)
+            .await?;
+        client_raw
+            .subscribe("hermes/audioServer/+/playFinished", QoS::AtLeastOnce)
+            .await?;
+        client_raw
+            .subscribe("hermes/dialogueManager/startSession", QoS::AtLeastOnce)
+            .await?;
+        client_raw
+            .subscribe("hermes/dialogueManager/endSession", QoS::AtLeastOnce)
+            .await?;
+        client_raw
+            .subscribe("hermes/dialogueManager/continueSession", QoS::AtLeastOnce)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn handle_tts_say(&self, payload: &Bytes) -> Result<()> {
+        if let Ok(Some(msg)) = HERMES_API_INPUT
+            .lock_it()
+            .as_mut()
+            .expect("No Hermes API input")
+            .intercept_tts_say(payload)
+        {
+            MSG_OUTPUT.with::<_, Result<()>>(|m| match *m.borrow_mut() {
+                Some(ref mut output) => {
+                    let l = msg
+                        .lang
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or_else(|| self.def_lang.clone());
+                    output.answer(msg.text, &l, msg.site_id)
+                }
+                _ => Err(anyhow!("No output channel")),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn handle_hotword_detected(&self, payload: &Bytes) -> Result<()> {
+        let msg: messages::HotwordDetectedMessage =
+            serde_json::from_reader(std::io::Cursor::new(payload))?;
+        AUDIO_SERVER_SITES.with(|s| s.borrow_mut().start_listening(&msg.site_id));
+        Ok(())
+    }
+
+    /// Handles "hermes/audioServer/<site>/..." topics
+    pub async fn handle_audio_server(
+        &self,
+        topic: &str,
+        payload: &Bytes,
+        channel_nlu: &mpsc::Sender<MsgRequest>,
+    ) -> Result<()> {
+        let (site_id, kind) = topic
+            .strip_prefix("hermes/audioServer/")
+            .and_then(|t| t.split_once('/'))
+            .ok_or_else(|| anyhow!("Malformed audio server topic: {}", topic))?;
+
+        match kind {
+            "audioFrame" => {
+                let frame = AudioRaw::from_wav(payload)?;
+                let to_send =
+                    AUDIO_SERVER_SITES.with(|s| s.borrow_mut().process_frame(site_id, frame));
+                if let Some((data, is_final)) = to_send {
+                    let msg = MsgRequest {
+                        data: RequestData::RawAudio { data, is_final },
+                        satellite: site_id.to_string(),
+                    };
+                    channel_nlu.send(msg).await?;
+                }
+            }
+            "playFinished" => {
+                let msg: messages::PlayFinishedMessage =
+                    serde_json::from_reader(std::io::Cursor::new(payload))?;
+                AUDIO_SERVER_SITES.with(|s| s.borrow_mut().play_finished(site_id, &msg.id));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub async fn handle_nlu_query(&self, payload: &Bytes) -> Result<()> {
+        let msg: NluQueryMessage = serde_json::from_reader(std::io::Cursor::new(payload))?;
+        Ok(self.nlu_queries.send(msg).await?)
+    }
+
+    pub async fn handle_start_session(
+        &self,
+        payload: &Bytes,
+        sessions: &Arc<Mutex<SessionManager>>,
+    ) -> Result<()> {
+        let msg: messages::StartSessionMessage =
+            serde_json::from_reader(std::io::Cursor::new(payload))?;
+        let (text, intent_filter, should_end) = match msg.init {
+            messages::SessionInit::Action {
+                text,
+                intent_filter,
+                can_be_enqueued,
+                ..
+            } => {
+                if can_be_enqueued {
+                    debug!("Hermes sessions can't be enqueued, they are started right away");
+                }
+                (text, intent_filter, false)
+            }
+            messages::SessionInit::Notification { text } => (Some(text), None, true),
+        };
+
+        let session_id = sessions.lock_it().start_session(
+            msg.site_id.clone(),
+            intent_filter,
+            msg.custom_data,
+        )?;
+        debug!("Started session \"{}\" on {}", session_id, &msg.site_id);
+        self.say_in_session(text, should_end, msg.site_id)
+    }
+
+    pub async fn handle_end_session(
+        &self,
+        payload: &Bytes,
+        sessions: &Arc<Mutex<SessionManager>>,
+    ) -> Result<()> {
+        let msg: messages::EndSessionMessage =
+            serde_json::from_reader(std::io::Cursor::new(payload))?;
+        let session_id = msg.session_id.clone();
+        let res = HERMES_API_INPUT
+            .lock_it()
+            .as_mut()
+            .expect("No Hermes API input")
+            .deliver(Some(&session_id), None, HermesAnswer::EndSession(msg));
+
+        // No action is waiting, this session was started with startSession
+        if let Some(HermesAnswer::EndSession(msg)) = res {
+            match sessions.lock_it().find_by_id(&session_id) {
+                Some(site_id) => self.say_in_session(msg.text, true, site_id)?,
+                None => debug!("Asked to end unknown session \"{}\"", session_id),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn handle_continue_session(
+        &self,
+        payload: &Bytes,
+        sessions: &Arc<Mutex<SessionManager>>,
+    ) -> Result<()> {
+        let msg: messages::ContinueSessionMessage =
+            serde_json::from_reader(std::io::Cursor::new(payload))?;
+        let session_id = msg.session_id.clone();
+        let site_id = sessions
+            .lock_it()
+            .continue_session(&session_id, msg.intent_filter.clone());
+        let res = HERMES_API_INPUT
+            .lock_it()
+            .as_mut()
+            .expect("No Hermes API input")
+            .deliver(Some(&session_id), None, HermesAnswer::ContinueSession(msg));
+
+        // No action is waiting, this session was started with startSession
+        if let Some(HermesAnswer::ContinueSession(msg)) = res {
+            match site_id {
+                Some(site_id) => self.say_in_session(Some(msg.text), false, site_id)?,
+                None => debug!("Asked to continue unknown session \"{}\"", session_id),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Say something (if there's anything to say) and then either end the
+    // session or wait for the user to answer
+    fn say_in_session(
+        &self,
+        text: Option<String>,
+        should_end: bool,
+        site_id: String,
+    ) -> Result<()> {
+        MSG_OUTPUT.with::<_, Result<()>>(|m| match *m.borrow_mut() {
+            Some(ref mut output) => {
+                if let Some(text) = text.filter(|t| !t.is_empty()) {
+                    output.answer(text, &self.def_lang, site_id.clone())?;
+                }
+                output.session_status(should_end, site_id)
+            }
+            _ => Err(anyhow!("No output channel")),
+        })
+    }
+
+    pub async fn handle_register_intents(&self, payload: &Bytes) -> Result<()> {
+        let msg: RegisterIntentsMessage = serde_json::from_reader(std::io::Cursor::new(payload))?;
+        let lang: LanguageIdentifier = match msg.lang {
+            Some(l) => l.parse()?,
+            None => self.def_lang.clone(),
+        };
+
+        let mut actions = Vec::new();
+        for (intent_name, data) in msg.dataset.into_intents()? {
+            // Registering again just replaces the intent
+            let _ = ACT_REG.lock_it().remove(HERMES_SKILL, &intent_name);
+            let action: Arc<Mutex<dyn Action + Send>> = HermesAction::new_shared(&intent_name);
+            actions.push((intent_name, hashmap! {lang.clone() => data}, action));
+        }
+
+        info!("Registering {} Hermes intents", actions.len());
+        register_skill(HERMES_SKILL, actions, vec![], vec![], vec![])
+    }
+}
+
+/// What a Hermes action can answer with
+pub enum HermesAnswer {
+    Say(messages::SayMessage),
+    EndSession(messages::EndSessionMessage),
+    ContinueSession(messages::ContinueSessionMessage),
+}
+
+struct PendingAnswer {
+    site_id: String,
+    sender: oneshot::Sender<HermesAnswer>,
+}
+
+pub struct HermesApiInput {
+    // Actions waiting for an answer, by session id
+    pending: HashMap<String, PendingAnswer>,
+    timeout: Duration,
+}
+
+impl HermesApiInput {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            pending: HashMap::new(),
+            timeout,
+        }
+    }
+
+    /// Get a channel for the answer of a session and how much to wait for it
+    pub fn wait_answer(
+        &mut self,
+        session_id: &str,
+        site_id: &str,
+    ) -> (oneshot::Receiver<HermesAnswer>, Duration) {
+        // Forget about those which already gave up
+        self.pending.retain(|_, p| !p.sender.is_closed());
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending.insert(
+            session_id.to_string(),
+            PendingAnswer {
+                site_id: site_id.to_string(),
+                sender,
+            },
+        );
+
+        (receiver, self.timeout)
+    }
+
+    /// Give the answer to whoever was waiting for it (by session or, if the
+    /// message lacks one, by site). If no one was, the answer is given back.
+    pub fn deliver(
+        &mut self,
+        session_id: Option<&str>,
+        site_id: Option<&str>,
+        answer: HermesAnswer,
+    ) -> Option<HermesAnswer> {
+        let key = match (session_id, site_id) {
+            (Some(session_id), _) => Some(session_id.to_string()),
+            (None, Some(site_id)) => self
+                .pending
+                .iter()
+                .find(|(_, p)| p.site_id == site_id)
+                .map(|(k, _)| k.clone()),
+            (None, None) => None,
+        };
+
+        match key.and_then(|k| self.pending.remove(&k)) {
+            Some(pending) => pending.sender.send(answer).err(),
+            None => Some(answer),
+        }
+    }
+
+    pub fn intercept_tts_say(&mut self, msg: &Bytes) -> Result<Option<messages::SayMessage>> {
+        let msg: messages::SayMessage = serde_json::from_reader(std::io::Cursor::new(msg))?;
+        let session_id = msg.session_id.clone();
+        let site_id = msg.site_id.clone();
+        match self.deliver(
+            session_id.as_deref(),
+            Some(&site_id),
+            HermesAnswer::Say(msg),
+        ) {
+            Some(HermesAnswer::Say(msg)) => Ok(Some(msg)),
+            Some(_) => Err(anyhow!(
+                "Received a different answer than the one delivered"
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+pub struct HermesApiOut {
+    common_out: mpsc::Receiver<(String, String)>,
+}
+
+impl HermesApiOut {
+    pub fn new() -> Result<Self> {
+        let (sender, common_out) = mpsc::channel(100);
+        let output = HermesApiOutput::new(sender);
+        HERMES_API_OUTPUT.lock_it().replace(output);
+
+        Ok(Self { common_out })
+    }
+
+    pub async fn handle_out(&mut self, client: &Arc<Mutex<AsyncClient>>) -> Result<()> {
+        loop {
+            let (topic, payload) = self.common_out.recv().await.expect("Out channel broken");
+            client
+                .lock_it()
+                .publish(topic, QoS::AtMostOnce, false, payload)
+                .await?;
+        }
+    }
+}
+
+pub struct HermesApiOutput {
+    client: mpsc::Sender<(String, String)>,
+}
+
+impl HermesApiOutput {
+    pub fn new(client: mpsc::Sender<(String, String)>) -> Self {
+        Self { client }
+    }
+
+    pub fn send<M: Serialize>(&self, path: String, msg: &M) -> Result<()> {
+        let msg_str = serde_json::to_string(msg)?;
+        self.client.try_send((path, msg_str)).unwrap();
+        Ok(())
+    }
+}
+
+/// Answer a "hermes/nlu/query" with what the NLU understood out of it
+pub fn answer_nlu_query(query: NluQueryMessage, response: NluResponse) -> Result<()> {
+    let intent_name = response
+        .name
+        .as_deref()
+        .map(|name| demangle(name).to_string())
+        .filter(|name| {
+            response.confidence >= MIN_SCORE_FOR_ACTION
+                && query
+                    .intent_filter
+                    .as_ref()
+                    .map_or(true, |filter| filter.contains(name))
+        });
+
+    let intent_name = match intent_name {
+        Some(intent_name) => intent_name,
+        None => return nlu_query_not_recognized(query),
+    };
+
+    let msg = IntentMessage {
+        id: query.id,
+        input: query.input,
+        intent: messages::ObjectIntentMessage {
+            intent_name: intent_name.clone(),
+            confidence_score: response.confidence,
+            slots: response.slots.into_iter().map(slot_message).collect(),
+        },
+        site_id: query.site_id,
+        session_id: query.session_id,
+        custom_data: None,
+        asr_tokens: vec![],
+        asr_confidence: None,
+    };
+    let output = HERMES_API_OUTPUT.lock_it();
+    let output = output.as_ref().ok_or_else(|| anyhow!("No Hermes output"))?;
+    output.send(format!("hermes/intent/{}", intent_name), &msg)
+}
+
+/// Answer a "hermes/nlu/query" which couldn't be understood
+pub fn nlu_query_not_recognized(query: NluQueryMessage) -> Result<()> {
+    let msg = messages::IntentNotRecognizedMessage {
+        input: query.input,
+        id: query.id,
+        site_id: query.site_id,
+        session_id: query.session_id,
+    };
+    let output = HERMES_API_OUTPUT.lock_it();
+    let output = output.as_ref().ok_or_else(|| anyhow!("No Hermes output"))?;
+    output.send("hermes/nlu/intentNotRecognized".into(), &msg)
+}
+
+fn slot_message(slot: NluResponseSlot) -> messages::SlotIntentMessage {
+    let NluResponseSlot {
+        raw_value,
+        value,
+        name,
+        entity,
+        range,
+    } = slot;
+
+    messages::SlotIntentMessage {
+        // Entities of skills are mangled, builtin ones (e.g: snips/number) not
+        entity: entity
+            .map(|e| demangle(&e).to_string())
+            .unwrap_or_else(|| name.clone()),
+        slot_name: name,
+        raw_value,
+        value: (&value).into(),
+        range: range.map(|r| messages::RangeSlotIntentMessage {
+            start: r.start as i32,
+            end: r.end as i32,
+        }),
+    }
+}
+
+// Hermes has no plain text, anything unresolved is sent as a custom value
+impl From<&SlotValue> for messages::ValueSlotIntentMessage {
+    fn from(value: &SlotValue) -> Self {
+        use messages::ValueSlotIntentMessage as Msg;
+
+        match value {
+            SlotValue::Text(value) | SlotValue::Entity { value, .. } => Msg::Custom {
+                value: value.clone(),
+            },
+            SlotValue::Number(value) => Msg::Number { value: *value },
+            SlotValue::Percentage(value) => Msg::Percentage { value: *value },
+            SlotValue::DateTime {
+                value,
+                grain,
+                precision,
+            } => Msg::InstantTime {
+                value: SlotValue::format_date(value),
+                grain: *grain,
+                precision: *precision,
+            },
+            SlotValue::Interval { from, to } => Msg::TimeInterval {
+                from: from.as_ref().map(SlotValue::format_date),
+                to: to.as_ref().map(SlotValue::format_date),
+            },
+            SlotValue::Duration(d) => Msg::Duration {
+                years: d.years,
+                quarters: d.quarters,
+                months: d.months,
+                weeks: d.weeks,
+                days: d.days,
+                hours: d.hours,
+                minutes: d.minutes,
+                seconds: d.seconds,
+                precision: d.precision,
+            },
+        }
+    }
+}
+
+/// Tell Hermes skills that a session has started
+pub fn notify_session_started(session: &SessionData, site_id: &str) {
+    let msg = messages::SessionStartedMessage {
+        session_id: session.id.clone(),
+        site_id: site_id.to_string(),
+        custom_data: session.custom_data.clone(),
+        reactivated_from_session_id: None,
+    };
+    send_notification("hermes/dialogueManager/sessionStarted", &msg);
+}
+
+/// Tell Hermes skills that a session has ended
+pub fn notify_session_ended(session: &SessionData, site_id: &str) {
+    let msg = messages::SessionEndedMessage {
+        session_id: session.id.clone(),
+        site_id: site_id.to_string(),
+        custom_data: session.custom_data.clone(),
+        termination: messages::SessionTermination {
+            reason: "nominal".into(),
+        },
+    };
+    send_notification("hermes/dialogueManager/sessionEnded", &msg);
+}
+
+fn send_notification<M: Serialize>(topic: &str, msg: &M) {
+    // Before the MQTT loop is up there's no one to notify
+    if let Some(output) = HERMES_API_OUTPUT.lock_it().as_ref() {
+        if let Err(e) = output.send(topic.to_string(), msg) {
+            warn!("Failed to publish {}: {}", topic, e);
+        }
+    }
+}
+
+pub struct HermesAction {
+    name: Arc<String>,
+    intent_name: Arc<String>,
+}
+
+impl HermesAction {
+    pub fn new(name: Arc<String>, intent_name: Arc<String>) -> Self {
+        Self { name, intent_name }
+    }
+
+    fn new_shared(intent_name: &str) -> Arc<Mutex<dyn Action + Send>> {
+        let name = Arc::new(intent_name.to_string());
+        Arc::new(Mutex::new(Self::new(name.clone(), name)))
+    }
+}
+
+#[async_trait(?Send)]
+impl Action for HermesAction {
+    async fn call(&mut self, context: &ActionContext) -> Result<ActionAnswer> {
+        const ERR: &str = "DynamicDict lacks mandatory element";
+
+        let intent_name = (*self.intent_name).clone();
+        let intent_data = context.data.as_intent().expect(ERR);
+        let satellite = context.satellite.as_ref().expect(ERR);
+
+        // Text requests have no session, but Hermes needs one anyway
+        let (session_id, custom_data) = match &satellite.session {
+            Some(session) => (session.id.clone(), session.custom_data.clone()),
+            None => (new_session_id(), None),
+        };
+        let msg = IntentMessage {
+            id: None,
+            input: intent_data.input.clone(),
+            intent: messages::ObjectIntentMessage {
+                intent_name: intent_name.clone(),
+                confidence_score: 1.0,
+                slots: intent_data
+                    .slots
+                    .iter()
+                    .map(|(n, v)| {
+                        messages::SlotIntentMessage {
+                            raw_value: v.raw(),
+                            value: v.into(),
+                            entity: n.to_string(),
+                            slot_name: n.clone(),
+                            range: None, // TODO: Actually get to pass this information
+                        }
+                    })
+                    .collect(),
+            },
+            site_id: satellite.uuid.clone(),
+            session_id: Some(session_id.clone()),
+            custom_data,
+            asr_tokens: vec![],
+            asr_confidence: None,
+        };
+        let (receiver, timeout) = HERMES_API_INPUT
+            .lock_it()
+            .as_mut()
+            .expect("No Hermes API input")
+            .wait_answer(&session_id, &msg.site_id);
+        HERMES_API_OUTPUT
+            .lock_it()
+            .as_ref()
+            .unwrap()
+            .send(format!("hermes/intent/{}", intent_name), &msg)?;
+
+        let answer = tokio::time::timeout(timeout, receiver)
+            .await
+            .map_err(|_| anyhow!("Hermes action \"{}\" didn't answer in time", intent_name))?
+            .map_err(|_| anyhow!("Hermes answer channel dropped"))?;
+
+        match answer {
+            HermesAnswer::Say(msg) => ActionAnswer::send_text(msg.text, true),
+            HermesAnswer::EndSession(msg) => match msg.text.filter(|t| !t.is_empty()) {
+                Some(text) => ActionAnswer::send_text(text, true),
+                None => ActionAnswer::nothing(true),
+            },
+            HermesAnswer::ContinueSession(msg) => ActionAnswer::send_text(msg.text, false),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        self.name.as_ref().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn end_session(session_id: &str) -> HermesAnswer {
+        HermesAnswer::EndSession(messages::EndSessionMessage {
+            session_id: session_id.to_string(),
+            text: None,
+            custom_data: None,
+        })
+    }
+
+    #[test]
+    fn answers_by_session_or_site() {
+        let mut input = HermesApiInput::new(Duration::from_millis(100));
+        let (mut kitchen, _) = input.wait_answer("session-1", "kitchen");
+        let (mut bedroom, _) = input.wait_answer("session-2", "bedroom");
+
+        assert!(input
+            .deliver(Some("session-2"), None, end_session("session-2"))
+            .is_none());
+        match bedroom.try_recv() {
+            Ok(HermesAnswer::EndSession(msg)) => assert_eq!(msg.session_id, "session-2"),
+            _ => panic!("The bedroom's session didn't get its answer"),
+        }
+
+        assert!(input
+            .deliver(None, Some("kitchen"), end_session("session-1"))
+            .is_none());
+        assert!(kitchen.try_recv().is_ok());
+
+        // No one is waiting anymore
+        assert!(input
+            .deliver(Some("session-1"), None, end_session("session-1"))
+            .is_some());
+        assert!(input
+            .deliver(None, Some("kitchen"), end_session("session-1"))
+            .is_some());
+    }
+
+    #[test]
+    fn intercepted_tts() {
+        let mut input = HermesApiInput::new(Duration::from_millis(100));
+        let (mut receiver, _) = input.wait_answer("session-1", "kitchen");
+
+        let unrelated = Bytes::from_static(br#"{"text": "Hello", "siteId": "bedroom"}"#);
+        match input.intercept_tts_say(&unrelated).unwrap() {
+            Some(msg) => assert_eq!(msg.text, "Hello"),
+            None => panic!("A say from another site was intercepted"),
+        }
+
+        let answer = Bytes::from_static(br#"{"text": "It's 5 o'clock", "sessionId": "session-1"}"#);
+        assert!(input.intercept_tts_say(&answer).unwrap().is_none());
+        match receiver.try_recv() {
+            Ok(HermesAnswer::Say(msg)) => assert_eq!(msg.text, "It's 5 o'clock"),
+            _ => panic!("The session didn't get its answer"),
+        }
+
+        assert!(input.intercept_tts_say(&Bytes::from_static(b"{}")).is_err());
+    }
+}
@@ -0,0 +1,516 @@
+// Standard library
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// This crate
+use crate::actions::{ActionSet, ACT_REG};
+use crate::exts::LockIt;
+use crate::nlu::{EntityData, EntityDef, IntentData, OrderKind, SlotData};
+use crate::queries::{BuiltinQuery, QueryDef, QUERY_REG};
+use crate::signals::{collections::Hook, SignalOrder, POLL_SIGNAL, SIG_REG};
+use crate::skills::translations::{register_translations, SkillTranslations};
+use crate::skills::SkillLoader;
+use crate::vars::{SKILLS_PATH, SKILL_MODEL_FILE, SKILL_TRANSLATIONS_DIR};
+
+// Other crates
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use fluent_bundle::{FluentArgs, FluentValue};
+use log::{error, info, warn};
+use serde::Deserialize;
+use unic_langid::LanguageIdentifier;
+
+/*** Model ********************************************************************/
+#[derive(Deserialize)]
+struct SkillModel {
+    #[serde(default)]
+    events: Vec<EventEntry>,
+
+    #[serde(default)]
+    signals: Vec<SignalEntry>,
+
+    #[serde(default)]
+    queries: HashMap<String, QueryDef>,
+
+    #[serde(flatten)]
+    intents: HashMap<String, IntentDef>,
+}
+
+// Events are a list of the event names and hooks to be called on them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EventEntry {
+    Hook(Hook),
+    Name(String),
+}
+
+// Signals set up as soon as the skill is loaded, e.g: a timer with a cron rule,
+// anything besides the signal and the action is data for the signal. The
+// "poll" signal also needs the query to be watched.
+#[derive(Deserialize)]
+struct SignalEntry {
+    signal: String,
+    action: String,
+
+    #[serde(default)]
+    query: Option<String>,
+
+    #[serde(flatten)]
+    data: HashMap<String, serde_yaml::Value>,
+}
+
+#[derive(Deserialize)]
+struct IntentDef {
+    samples: OneOrMany,
+
+    #[serde(default)]
+    slots: HashMap<String, SlotDef>,
+
+    #[serde(flatten)]
+    hook: Hook,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn as_slice(&self) -> &[String] {
+        match self {
+            OneOrMany::One(s) => std::slice::from_ref(s),
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SlotDef {
+    #[serde(rename = "type")]
+    slot_type: SlotTypeDef,
+
+    #[serde(default)]
+    required: bool,
+
+    #[serde(default)]
+    prompt: Option<String>,
+
+    #[serde(default)]
+    reprompt: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SlotTypeDef {
+    Ref(String),
+    Def {
+        data: Vec<String>,
+        #[serde(default)]
+        strict: bool,
+    },
+}
+
+/*** Loader *******************************************************************/
+
+/// Loads skills from the skills folder, each one of them being a folder with
+/// a "model.yaml" and a "translations" folder with Fluent files.
+pub struct LocalLoader {}
+
+impl LocalLoader {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn load_skill(
+        path: &Path,
+        langs: &[LanguageIdentifier],
+        sig_order: &Arc<Mutex<SignalOrder>>,
+    ) -> Result<()> {
+        let skill_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("Skill folder {:?} has not a valid name", path))?;
+
+        let trans = SkillTranslations::load(&path.join(SKILL_TRANSLATIONS_DIR), langs)?;
+        let model: SkillModel =
+            serde_yaml::from_reader(BufReader::new(File::open(path.join(SKILL_MODEL_FILE))?))?;
+
+        // Queries go first, intents and signals might use them
+        for (query_name, def) in model.queries {
+            if let Err(e) = register_query(skill_name, &query_name, def) {
+                warn!(
+                    "Query \"{}\" of skill \"{}\" won't be available: {}",
+                    query_name, skill_name, e
+                );
+            }
+        }
+
+        for (intent_name, intent) in model.intents {
+            if let Err(e) = register_intent(skill_name, &intent_name, intent, &trans, sig_order) {
+                warn!(
+                    "Intent \"{}\" of skill \"{}\" won't be available: {}",
+                    intent_name, skill_name, e
+                );
+            }
+        }
+
+        register_events(skill_name, model.events);
+        for entry in model.signals {
+            let sig_name = entry.signal.clone();
+            if let Err(e) = register_signal(skill_name, entry) {
+                warn!(
+                    "Signal \"{}\" of skill \"{}\" won't be set: {}",
+                    sig_name, skill_name, e
+                );
+            }
+        }
+        register_translations(skill_name, trans);
+
+        info!("Loaded skill \"{}\"", skill_name);
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl SkillLoader for LocalLoader {
+    fn load_skills(&mut self, langs: &[LanguageIdentifier]) -> Result<()> {
+        let skills_path = SKILLS_PATH.resolve();
+        if !skills_path.is_dir() {
+            warn!("Skills folder {:?} does not exist", skills_path);
+            return Ok(());
+        }
+
+        let sig_order = SIG_REG
+            .lock_it()
+            .get_sig_order()
+            .expect("Order signal was not initialized")
+            .clone();
+
+        for entry in fs::read_dir(&skills_path)? {
+            let path = entry?.path();
+            if path.join(SKILL_MODEL_FILE).is_file() {
+                if let Err(e) = Self::load_skill(&path, langs, &sig_order) {
+                    error!("Skill at {:?} couldn't be loaded: {}", path, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn run_loader(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Skills can use their own items as well as the ones provided by Lily
+fn find_item<T, F: Fn(&str) -> Option<T>>(get: F, skill_name: &str, name: &str) -> Result<T> {
+    get(skill_name).or_else(|| get("embedded")).ok_or_else(|| {
+        anyhow!(
+            "\"{}\" is neither in skill \"{}\" nor embedded",
+            name,
+            skill_name
+        )
+    })
+}
+
+fn register_intent(
+    skill_name: &str,
+    intent_name: &str,
+    intent: IntentDef,
+    trans: &SkillTranslations,
+    sig_order: &Arc<Mutex<SignalOrder>>,
+) -> Result<()> {
+    let mut sig_arg = Vec::new();
+    for lang in trans.langs() {
+        sig_arg.push((lang, make_intent_data(&intent, lang, trans)?));
+    }
+
+    match intent.hook {
+        Hook::Action(act_name) => {
+            let action = find_item(
+                |s| ACT_REG.lock_it().get(s, &act_name).cloned(),
+                skill_name,
+                &act_name,
+            )?;
+            sig_order
+                .lock_it()
+                .add_intent_action(sig_arg, skill_name, intent_name, &action)
+        }
+        Hook::Signal(sig_name) => {
+            let signal = find_item(
+                |s| SIG_REG.lock_it().get(s, &sig_name),
+                skill_name,
+                &sig_name,
+            )?;
+            sig_order.lock_it().add_intent_signal(
+                sig_arg,
+                skill_name,
+                intent_name,
+                format!("{}_signal_wrapper", intent_name),
+                signal,
+            )
+        }
+        Hook::Query(query_name) => {
+            let query = find_item(
+                |s| QUERY_REG.lock_it().get(s, &query_name).cloned(),
+                skill_name,
+                &query_name,
+            )?;
+            sig_order.lock_it().add_intent_query(
+                sig_arg,
+                skill_name,
+                intent_name,
+                format!("{}_query_wrapper", intent_name),
+                query,
+            )
+        }
+    }
+}
+
+fn make_intent_data(
+    intent: &IntentDef,
+    lang: &LanguageIdentifier,
+    trans: &SkillTranslations,
+) -> Result<IntentData> {
+    // Slots in samples are left as "$slot_name", that's what NLUs expect
+    let mut args = FluentArgs::new();
+    for slot_name in intent.slots.keys() {
+        args.set(
+            slot_name.clone(),
+            FluentValue::from(format!("${}", slot_name)),
+        );
+    }
+
+    let mut utts = Vec::new();
+    for sample in intent.samples.as_slice() {
+        utts.extend(trans.translate_all(lang, sample, Some(&args))?);
+    }
+
+    let mut slots = HashMap::new();
+    for (slot_name, slot) in &intent.slots {
+        slots.insert(slot_name.clone(), make_slot_data(slot, lang, trans)?);
+    }
+
+    Ok(IntentData {
+        utts,
+        slots,
+        hook: intent.hook.clone(),
+    })
+}
+
+fn make_slot_data(
+    slot: &SlotDef,
+    lang: &LanguageIdentifier,
+    trans: &SkillTranslations,
+) -> Result<SlotData> {
+    let slot_type = match &slot.slot_type {
+        SlotTypeDef::Ref(name) => OrderKind::Ref(name.clone()),
+        SlotTypeDef::Def { data, strict } => {
+            let mut entity_data = Vec::new();
+            for d in data {
+                // First variant is the value, any other is a synonym
+                let mut variants = trans.translate_all(lang, d, None)?.into_iter();
+                let value = variants
+                    .next()
+                    .ok_or_else(|| anyhow!("Entity value \"{}\" is empty", d))?;
+                entity_data.push(EntityData {
+                    value,
+                    synonyms: variants.collect(),
+                });
+            }
+            OrderKind::Def(EntityDef::new(entity_data, !strict))
+        }
+    };
+
+    let translate_opt = |text: &Option<String>| -> Result<Option<String>> {
+        text.as_ref()
+            .map(|t| trans.translate(lang, t, None))
+            .transpose()
+    };
+
+    Ok(SlotData {
+        slot_type,
+        required: slot.required,
+        prompt: translate_opt(&slot.prompt)?,
+        reprompt: translate_opt(&slot.reprompt)?,
+    })
+}
+
+// Events still work with whatever actions could be found
+fn register_events(skill_name: &str, events: Vec<EventEntry>) {
+    let mut act_set = ActionSet::empty();
+    let mut event_names = Vec::new();
+
+    for entry in events {
+        match entry {
+            EventEntry::Hook(Hook::Action(act_name)) => {
+                match find_item(
+                    |s| ACT_REG.lock_it().get(s, &act_name).cloned(),
+                    skill_name,
+                    &act_name,
+                ) {
+                    Ok(action) => act_set.add_action(Arc::downgrade(&action)),
+                    Err(e) => warn!(
+                        "Skill \"{}\": action won't be called on events: {}",
+                        skill_name, e
+                    ),
+                }
+            }
+            EventEntry::Hook(hook) => {
+                warn!(
+                    "Skill \"{}\": only actions can be called on events, {:?} will be ignored",
+                    skill_name, hook
+                );
+            }
+            EventEntry::Name(name) => event_names.push(name),
+        }
+    }
+
+    let sig_event = SIG_REG.lock_it().get_sig_event().clone();
+    let mut sig_event = sig_event.lock_it();
+    for name in event_names {
+        sig_event.add(&name, act_set.clone());
+    }
+}
+
+fn register_query(skill_name: &str, query_name: &str, def: QueryDef) -> Result<()> {
+    let query = BuiltinQuery::new(query_name.to_string(), def)?;
+    QUERY_REG
+        .lock_it()
+        .insert(skill_name, query_name, Arc::new(Mutex::new(query)))
+}
+
+fn register_signal(skill_name: &str, entry: SignalEntry) -> Result<()> {
+    let action = find_item(
+        |s| ACT_REG.lock_it().get(s, &entry.action).cloned(),
+        skill_name,
+        &entry.action,
+    )?;
+
+    let mut data = HashMap::new();
+    for (key, value) in entry.data {
+        let value = match value {
+            serde_yaml::Value::String(s) => s,
+            serde_yaml::Value::Number(n) => n.to_string(),
+            serde_yaml::Value::Bool(b) => b.to_string(),
+            other => return Err(anyhow!("\"{}\" has a non-text value: {:?}", key, other)),
+        };
+        data.insert(key, value);
+    }
+
+    let act_set = ActionSet::create(Arc::downgrade(&action));
+    if entry.signal == "poll" {
+        let query_name = entry
+            .query
+            .ok_or_else(|| anyhow!("Polling needs a \"query\""))?;
+        let query = find_item(
+            |s| QUERY_REG.lock_it().get(s, &query_name).cloned(),
+            skill_name,
+            &query_name,
+        )?;
+        let poll = POLL_SIGNAL
+            .lock_it()
+            .clone()
+            .ok_or_else(|| anyhow!("Poll signal was not initialized"))?;
+        poll.lock_it().add(query, act_set, data)?;
+    } else {
+        let sig_name = entry.signal;
+        let signal = find_item(
+            |s| SIG_REG.lock_it().get(s, &sig_name),
+            skill_name,
+            &sig_name,
+        )?;
+        signal.lock_it().add(data, skill_name, act_set)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::SayHelloAction;
+    use crate::nlu::{Nlu, NluBackend, NluConf};
+    use crate::signals::Signal;
+    use crate::vars::{mangle, SKILL_TRANSLATIONS_FILE};
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use tokio::time::{sleep, timeout};
+
+    const MODEL: &str = "
+greet:
+  samples: $greet_samples
+  action: say_hello
+
+# Neither of these exist, the skill loads anyway
+broken:
+  samples: it is broken
+  action: not_an_action
+
+events:
+  - action: not_an_action
+  - lily_start
+";
+
+    const TRANSLATIONS: &str = "
+greet_samples = hello there
+    .a_1 = good morning
+";
+
+    // The folder's name is the skill's
+    fn write_skill(skill_name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(skill_name);
+        let lang_path = path.join(SKILL_TRANSLATIONS_DIR).join("en-US");
+        fs::create_dir_all(&lang_path).unwrap();
+        fs::write(path.join(SKILL_MODEL_FILE), MODEL).unwrap();
+        fs::write(lang_path.join(SKILL_TRANSLATIONS_FILE), TRANSLATIONS).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn loaded_skill_is_understood() {
+        let skill_name = format!("lily_yaml_test_{}", std::process::id());
+        let path = write_skill(&skill_name);
+        let lang: LanguageIdentifier = "en-US".parse().unwrap();
+        let langs = vec![lang.clone()];
+        ACT_REG
+            .lock_it()
+            .insert(
+                &skill_name,
+                "say_hello",
+                Arc::new(Mutex::new(SayHelloAction::new())),
+            )
+            .unwrap();
+
+        let conf = NluConf {
+            backend: NluBackend::Builtin,
+            ..Default::default()
+        };
+        let order = Arc::new(Mutex::new(SignalOrder::new(langs.clone(), &conf)));
+        let res = LocalLoader::load_skill(&path, &langs, &order);
+        fs::remove_dir_all(&path).unwrap();
+        res.unwrap();
+
+        // Training is scheduled once loading ends
+        let nlu_map = order.lock_it().nlu_map();
+        order.lock_it().end_load(&langs).unwrap();
+        let nlu = timeout(Duration::from_secs(10), async {
+            loop {
+                if let Ok(nlu) = nlu_map.lock_it().get_nlu(&lang) {
+                    break nlu;
+                }
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("The NLU was never trained");
+
+        let res = nlu.lock().await.parse("good morning").await.unwrap();
+        assert_eq!(res.name, Some(mangle(&skill_name, "greet")));
+    }
+}
@@ -1,5 +1,7 @@
 mod embedded;
 pub mod hermes;
+mod local;
+pub mod translations;
 mod vap;
 
 // Standard library
@@ -7,18 +9,22 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 // This crate
-use self::{embedded::EmbeddedLoader, hermes::HermesLoader, vap::VapLoader};
+use self::{
+    embedded::EmbeddedLoader, hermes::HermesLoader, local::LocalLoader,
+    translations::unregister_translations, vap::VapLoader,
+};
 use crate::actions::{Action, ACT_REG};
 use crate::exts::LockIt;
 use crate::nlu::{EntityDef, IntentData};
-use crate::queries::{ActQuery, Query};
+use crate::queries::{ActQuery, Query, QUERY_REG};
 use crate::signals::order::dynamic_nlu;
-use crate::signals::{ActSignal, UserSignal, SIG_REG};
+use crate::signals::{remove_skill_signals, ActSignal, UserSignal, SIG_REG};
 use crate::vars::DEFAULT_COAP_PORT;
 
 // Other crates
 use anyhow::Result;
 use async_trait::async_trait;
+use log::info;
 use unic_langid::LanguageIdentifier;
 
 pub fn load_skills(curr_langs: &[LanguageIdentifier]) -> Result<Vec<Box<dyn SkillLoader>>> {
@@ -73,9 +79,7 @@ pub fn register_skill(
     let skill_name_str = skill_name.to_string();
 
     for (intent_name, utts, action) in actions {
-        let weak = Arc::downgrade(&action);
-        dynamic_nlu::add_intent(utts, skill_name_str.clone(), intent_name.clone())?;
-        dynamic_nlu::link_action_intent(intent_name, skill_name.to_string(), weak)?;
+        add_new_intent(intent_name, skill_name_str.clone(), utts, action)?;
     }
 
     for (name, utts, signal) in signals {
@@ -83,7 +87,11 @@ pub fn register_skill(
             name.clone(),
             skill_name_str.clone(),
             utts,
-            ActSignal::new(signal, format!("{}_signal_wrapper", name)),
+            ActSignal::new(
+                signal,
+                format!("{}_signal_wrapper", name),
+                skill_name_str.clone(),
+            ),
         )?;
     }
 
@@ -103,9 +111,28 @@ pub fn register_skill(
     Ok(())
 }
 
+/// Remove everything a skill registered: it's intents and entities from the
+/// NLU (which will be retrained), it's actions, queries, signals and
+/// translations.
+pub fn unregister_skill(skill_name: &str) -> Result<()> {
+    dynamic_nlu::remove_skill(skill_name.to_string())?;
+
+    let acts = ACT_REG.lock_it().remove_skill(skill_name);
+    let queries = QUERY_REG.lock_it().remove_skill(skill_name);
+    let signals = remove_skill_signals(skill_name);
+    unregister_translations(skill_name);
+    info!(
+        "Unregistered skill \"{}\" ({} actions, {} queries, {} signals)",
+        skill_name, acts, queries, signals
+    );
+
+    Ok(())
+}
+
 fn get_loaders(langs: Vec<LanguageIdentifier>) -> Vec<Box<dyn SkillLoader>> {
     vec![
         Box::new(EmbeddedLoader::new()),
+        Box::new(LocalLoader::new()),
         Box::new(HermesLoader::new()),
         Box::new(VapLoader::new(DEFAULT_COAP_PORT, langs)),
     ]
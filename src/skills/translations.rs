@@ -0,0 +1,313 @@
+// Standard library
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// This crate
+use crate::exts::LockIt;
+use crate::vars::SKILL_TRANSLATIONS_FILE;
+
+// Other crates
+use anyhow::{anyhow, Result};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentMessage, FluentResource};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use rand::seq::SliceRandom;
+use unic_langid::LanguageIdentifier;
+
+lazy_static! {
+    static ref TRANS_REG: Mutex<HashMap<String, Arc<SkillTranslations>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Make the translations of a skill available to it's actions
+pub fn register_translations(skill_name: &str, trans: SkillTranslations) {
+    TRANS_REG
+        .lock_it()
+        .insert(skill_name.to_string(), Arc::new(trans));
+}
+
+/// Forget the translations of a skill which is being unregistered
+pub fn unregister_translations(skill_name: &str) {
+    TRANS_REG.lock_it().remove(skill_name);
+}
+
+pub fn get_translations(skill_name: &str) -> Result<Arc<SkillTranslations>> {
+    TRANS_REG
+        .lock_it()
+        .get(skill_name)
+        .cloned()
+        .ok_or_else(|| anyhow!("Skill \"{}\" has no translations", skill_name))
+}
+
+/// Find, for each one of `langs`, the best folder inside of `path` (which is
+/// expected to have a folder per language e.g: "en-US").
+pub fn find_lang_dirs(
+    path: &Path,
+    langs: &[LanguageIdentifier],
+) -> Result<HashMap<LanguageIdentifier, PathBuf>> {
+    let available: HashMap<LanguageIdentifier, PathBuf> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let lang = path.file_name()?.to_str()?.parse().ok()?;
+            Some((lang, path))
+        })
+        .collect();
+    let available_langs: Vec<LanguageIdentifier> = available.keys().cloned().collect();
+
+    let mut res = HashMap::new();
+    for lang in langs {
+        let negotiated = negotiate_languages(
+            &[lang],
+            &available_langs,
+            None,
+            NegotiationStrategy::Filtering,
+        );
+
+        match negotiated.first() {
+            Some(found) => {
+                res.insert(lang.clone(), available[*found].clone());
+            }
+            None => {
+                warn!("No folder for language \"{}\" in {:?}", lang, path);
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// Translations of a skill, there's one Fluent bundle for each language that
+/// Lily is using and the skill can provide.
+pub struct SkillTranslations {
+    bundles: HashMap<LanguageIdentifier, FluentBundle<FluentResource>>,
+}
+
+impl SkillTranslations {
+    /// Load translations from `path`, which is expected to have a folder per
+    /// language (e.g: "en-US/translations.ftl").
+    pub fn load(path: &Path, langs: &[LanguageIdentifier]) -> Result<Self> {
+        let mut bundles = HashMap::new();
+        for (lang, lang_path) in find_lang_dirs(path, langs)? {
+            let ftl_path = lang_path.join(SKILL_TRANSLATIONS_FILE);
+            let bundle = Self::load_bundle(&ftl_path, &lang)?;
+            bundles.insert(lang, bundle);
+        }
+
+        Ok(Self { bundles })
+    }
+
+    fn load_bundle(
+        ftl_path: &Path,
+        lang: &LanguageIdentifier,
+    ) -> Result<FluentBundle<FluentResource>> {
+        let source = fs::read_to_string(ftl_path)?;
+        let res = FluentResource::try_new(source).unwrap_or_else(|(res, errs)| {
+            warn!(
+                "{:?} has some errors, some translations might be missing: {:?}",
+                ftl_path, errs
+            );
+            res
+        });
+
+        let mut bundle = FluentBundle::new_concurrent(vec![lang.clone()]);
+        // Unicode isolation marks would end up in the NLU and in the TTS
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(res)
+            .map_err(|errs| anyhow!("Failed to add {:?}: {:?}", ftl_path, errs))?;
+
+        Ok(bundle)
+    }
+
+    /// Languages for which this skill has translations
+    pub fn langs(&self) -> impl Iterator<Item = &LanguageIdentifier> {
+        self.bundles.keys()
+    }
+
+    // Languages given by actions might not be exactly one of ours
+    fn get_bundle(&self, lang: &LanguageIdentifier) -> Result<&FluentBundle<FluentResource>> {
+        if let Some(bundle) = self.bundles.get(lang) {
+            return Ok(bundle);
+        }
+
+        let available: Vec<LanguageIdentifier> = self.bundles.keys().cloned().collect();
+        negotiate_languages(&[lang], &available, None, NegotiationStrategy::Filtering)
+            .first()
+            .map(|l| &self.bundles[*l])
+            .ok_or_else(|| anyhow!("No translations for language \"{}\"", lang))
+    }
+
+    /// Get all the variants of a message: its value and every attribute of it.
+    pub fn get_all(
+        &self,
+        lang: &LanguageIdentifier,
+        id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Result<Vec<String>> {
+        let bundle = self.get_bundle(lang)?;
+        let msg = bundle
+            .get_message(id)
+            .ok_or_else(|| anyhow!("Translation \"{}\" not found for \"{}\"", id, lang))?;
+
+        Ok(Self::format_all(bundle, &msg, args))
+    }
+
+    fn format_all(
+        bundle: &FluentBundle<FluentResource>,
+        msg: &FluentMessage,
+        args: Option<&FluentArgs>,
+    ) -> Vec<String> {
+        let mut errs = Vec::new();
+        let mut res: Vec<String> = msg
+            .value()
+            .into_iter()
+            .chain(msg.attributes().map(|a| a.value()))
+            .map(|pattern| bundle.format_pattern(pattern, args, &mut errs).to_string())
+            .collect();
+
+        if !errs.is_empty() {
+            debug!("Formatting translation had errors: {:?}", errs);
+        }
+        res.retain(|s| !s.is_empty());
+        res
+    }
+
+    /// Get one of the variants of a message at random, so that answers feel
+    /// less robotic.
+    pub fn get_random(
+        &self,
+        lang: &LanguageIdentifier,
+        id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Result<String> {
+        self.get_all(lang, id, args)?
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .ok_or_else(|| anyhow!("Translation \"{}\" is empty", id))
+    }
+
+    /// Texts starting with '$' are references to a translation, those are
+    /// resolved to all it's variants, anything else is returned as is.
+    pub fn translate_all(
+        &self,
+        lang: &LanguageIdentifier,
+        text: &str,
+        args: Option<&FluentArgs>,
+    ) -> Result<Vec<String>> {
+        match text.strip_prefix('$') {
+            Some(id) => self.get_all(lang, id, args),
+            None => Ok(vec![text.to_string()]),
+        }
+    }
+
+    /// Like `translate_all` but only gives back the main variant
+    pub fn translate(
+        &self,
+        lang: &LanguageIdentifier,
+        text: &str,
+        args: Option<&FluentArgs>,
+    ) -> Result<String> {
+        self.translate_all(lang, text, args)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Translation \"{}\" is empty", text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EN: &str = "
+greeting = Hello {$name}
+    .a_1 = Hi {$name}
+    .a_2 = Hey there
+
+empty =
+    .a_1 = {\"\"}
+";
+
+    const ES: &str = "greeting = Hola {$name}\n";
+
+    fn load() -> SkillTranslations {
+        let path = std::env::temp_dir().join(format!("lily_trans_test_{}", std::process::id()));
+        for (dir, source) in &[("en-US", EN), ("es", ES)] {
+            fs::create_dir_all(path.join(dir)).unwrap();
+            fs::write(path.join(dir).join(SKILL_TRANSLATIONS_FILE), source).unwrap();
+        }
+        let langs = ["en-US".parse().unwrap(), "es-ES".parse().unwrap()];
+        let trans = SkillTranslations::load(&path, &langs);
+        fs::remove_dir_all(&path).unwrap();
+        trans.unwrap()
+    }
+
+    fn name_args() -> FluentArgs<'static> {
+        let mut args = FluentArgs::new();
+        args.set("name", "Ana");
+        args
+    }
+
+    #[test]
+    fn every_variant() {
+        let trans = load();
+        let en = "en-US".parse().unwrap();
+        assert_eq!(
+            trans.get_all(&en, "greeting", Some(&name_args())).unwrap(),
+            vec!["Hello Ana", "Hi Ana", "Hey there"]
+        );
+        let random = trans
+            .get_random(&en, "greeting", Some(&name_args()))
+            .unwrap();
+        assert!(["Hello Ana", "Hi Ana", "Hey there"].contains(&random.as_str()));
+
+        assert!(trans.get_all(&en, "missing", None).is_err());
+        assert!(trans.get_random(&en, "empty", None).is_err());
+    }
+
+    #[test]
+    fn references_and_text() {
+        let trans = load();
+        let en = "en-US".parse().unwrap();
+        assert_eq!(
+            trans
+                .translate_all(&en, "$greeting", Some(&name_args()))
+                .unwrap(),
+            vec!["Hello Ana", "Hi Ana", "Hey there"]
+        );
+        assert_eq!(
+            trans
+                .translate(&en, "$greeting", Some(&name_args()))
+                .unwrap(),
+            "Hello Ana"
+        );
+        assert_eq!(
+            trans.translate_all(&en, "just text", None).unwrap(),
+            vec!["just text"]
+        );
+    }
+
+    #[test]
+    fn close_languages() {
+        let trans = load();
+        let mut langs: Vec<String> = trans.langs().map(|l| l.to_string()).collect();
+        langs.sort();
+        assert_eq!(langs, vec!["en-US", "es-ES"]);
+
+        let es_mx = "es-MX".parse().unwrap();
+        assert_eq!(
+            trans
+                .translate(&es_mx, "$greeting", Some(&name_args()))
+                .unwrap(),
+            "Hola Ana"
+        );
+        assert!(trans
+            .get_all(&"fr-FR".parse().unwrap(), "greeting", None)
+            .is_err());
+    }
+}
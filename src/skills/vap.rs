@@ -8,11 +8,12 @@ use crate::actions::{Action, ActionAnswer, ActionContext};
 use crate::exts::LockIt;
 use crate::nlu::{EntityData, EntityDef, IntentData, OrderKind, SlotData};
 use crate::signals::collections::Hook;
-use crate::skills::{register_skill, SkillLoader};
+use crate::skills::{register_skill, unregister_skill, SkillLoader};
 
 // Other crates
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use log::error;
 use maplit::hashmap;
 use rmp_serde::to_vec_named;
 use unic_langid::{subtags, LanguageIdentifier};
@@ -96,6 +97,12 @@ impl VapLoader {
                 }
 
                 SkillRegisterMessage::RegisterIntents(msg) => {
+                    // A skill that restarted without closing would have left
+                    // it's old intents behind
+                    if let Err(e) = unregister_skill(&msg.skill_id) {
+                        error!("Failed to clean up skill \"{}\": {}", &msg.skill_id, e);
+                    }
+
                     let (actions, entities) = self.transform(msg.nlu_data);
                     match register_skill(&msg.skill_id, actions, vec![], vec![], entities) {
                         Ok(()) => Response {
@@ -113,8 +120,12 @@ impl VapLoader {
                     }
                 }
 
-                SkillRegisterMessage::Close(_) => {
-                    // TODO! We should unregister the skill from the NLU
+                SkillRegisterMessage::Close(msg) => {
+                    // The skill is leaving anyway, it doesn't care about errors
+                    if let Err(e) = unregister_skill(&msg.skill_id) {
+                        error!("Failed to unregister skill \"{}\": {}", &msg.skill_id, e);
+                    }
+
                     Response {
                         status: ResponseType::Valid,
                         payload: Vec::new(),
@@ -365,9 +376,11 @@ impl Action for VapAction {
             .unwrap()
             .slots
             .iter()
+            // VAP only has text, dates and durations go in the format
+            // timers understand
             .map(|(n, v)| RequestSlot {
                 name: n.clone(),
-                value: Some(v.clone()),
+                value: Some(v.to_string()),
             })
             .collect();
 
@@ -5,13 +5,12 @@ pub const STT_DATA_PATH: PathRef = PathRef::own("stt");
 #[cfg(feature = "deepspeech_stt")]
 pub const DEEPSPEECH_DATA_PATH: PathRef = PathRef::own("stt/deepspeech");
 pub const PICO_DATA_PATH: PathRef = PathRef::own("tts");
+pub const SKILLS_PATH: PathRef = PathRef::own("skills");
 
-#[cfg(not(feature = "devel_rasa_nlu"))]
 pub const NLU_ENGINE_PATH: PathRef = PathRef::user_cfg("data/nlu/engine");
-#[cfg(not(feature = "devel_rasa_nlu"))]
 pub const NLU_TRAIN_SET_PATH: PathRef = PathRef::user_cfg("data/nlu/train-set.json");
-#[cfg(feature = "devel_rasa_nlu")]
 pub const NLU_RASA_PATH: PathRef = PathRef::user_cfg("data/nlu/rasa");
+pub const NLU_RASA_SERVER_PATH: PathRef = PathRef::user_cfg("data/nlu/rasa_server");
 
 #[cfg(debug_assertions)]
 pub const PS_LOG_PATH: PathRef = PathRef::user_cfg("logs/pocketsphinx.log");
@@ -19,6 +18,13 @@ pub const PS_LOG_PATH: PathRef = PathRef::user_cfg("logs/pocketsphinx.log");
 pub const PS_LOG_PATH: PathRef = PathRef::user_cfg("logs/pocketsphinx.log");
 
 pub const MAIN_CONF_PATH: PathRef = PathRef::user_cfg("conf.yaml");
+pub const TIMERS_PATH: PathRef = PathRef::user_cfg("data/timers.json");
+
+// Skill files
+pub const SKILL_MODEL_FILE: &str = "model.yaml";
+pub const SKILL_TRANSLATIONS_DIR: &str = "translations";
+pub const SKILL_TRANSLATIONS_FILE: &str = "translations.ftl";
+pub const HERMES_DATASET_FILE: &str = "dataset.yaml";
 
 // Messages
 pub const NO_COMPATIBLE_LANG_MSG: &str =
@@ -36,8 +42,43 @@ pub const POISON_MSG: &str = "A shared lock had a panic in another thread";
 // Other
 pub const MIN_SCORE_FOR_ACTION: f32 = 0.3;
 pub const NLU_TRAINING_DELAY: u64 = 1000;
+pub const MAX_SLOT_FILLING_TRIES: u8 = 2;
+pub const SLOT_FILLING_TIMEOUT: u64 = 30000;
+pub const FOLLOW_UP_TIMEOUT: u64 = 30000;
+pub const DEFAULT_HERMES_ACTION_TIMEOUT: u64 = 10000;
+pub const HERMES_LISTEN_TIMEOUT: u64 = 8000;
+pub const HERMES_MAX_UTTERANCE: u64 = 15000;
+pub const HERMES_END_SILENCE: f32 = 0.8;
+pub const HERMES_PRE_ROLL_SAMPLES: usize = 4800;
+pub const HERMES_VAD_MIN_RMS: f64 = 300.0;
+pub const HERMES_VAD_NOISE_RATIO: f64 = 3.0;
+pub const MISSED_TIMERS_DELAY: u64 = 10000;
+pub const SNOOZE_WINDOW: i64 = 600;
+pub const DEFAULT_SNOOZE: u64 = 300;
+pub const DEFAULT_POLL_INTERVAL: u64 = 30;
+pub const DEFAULT_WEBHOOK_PORT: u16 = 8778;
+pub const WEBHOOK_MAX_BODY: usize = 65536;
+pub const DEFAULT_FS_DEBOUNCE: u64 = 500;
+pub const SHUTDOWN_TIMEOUT: u64 = 5000;
+pub const QUERY_TIMEOUT: u64 = 10;
+pub const SHUTDOWN_ANSWERS_WAIT: u64 = 1000;
 pub const DEFAULT_COAP_PORT: u16 = 5683;
+pub const DEFAULT_RASA_SERVER_URL: &str = "http://localhost:5005";
 
 pub fn mangle(skill_name: &str, intent_name: &str) -> String {
     format!("__{}__{}", skill_name, intent_name)
 }
+
+/// Get back the name of the item, skill names are expected not to have "__"
+pub fn demangle(mangled: &str) -> &str {
+    mangled
+        .strip_prefix("__")
+        .and_then(|s| s.split_once("__"))
+        .map(|(_, name)| name)
+        .unwrap_or(mangled)
+}
+
+/// Whether a mangled name belongs to that skill
+pub fn is_mangled_from(skill_name: &str, mangled: &str) -> bool {
+    mangled.starts_with(&mangle(skill_name, ""))
+}